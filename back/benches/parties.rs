@@ -0,0 +1,108 @@
+//! Throughput of the back server with many concurrent parties.
+//!
+//! Every party is played by two in-process clients over real TCP sockets
+//! against a `Controller` bound to an ephemeral port. Parties are paired one
+//! by one, then all of them play their rounds at the same time.
+//!
+//! Run with `cargo bench`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use prisoner_dilemma::controller::Controller;
+use settings::{PlayStatus, Protocol, Settings, Status};
+use std::net::SocketAddr;
+use std::time::Instant;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+const ROUNDS: u32 = 50;
+const BET: u32 = 10;
+const PARTIES: [usize; 4] = [1, 10, 100, 500];
+
+struct Client {
+    stream: TcpStream,
+    protocol: Protocol,
+}
+
+impl Client {
+    async fn connect(addr: SocketAddr) -> Self {
+        let mut client = Self {
+            stream: TcpStream::connect(addr).await.unwrap(),
+            protocol: Protocol::default(),
+        };
+        client.send(Status::Init, PlayStatus::Stanby).await;
+        client.receive().await;
+        client
+    }
+
+    async fn send(&mut self, status: Status, play: PlayStatus) {
+        self.protocol.party_status = status;
+        self.protocol.play = play;
+        let bytes = self.protocol.to_bytes();
+        self.stream.write_all(&bytes).await.unwrap();
+    }
+
+    async fn receive(&mut self) {
+        self.protocol = Controller::read_protocol(&mut self.stream).await.unwrap();
+    }
+}
+
+async fn pair(addr: SocketAddr) -> (Client, Client) {
+    let mut creator = Client::connect(addr).await;
+    let mut joiner = Client::connect(addr).await;
+
+    creator.protocol.total_round = ROUNDS;
+    creator.protocol.bet = BET;
+    creator.send(Status::Created, PlayStatus::Stanby).await;
+    creator.receive().await;
+
+    joiner.send(Status::JoinParty, PlayStatus::Stanby).await;
+    joiner.receive().await;
+    creator.receive().await;
+    (creator, joiner)
+}
+
+async fn play(mut creator: Client, mut joiner: Client) {
+    for _ in 0..ROUNDS {
+        creator.send(Status::Started, PlayStatus::Cooperate).await;
+        joiner.send(Status::Started, PlayStatus::Betrail).await;
+        creator.receive().await;
+        joiner.receive().await;
+    }
+}
+
+async fn run(addr: SocketAddr, parties: usize) {
+    let mut pairs = Vec::with_capacity(parties);
+    for _ in 0..parties {
+        pairs.push(pair(addr).await);
+    }
+
+    let start = Instant::now();
+    let tasks: Vec<_> = pairs
+        .into_iter()
+        .map(|(creator, joiner)| tokio::spawn(play(creator, joiner)))
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let elapsed = start.elapsed();
+
+    let rounds = parties as f64 * ROUNDS as f64;
+    println!(
+        "{:>6} parties | {:>8} rounds | {:>9.2?} | {:>10.0} rounds/s",
+        parties,
+        rounds,
+        elapsed,
+        rounds / elapsed.as_secs_f64()
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let addr = common::start(Settings::default()).await;
+
+    for parties in PARTIES {
+        run(addr, parties).await;
+    }
+}
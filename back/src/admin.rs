@@ -0,0 +1,269 @@
+use crate::archive::Archive;
+use crate::export;
+use crate::lobby::Lobby;
+use crate::metrics::Metrics;
+use settings::{Level, Log, Party, Settings, Status};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+const HELP: &str = "Commands:
+  players               list connected players
+  parties               list waiting, running and finished parties
+  party <id>            show the rounds of a party
+  kick <player id>      disconnect a player
+  cancel <party id>     cancel a party
+  broadcast <message>   send a message to every player
+  loglevel [level]      show or set the log level (DEBUG, INFO, WARN, ERROR)
+  exports               list the last failed party exports
+  exportall             export every archived party to one file
+  help                  show this help";
+
+/// Operator commands run against the lobby of a running server, read from
+/// the standard input and from an optional Unix socket.
+#[derive(Clone)]
+pub struct Admin {
+    lobby: Lobby,
+    metrics: Arc<Metrics>,
+    settings: Arc<Settings>,
+}
+
+impl Admin {
+    pub fn new(lobby: Lobby, metrics: Arc<Metrics>, settings: Arc<Settings>) -> Self {
+        Self {
+            lobby,
+            metrics,
+            settings,
+        }
+    }
+
+    /// Starts the consoles enabled in the settings.
+    pub fn spawn(settings: &Settings, lobby: Lobby, metrics: Arc<Metrics>) {
+        let admin = Admin::new(lobby, metrics, Arc::new(settings.clone()));
+        if settings.admin_console {
+            tokio::spawn(admin.clone().serve_stdin());
+        }
+        #[cfg(unix)]
+        if !settings.admin_socket.is_empty() {
+            tokio::spawn(admin.serve_socket(settings.admin_socket.clone()));
+        }
+    }
+
+    /// Reading stdin blocks, so lines are read on a plain thread which does
+    /// not keep the runtime alive on shutdown.
+    async fn serve_stdin(self) {
+        let (sender, mut receiver) = mpsc::channel::<String>(16);
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.blocking_send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        while let Some(line) = receiver.recv().await {
+            let answer = self.execute(&line).await;
+            if !answer.is_empty() {
+                println!("{}", answer);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    async fn serve_socket(self, path: String) {
+        let _ = std::fs::remove_file(&path);
+        let listener = match tokio::net::UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                Log::error(format!("admin socket {}: {:?}", path, e));
+                return;
+            }
+        };
+        Log::info(format!("Admin console listening on {}", path));
+
+        while let Ok((stream, _)) = listener.accept().await {
+            let admin = self.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let mut answer = admin.execute(&line).await;
+                    answer.push('\n');
+                    if writer.write_all(answer.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    pub async fn execute(&self, line: &str) -> String {
+        let line = line.trim();
+        let (command, argument) = match line.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (line, ""),
+        };
+        match command {
+            "" => String::new(),
+            "players" => self.players().await,
+            "parties" => self.parties().await,
+            "party" => match argument.parse() {
+                Ok(party_id) => self.party(party_id).await,
+                Err(_) => String::from("Usage: party <id>"),
+            },
+            "kick" => match argument.parse() {
+                Ok(player_id) if self.lobby.kick(player_id).await => {
+                    format!("Player #{} kicked", player_id)
+                }
+                Ok(player_id) => format!("No player #{}", player_id),
+                Err(_) => String::from("Usage: kick <player id>"),
+            },
+            "cancel" => match argument.parse() {
+                Ok(party_id) if self.lobby.cancel(party_id).await => {
+                    format!("Party #{} cancelled", party_id)
+                }
+                Ok(party_id) => format!("No party #{}", party_id),
+                Err(_) => String::from("Usage: cancel <party id>"),
+            },
+            "broadcast" if !argument.is_empty() => {
+                self.lobby.broadcast(argument.to_string()).await;
+                String::from("Message sent")
+            }
+            "broadcast" => String::from("Usage: broadcast <message>"),
+            "loglevel" if argument.is_empty() => format!("Log level is {}", Log::level()),
+            "loglevel" => match argument.parse::<Level>() {
+                Ok(level) => {
+                    Log::set_level(level);
+                    format!("Log level set to {}", level)
+                }
+                Err(_) => format!("Unknown level {}, use DEBUG, INFO, WARN or ERROR", argument),
+            },
+            "exports" => self.exports(),
+            "exportall" => self.export_all().await,
+            "help" => String::from(HELP),
+            _ => format!("Unknown command {}, type help", command),
+        }
+    }
+
+    async fn players(&self) -> String {
+        let Some(snapshot) = self.lobby.snapshot().await else {
+            return String::from("Lobby is not running");
+        };
+        let mut party_of = HashMap::new();
+        for handle in snapshot.parties.iter() {
+            if let Some(party) = handle.inspect().await {
+                party_of.insert(party.player1.id, (party.id, party.status.clone()));
+                party_of.insert(party.player2.id, (party.id, party.status));
+            }
+        }
+
+        let mut lines = vec![format!("{} player(s)", snapshot.players.len())];
+        for player_id in snapshot.players {
+            lines.push(match party_of.get(&player_id) {
+                Some((party_id, status)) => {
+                    format!("player #{}  party #{} ({:?})", player_id, party_id, status)
+                }
+                None => format!("player #{}  no party", player_id),
+            });
+        }
+        lines.join("\n")
+    }
+
+    async fn parties(&self) -> String {
+        let parties = self.lobby.parties().await;
+        let mut lines = vec![format!("{} party(ies)", parties.len())];
+        for party in parties.iter() {
+            lines.push(Admin::describe(party));
+        }
+        lines.join("\n")
+    }
+
+    async fn party(&self, party_id: u32) -> String {
+        let parties = self.lobby.parties().await;
+        let Some(party) = parties.iter().find(|party| party.id == party_id) else {
+            return format!("No party #{}", party_id);
+        };
+
+        let mut lines = vec![Admin::describe(party)];
+        for (index, round) in party.party_round.round_played.iter().enumerate() {
+            if round.0 .0.id == 0 && round.1 .0.id == 0 {
+                continue;
+            }
+            lines.push(format!(
+                "round {}: #{} {} {} | #{} {} {}",
+                index + 1,
+                round.0 .0.id,
+                round.0 .1.to_string(),
+                round.0 .0.money,
+                round.1 .0.id,
+                round.1 .1.to_string(),
+                round.1 .0.money,
+            ));
+        }
+        lines.join("\n")
+    }
+
+    fn exports(&self) -> String {
+        let failures = self.metrics.export_failures();
+        let mut lines = vec![format!("{} failed export(s)", failures.len())];
+        for failure in failures {
+            lines.push(format!("party #{}  {}", failure.party_id, failure.error));
+        }
+        lines.join("\n")
+    }
+
+    /// Parties of the storage file when there is one, as it also holds those
+    /// dropped from memory, else the parties archived by the lobby.
+    async fn export_all(&self) -> String {
+        let settings = Arc::clone(&self.settings);
+        let archived: Vec<Party> = match settings.storage_path.as_str() {
+            "" => self
+                .lobby
+                .parties()
+                .await
+                .into_iter()
+                .filter(|party| party.status == Status::Finished)
+                .collect(),
+            _ => Vec::new(),
+        };
+        // Reading the storage file and writing the export both block
+        let exported = tokio::task::spawn_blocking(move || {
+            let parties = match settings.storage_path.as_str() {
+                "" => archived,
+                path => Archive::load(std::path::Path::new(path))
+                    .map_err(|e| format!("Cannot read {}: {}", path, e))?,
+            };
+            export::export_all(&settings, &parties)
+                .map(|path| (parties.len(), path))
+                .map_err(|e| format!("Export failed: {}", e))
+        })
+        .await;
+        match exported {
+            Ok(Ok((count, path))) => format!("{} party(ies) exported to {}", count, path.display()),
+            Ok(Err(message)) => message,
+            Err(e) => format!("Export failed: {}", e),
+        }
+    }
+
+    fn describe(party: &Party) -> String {
+        let round = match party.status {
+            Status::Finished => party.total_round,
+            _ => party.round.min(party.total_round),
+        };
+        format!(
+            "party #{}  {:?}  round {}/{}  bet {}  #{} ({}) vs #{} ({})",
+            party.id,
+            party.status,
+            round,
+            party.total_round,
+            party.bet,
+            party.player1.id,
+            party.player1.money,
+            party.player2.id,
+            party.player2.money,
+        )
+    }
+}
@@ -0,0 +1,478 @@
+use crate::export::{self, ExportError, RoundRecord};
+use rust_xlsxwriter::*;
+use serde_json::{json, Map, Value};
+use settings::{ExportFormat, Log, Settings};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Name of an aggregate under `export_dir`, placeholders as in `export_file`.
+const AGGREGATE_FILE: &str = "aggregate_{date}_{time}.{ext}";
+/// Columns of the CSV and JSON Lines aggregates, one metric per row.
+const COLUMNS: [&str; 5] = ["table", "player_id", "opponent_id", "metric", "value"];
+const PERCENT_FORMAT: &str = "0.0%";
+const MONEY_FORMAT: &str = "#,##0.00;[Red]-#,##0.00";
+
+/// Results of a player over every party.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerTotals {
+    pub player_id: u32,
+    pub parties: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    /// Parties cancelled or interrupted before their last round.
+    pub unfinished: u32,
+    pub rounds: u32,
+    pub cooperations: u32,
+    /// Sum of the final balances of the player's parties.
+    pub balance: f64,
+}
+
+/// Results of a player against one opponent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeadToHead {
+    pub player_id: u32,
+    pub opponent_id: u32,
+    pub parties: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub balance: f64,
+}
+
+/// Moves of both players over every round.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cooperation {
+    pub rounds: u32,
+    pub mutual_cooperations: u32,
+    pub mutual_betrayals: u32,
+    /// Rounds in which exactly one player betrayed.
+    pub exploitations: u32,
+    /// Cooperating moves over all moves, by round number (index 0 is round 1).
+    pub by_round: Vec<(u32, u32)>,
+}
+
+impl Cooperation {
+    pub fn rate(&self) -> f64 {
+        rate(
+            2 * self.mutual_cooperations + self.exploitations,
+            2 * self.rounds,
+        )
+    }
+}
+
+/// Statistics over a set of parties, built from export rows so that both the
+/// storage file and previous exports can feed it.
+///
+/// Parties are told apart by their creation time and id, as every server
+/// run draws ids anew. Player ids do not persist either: a player gets a new
+/// id at each connection, so totals are per connection, not per person.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Aggregate {
+    pub players: Vec<PlayerTotals>,
+    pub head_to_head: Vec<HeadToHead>,
+    pub cooperation: Cooperation,
+}
+
+fn rate(count: u32, total: u32) -> f64 {
+    match total {
+        0 => 0.0,
+        total => count as f64 / total as f64,
+    }
+}
+
+impl Aggregate {
+    pub fn new(records: &[RoundRecord]) -> Self {
+        let mut parties: BTreeMap<(u64, u32), Vec<&RoundRecord>> = BTreeMap::new();
+        for record in records {
+            parties
+                .entry((record.party_created, record.party_id))
+                .or_default()
+                .push(record);
+        }
+
+        let mut players: BTreeMap<u32, PlayerTotals> = BTreeMap::new();
+        let mut head_to_head: BTreeMap<(u32, u32), HeadToHead> = BTreeMap::new();
+        let mut cooperation = Cooperation::default();
+        for rounds in parties.values_mut() {
+            rounds.sort_by_key(|record| record.round);
+            let last = rounds[rounds.len() - 1];
+            let finished = last.party_status == "Finished";
+            let sides = [
+                (last.player1_id, last.player2_id, last.player1_balance),
+                (last.player2_id, last.player1_id, last.player2_balance),
+            ];
+            for (player_id, opponent_id, balance) in sides {
+                let won = last.winner_id == player_id;
+                let lost = last.winner_id == opponent_id;
+                let drawn = finished && last.winner_id == 0;
+
+                let totals = players.entry(player_id).or_insert_with(|| PlayerTotals {
+                    player_id,
+                    ..Default::default()
+                });
+                totals.parties += 1;
+                totals.wins += won as u32;
+                totals.losses += lost as u32;
+                totals.draws += drawn as u32;
+                totals.unfinished += (!won && !lost && !drawn) as u32;
+                totals.balance += balance;
+
+                let versus = head_to_head
+                    .entry((player_id, opponent_id))
+                    .or_insert_with(|| HeadToHead {
+                        player_id,
+                        opponent_id,
+                        ..Default::default()
+                    });
+                versus.parties += 1;
+                versus.wins += won as u32;
+                versus.losses += lost as u32;
+                versus.draws += drawn as u32;
+                versus.balance += balance;
+            }
+
+            for record in rounds.iter() {
+                let cooperated = [
+                    (record.player1_id, record.player1_play == "Cooperate"),
+                    (record.player2_id, record.player2_play == "Cooperate"),
+                ];
+                for (player_id, cooperated) in cooperated {
+                    if let Some(totals) = players.get_mut(&player_id) {
+                        totals.rounds += 1;
+                        totals.cooperations += cooperated as u32;
+                    }
+                }
+
+                let cooperations = cooperated.iter().filter(|(_, c)| *c).count() as u32;
+                cooperation.rounds += 1;
+                match cooperations {
+                    2 => cooperation.mutual_cooperations += 1,
+                    1 => cooperation.exploitations += 1,
+                    _ => cooperation.mutual_betrayals += 1,
+                }
+                let index = record.round.max(1) as usize - 1;
+                if cooperation.by_round.len() <= index {
+                    cooperation.by_round.resize(index + 1, (0, 0));
+                }
+                cooperation.by_round[index].0 += cooperations;
+                cooperation.by_round[index].1 += 2;
+            }
+        }
+
+        Self {
+            players: players.into_values().collect(),
+            head_to_head: head_to_head.into_values().collect(),
+            cooperation,
+        }
+    }
+
+    /// One metric per row: `table` is `players`, `head_to_head`,
+    /// `cooperation` or `cooperation_by_round` (with the round as
+    /// `opponent_id`), ids are empty where they do not apply.
+    pub fn rows(&self) -> Vec<[Value; 5]> {
+        let mut rows = Vec::new();
+        for totals in self.players.iter() {
+            let metrics = [
+                ("parties", json!(totals.parties)),
+                ("wins", json!(totals.wins)),
+                ("losses", json!(totals.losses)),
+                ("draws", json!(totals.draws)),
+                ("unfinished", json!(totals.unfinished)),
+                ("rounds", json!(totals.rounds)),
+                ("cooperations", json!(totals.cooperations)),
+                (
+                    "cooperation_rate",
+                    json!(rate(totals.cooperations, totals.rounds)),
+                ),
+                ("balance", json!(totals.balance)),
+            ];
+            for (metric, value) in metrics {
+                rows.push([
+                    json!("players"),
+                    json!(totals.player_id),
+                    Value::Null,
+                    json!(metric),
+                    value,
+                ]);
+            }
+        }
+        for versus in self.head_to_head.iter() {
+            let metrics = [
+                ("parties", versus.parties),
+                ("wins", versus.wins),
+                ("losses", versus.losses),
+                ("draws", versus.draws),
+            ];
+            let metrics = metrics
+                .into_iter()
+                .map(|(metric, value)| (metric, json!(value)))
+                .chain([("balance", json!(versus.balance))]);
+            for (metric, value) in metrics {
+                rows.push([
+                    json!("head_to_head"),
+                    json!(versus.player_id),
+                    json!(versus.opponent_id),
+                    json!(metric),
+                    value,
+                ]);
+            }
+        }
+        let cooperation = &self.cooperation;
+        let metrics = [
+            ("rounds", json!(cooperation.rounds)),
+            (
+                "mutual_cooperations",
+                json!(cooperation.mutual_cooperations),
+            ),
+            ("mutual_betrayals", json!(cooperation.mutual_betrayals)),
+            ("exploitations", json!(cooperation.exploitations)),
+            ("cooperation_rate", json!(cooperation.rate())),
+        ];
+        for (metric, value) in metrics {
+            rows.push([
+                json!("cooperation"),
+                Value::Null,
+                Value::Null,
+                json!(metric),
+                value,
+            ]);
+        }
+        for (index, (cooperations, moves)) in cooperation.by_round.iter().enumerate() {
+            rows.push([
+                json!("cooperation_by_round"),
+                Value::Null,
+                json!(index + 1),
+                json!("cooperation_rate"),
+                json!(rate(*cooperations, *moves)),
+            ]);
+        }
+        rows
+    }
+
+    /// Writes the aggregate to `export_dir` in `export_format`: a workbook
+    /// with one sheet per table, or one metric per row in CSV and JSON Lines.
+    pub fn export(&self, settings: &Settings) -> Result<PathBuf, ExportError> {
+        let format = settings.export_format;
+        let name = export::fill(AGGREGATE_FILE, SystemTime::now(), format.as_str());
+        let path = export::create_path(settings, &name)?;
+        match format {
+            ExportFormat::Xlsx => self
+                .write_xlsx(&path)
+                .map_err(|e| ExportError::Xlsx(path.clone(), e))?,
+            ExportFormat::Csv | ExportFormat::Jsonl => self
+                .write_text(&path, format)
+                .map_err(|e| ExportError::Io(path.clone(), e))?,
+        }
+        Ok(path)
+    }
+
+    fn write_text(&self, path: &Path, format: ExportFormat) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        if format == ExportFormat::Csv {
+            writeln!(file, "{}", COLUMNS.join(","))?;
+        }
+        for row in self.rows() {
+            match format {
+                ExportFormat::Csv => writeln!(file, "{}", export::csv::line(&row))?,
+                _ => {
+                    let object: Map<String, Value> = COLUMNS
+                        .iter()
+                        .map(|column| column.to_string())
+                        .zip(row)
+                        .collect();
+                    writeln!(file, "{}", Value::Object(object))?;
+                }
+            }
+        }
+        file.flush()
+    }
+
+    fn write_xlsx(&self, path: &Path) -> Result<(), XlsxError> {
+        let bold = Format::new().set_bold();
+        let percent = Format::new().set_num_format(PERCENT_FORMAT);
+        let money = Format::new().set_num_format(MONEY_FORMAT);
+        let mut workbook = Workbook::new();
+
+        let sheet = workbook.add_worksheet().set_name("Players")?;
+        let headers = [
+            "Player",
+            "Parties",
+            "Wins",
+            "Losses",
+            "Draws",
+            "Unfinished",
+            "Rounds",
+            "Cooperation rate",
+            "Total balance",
+        ];
+        for (col, header) in headers.iter().enumerate() {
+            sheet.write_with_format(0, col as u16, *header, &bold)?;
+            sheet.set_column_width(col as u16, 14)?;
+        }
+        sheet.set_freeze_panes(1, 0)?;
+        for (index, totals) in self.players.iter().enumerate() {
+            let row = index as u32 + 1;
+            sheet.write(row, 0, totals.player_id)?;
+            sheet.write(row, 1, totals.parties)?;
+            sheet.write(row, 2, totals.wins)?;
+            sheet.write(row, 3, totals.losses)?;
+            sheet.write(row, 4, totals.draws)?;
+            sheet.write(row, 5, totals.unfinished)?;
+            sheet.write(row, 6, totals.rounds)?;
+            sheet.write_with_format(row, 7, rate(totals.cooperations, totals.rounds), &percent)?;
+            sheet.write_with_format(row, 8, totals.balance, &money)?;
+        }
+
+        let sheet = workbook.add_worksheet().set_name("Head to head")?;
+        let headers = [
+            "Player", "Opponent", "Parties", "Wins", "Losses", "Draws", "Balance",
+        ];
+        for (col, header) in headers.iter().enumerate() {
+            sheet.write_with_format(0, col as u16, *header, &bold)?;
+            sheet.set_column_width(col as u16, 12)?;
+        }
+        sheet.set_freeze_panes(1, 0)?;
+        for (index, versus) in self.head_to_head.iter().enumerate() {
+            let row = index as u32 + 1;
+            sheet.write(row, 0, versus.player_id)?;
+            sheet.write(row, 1, versus.opponent_id)?;
+            sheet.write(row, 2, versus.parties)?;
+            sheet.write(row, 3, versus.wins)?;
+            sheet.write(row, 4, versus.losses)?;
+            sheet.write(row, 5, versus.draws)?;
+            sheet.write_with_format(row, 6, versus.balance, &money)?;
+        }
+
+        let sheet = workbook.add_worksheet().set_name("Cooperation")?;
+        sheet.set_column_width(0, 22)?;
+        sheet.set_column_width(1, 16)?;
+        let cooperation = &self.cooperation;
+        let counts = [
+            ("Rounds", cooperation.rounds),
+            ("Mutual cooperations", cooperation.mutual_cooperations),
+            ("Mutual betrayals", cooperation.mutual_betrayals),
+            ("Exploitations", cooperation.exploitations),
+        ];
+        for (row, (label, count)) in counts.iter().enumerate() {
+            sheet.write_with_format(row as u32, 0, *label, &bold)?;
+            sheet.write(row as u32, 1, *count)?;
+        }
+        sheet.write_with_format(4, 0, "Cooperation rate", &bold)?;
+        sheet.write_with_format(4, 1, cooperation.rate(), &percent)?;
+        sheet.write_with_format(6, 0, "Round", &bold)?;
+        sheet.write_with_format(6, 1, "Cooperation rate", &bold)?;
+        for (index, (cooperations, moves)) in cooperation.by_round.iter().enumerate() {
+            let row = index as u32 + 7;
+            sheet.write(row, 0, index as u32 + 1)?;
+            sheet.write_with_format(row, 1, rate(*cooperations, *moves), &percent)?;
+        }
+
+        workbook.save(path)?;
+        Ok(())
+    }
+}
+
+/// Rows of the CSV and JSON Lines exports found in a directory. Files of
+/// another schema, such as aggregates, are skipped; workbooks are not read,
+/// and are named in a warning. A round found in several files is counted
+/// once. Fails when no export could be read, e.g. in a directory of
+/// workbooks only.
+pub fn read_exports(dir: &Path) -> io::Result<Vec<RoundRecord>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    paths.sort();
+
+    let mut records = BTreeMap::new();
+    let mut workbooks = Vec::new();
+    for path in paths {
+        let read = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => read_csv(&path),
+            Some("jsonl") => read_jsonl(&path),
+            Some("xlsx") => {
+                workbooks.push(path.display().to_string());
+                continue;
+            }
+            _ => continue,
+        };
+        match read {
+            Ok(read) => {
+                for record in read {
+                    let key = (record.party_created, record.party_id, record.round);
+                    records.insert(key, record);
+                }
+            }
+            Err(e) => Log::debug(format!("skipping {}: {}", path.display(), e)),
+        }
+    }
+
+    let skipped = format!(
+        "workbooks are not read, set storage_path or export to csv or jsonl: {}",
+        workbooks.join(", ")
+    );
+    if records.is_empty() {
+        let mut message = format!("no CSV or JSON Lines party export in {}", dir.display());
+        if !workbooks.is_empty() {
+            message = format!("{} ({})", message, skipped);
+        }
+        return Err(io::Error::new(io::ErrorKind::NotFound, message));
+    }
+    if !workbooks.is_empty() {
+        Log::warn(skipped);
+    }
+    Ok(records.into_values().collect())
+}
+
+fn invalid(path: &Path, line: usize, error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} line {}: {}", path.display(), line, error),
+    )
+}
+
+fn read_jsonl(path: &Path) -> io::Result<Vec<RoundRecord>> {
+    let mut records = Vec::new();
+    for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            records.push(serde_json::from_str(&line).map_err(|e| invalid(path, index + 1, e))?);
+        }
+    }
+    Ok(records)
+}
+
+fn read_csv(path: &Path) -> io::Result<Vec<RoundRecord>> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    if header != RoundRecord::COLUMNS.join(",") {
+        return Err(invalid(path, 1, "not a party export"));
+    }
+    let mut records = Vec::new();
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let object: Map<String, Value> = RoundRecord::COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .zip(export::csv::fields(&line).into_iter().map(|field| {
+                if let Ok(number) = field.parse::<u64>() {
+                    json!(number)
+                } else if let Ok(number) = field.parse::<f64>() {
+                    json!(number)
+                } else {
+                    json!(field)
+                }
+            }))
+            .collect();
+        let record = serde_json::from_value(Value::Object(object))
+            .map_err(|e| invalid(path, index + 2, e))?;
+        records.push(record);
+    }
+    Ok(records)
+}
@@ -0,0 +1,291 @@
+use crate::lobby::Lobby;
+use crate::metrics::Metrics;
+use serde_json::{json, Value};
+use settings::{Log, Party, PlayStatus, RoundPlay, Settings, Status};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Largest request head read before giving up on a client.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+/// Time a client has to send its request head.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Read-only JSON API over HTTP, for dashboards.
+///
+/// Every answer is built from the lobby, so it shows the same players and
+/// parties as the game server. Only `GET` is served:
+///
+/// - `/players`: connected players and their current party
+/// - `/parties`: every party, `?status=waiting|running|finished` to filter
+/// - `/parties/<id>`: one party with its round history
+/// - `/stats`: aggregate numbers over every known party
+///
+/// `/metrics` serves the server metrics in the Prometheus text format.
+#[derive(Clone)]
+pub struct Api {
+    lobby: Lobby,
+    metrics: Arc<Metrics>,
+}
+
+impl Api {
+    pub fn new(lobby: Lobby, metrics: Arc<Metrics>) -> Self {
+        Self { lobby, metrics }
+    }
+
+    /// Starts the API on `http_port` if it is set.
+    pub async fn spawn(settings: &Settings, lobby: Lobby, metrics: Arc<Metrics>) {
+        if settings.http_port == 0 {
+            return;
+        }
+        let address = format!("{}:{}", settings.host, settings.http_port);
+        match TcpListener::bind(&address).await {
+            Ok(listener) => {
+                Log::info(format!("HTTP API listening on {}", address));
+                tokio::spawn(Api::new(lobby, metrics).serve(listener));
+            }
+            Err(e) => Log::error(format!("HTTP API on {}: {:?}", address, e)),
+        }
+    }
+
+    pub async fn serve(self, listener: TcpListener) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let api = self.clone();
+                    tokio::spawn(async move { api.handle_connection(stream).await });
+                }
+                Err(e) => Log::error(format!("HTTP API accept: {:?}", e)),
+            }
+        }
+    }
+
+    /// Answers a single request, then closes the connection.
+    async fn handle_connection(&self, stream: TcpStream) {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader.take(MAX_REQUEST_SIZE as u64));
+        let request_line =
+            match tokio::time::timeout(REQUEST_TIMEOUT, Api::read_head(&mut reader)).await {
+                Ok(Some(request_line)) => request_line,
+                _ => return,
+            };
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let target = parts.next().unwrap_or_default();
+        let (status, content_type, body) = match (method, target) {
+            ("GET", "/metrics") => (200, METRICS_CONTENT_TYPE, self.metrics.render()),
+            _ => {
+                let (status, body) = self.respond(method, target).await;
+                (status, "application/json", body.to_string())
+            }
+        };
+
+        let reason = match status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            _ => "Method Not Allowed",
+        };
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason,
+            content_type,
+            body.len(),
+            body
+        );
+        if let Err(e) = writer.write_all(response.as_bytes()).await {
+            Log::warn(format!("HTTP API write: {:?}", e));
+        }
+    }
+
+    /// First line of a request head, `None` if the client went away or the
+    /// head does not fit in `MAX_REQUEST_SIZE`.
+    async fn read_head(reader: &mut (impl AsyncBufRead + Unpin)) -> Option<String> {
+        let mut request_line = String::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await.ok()? == 0 {
+                return None;
+            }
+            if line.trim_end().is_empty() {
+                return Some(request_line);
+            }
+            if request_line.is_empty() {
+                request_line = line;
+            }
+        }
+    }
+
+    /// Status code and JSON body answering `method` on `target`.
+    pub async fn respond(&self, method: &str, target: &str) -> (u16, Value) {
+        if method != "GET" {
+            return (405, json!({ "error": "only GET is supported" }));
+        }
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        match segments.as_slice() {
+            ["players"] => (200, self.players().await),
+            ["parties"] => {
+                let filter = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("status="))
+                    .unwrap_or("");
+                match filter {
+                    "" | "waiting" | "running" | "finished" => (200, self.parties(filter).await),
+                    _ => (
+                        400,
+                        json!({ "error": format!("unknown status {}", filter) }),
+                    ),
+                }
+            }
+            ["parties", id] => match id.parse::<u32>() {
+                Ok(party_id) => self.party(party_id).await,
+                Err(_) => (400, json!({ "error": format!("invalid party id {}", id) })),
+            },
+            ["stats"] => (200, self.stats().await),
+            _ => (404, json!({ "error": format!("no route for {}", path) })),
+        }
+    }
+
+    async fn players(&self) -> Value {
+        let Some(snapshot) = self.lobby.snapshot().await else {
+            return json!([]);
+        };
+        let mut party_of = HashMap::new();
+        for party in self.lobby.parties().await {
+            if party.status != Status::Finished {
+                party_of.insert(party.player1.id, party.id);
+                party_of.insert(party.player2.id, party.id);
+            }
+        }
+        let mut players = snapshot.players;
+        players.sort_unstable();
+        Value::Array(
+            players
+                .into_iter()
+                .map(|id| json!({ "id": id, "party_id": party_of.get(&id) }))
+                .collect(),
+        )
+    }
+
+    async fn parties(&self, filter: &str) -> Value {
+        Value::Array(
+            self.lobby
+                .parties()
+                .await
+                .iter()
+                .filter(|party| filter.is_empty() || Api::state(party) == filter)
+                .map(Api::summary)
+                .collect(),
+        )
+    }
+
+    async fn party(&self, party_id: u32) -> (u16, Value) {
+        let parties = self.lobby.parties().await;
+        let Some(party) = parties.iter().find(|party| party.id == party_id) else {
+            return (404, json!({ "error": format!("no party {}", party_id) }));
+        };
+
+        let mut detail = Api::summary(party);
+        detail["rounds"] = Value::Array(
+            Api::played_rounds(party)
+                .map(|(round, (first, second))| {
+                    json!({
+                        "round": round,
+                        "player1": Api::round_play(first),
+                        "player2": Api::round_play(second),
+                    })
+                })
+                .collect(),
+        );
+        (200, detail)
+    }
+
+    async fn stats(&self) -> Value {
+        let players = match self.lobby.snapshot().await {
+            Some(snapshot) => snapshot.players.len(),
+            None => 0,
+        };
+        let parties = self.lobby.parties().await;
+        let count = |state: &str| {
+            parties
+                .iter()
+                .filter(|party| Api::state(party) == state)
+                .count()
+        };
+
+        let mut rounds = 0;
+        let mut cooperations = 0;
+        for party in parties.iter() {
+            for (_, (first, second)) in Api::played_rounds(party) {
+                rounds += 1;
+                cooperations += [first, second]
+                    .iter()
+                    .filter(|side| side.1 == PlayStatus::Cooperate)
+                    .count();
+            }
+        }
+        let cooperation_rate = match rounds {
+            0 => 0.0,
+            _ => cooperations as f64 / (2 * rounds) as f64,
+        };
+
+        json!({
+            "players": players,
+            "parties": {
+                "waiting": count("waiting"),
+                "running": count("running"),
+                "finished": count("finished"),
+            },
+            "rounds_played": rounds,
+            "cooperation_rate": cooperation_rate,
+        })
+    }
+
+    fn state(party: &Party) -> &'static str {
+        match party.status {
+            Status::WaitingPlayer => "waiting",
+            Status::Finished => "finished",
+            _ => "running",
+        }
+    }
+
+    fn summary(party: &Party) -> Value {
+        json!({
+            "id": party.id,
+            "state": Api::state(party),
+            "status": party.status,
+            "round": party.round.min(party.total_round),
+            "total_round": party.total_round,
+            "bet": party.bet,
+            "player1": party.player1,
+            "player2": party.player2,
+            "winner": party.winner.as_ref().map(|player| player.id),
+        })
+    }
+
+    /// Rounds both players have played, numbered from 1.
+    fn played_rounds(party: &Party) -> impl Iterator<Item = (usize, &(RoundPlay, RoundPlay))> {
+        party
+            .party_round
+            .round_played
+            .iter()
+            .enumerate()
+            .filter(|(_, round)| round.0 .0.id != 0 && round.1 .0.id != 0)
+            .map(|(index, round)| (index + 1, round))
+    }
+
+    fn round_play(side: &RoundPlay) -> Value {
+        json!({
+            "id": side.0.id,
+            "play": side.1.to_string(),
+            "money": side.0.money,
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use settings::{Level, Log, Party, Settings};
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Finished parties, kept by the lobby once their actor has stopped.
+///
+/// Only the last `archive_size` parties stay in memory. When `storage_path`
+/// is set, every party is also appended to that file as one JSON line, by a
+/// writer task of its own so that pushing never waits on the disk.
+pub struct Archive {
+    parties: VecDeque<Party>,
+    capacity: usize,
+    writer: Option<(mpsc::UnboundedSender<Party>, JoinHandle<()>)>,
+}
+
+impl Archive {
+    /// Must be called within a tokio runtime when `storage_path` is set.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            parties: VecDeque::with_capacity(settings.archive_size),
+            capacity: settings.archive_size,
+            writer: match settings.storage_path.as_str() {
+                "" => None,
+                path => {
+                    let (sender, receiver) = mpsc::unbounded_channel();
+                    let writer = tokio::spawn(Archive::write(PathBuf::from(path), receiver));
+                    Some((sender, writer))
+                }
+            },
+        }
+    }
+
+    pub fn push(&mut self, party: Party) {
+        if let Some((sender, _)) = &self.writer {
+            let _ = sender.send(party.clone());
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+        if self.parties.len() == self.capacity {
+            self.parties.pop_front();
+        }
+        self.parties.push_back(party);
+    }
+
+    pub fn parties(&self) -> impl Iterator<Item = &Party> {
+        self.parties.iter()
+    }
+
+    /// Waits for the parties pushed so far to be written.
+    pub async fn flush(&mut self) {
+        if let Some((sender, writer)) = self.writer.take() {
+            drop(sender);
+            let _ = writer.await;
+        }
+    }
+
+    /// Appends parties in the order they were pushed.
+    async fn write(path: PathBuf, mut receiver: mpsc::UnboundedReceiver<Party>) {
+        while let Some(party) = receiver.recv().await {
+            let id = party.id;
+            let path = path.clone();
+            let written = tokio::task::spawn_blocking(move || Archive::append(&path, &party)).await;
+            if let Ok(Err(e)) = written {
+                Log::entry(Level::Error)
+                    .party(id)
+                    .message(format!("archiving party: {:?}", e));
+            }
+        }
+    }
+
+    /// Every party of a `storage_path` file, oldest first.
+    pub fn load(path: &Path) -> io::Result<Vec<Party>> {
+        let file = std::fs::File::open(path)?;
+        let mut parties = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let party = serde_json::from_str(&line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} line {}: {}", path.display(), index + 1, e),
+                )
+            })?;
+            parties.push(party);
+        }
+        Ok(parties)
+    }
+
+    fn append(path: &Path, party: &Party) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(party)?;
+        line.push('\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(line.as_bytes())
+    }
+}
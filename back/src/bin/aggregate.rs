@@ -0,0 +1,39 @@
+use prisoner_dilemma::aggregate::{self, Aggregate};
+use prisoner_dilemma::archive::Archive;
+use prisoner_dilemma::export::RoundRecord;
+use settings::{Log, Settings};
+use std::path::Path;
+
+/// Aggregates every party to one file of `export_dir`, in `export_format`.
+/// Parties are read from the `storage_path` file when it is set, else from
+/// the CSV and JSON Lines exports already in `export_dir`, which fails
+/// when it holds workbooks only.
+fn main() {
+    let settings = Settings::from_args("server");
+    let records = match settings.storage_path.as_str() {
+        "" => aggregate::read_exports(Path::new(&settings.export_dir)),
+        path => Archive::load(Path::new(path))
+            .map(|parties| parties.iter().flat_map(RoundRecord::of).collect()),
+    };
+    let records = match records {
+        Ok(records) => records,
+        Err(e) => {
+            Log::error(format!("reading parties: {}", e));
+            std::process::exit(1);
+        }
+    };
+
+    let aggregate = Aggregate::new(&records);
+    match aggregate.export(&settings) {
+        Ok(path) => println!(
+            "{} player(s) over {} round(s) aggregated to {}",
+            aggregate.players.len(),
+            aggregate.cooperation.rounds,
+            path.display()
+        ),
+        Err(e) => {
+            Log::error(format!("Aggregate failed: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use prisoner_dilemma::archive::Archive;
+use prisoner_dilemma::export;
+use settings::{Log, Settings};
+use std::path::Path;
+
+/// Exports every party of the `storage_path` file to one file of
+/// `export_dir`, in `export_format`.
+fn main() {
+    let settings = Settings::from_args("server");
+    if settings.storage_path.is_empty() {
+        Log::error("storage_path is not set, no party to export");
+        std::process::exit(1);
+    }
+    let parties = match Archive::load(Path::new(&settings.storage_path)) {
+        Ok(parties) => parties,
+        Err(e) => {
+            Log::error(format!("reading {}: {}", settings.storage_path, e));
+            std::process::exit(1);
+        }
+    };
+    match export::export_all(&settings, &parties) {
+        Ok(path) => println!(
+            "{} party(ies) exported to {}",
+            parties.len(),
+            path.display()
+        ),
+        Err(e) => {
+            Log::error(format!("Export failed: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,332 +1,244 @@
+use crate::lobby::Lobby;
+use crate::metrics::Metrics;
+use crate::outbox::Outbox;
+use crate::party::PartyHandle;
+use crate::seat::BotSeat;
 use rand::Rng;
-use rust_xlsxwriter::*;
-use settings::{Game, Log, Party, PlayStatus, Player, Protocol, Settings, Status};
-use std::collections::HashMap;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::sync::{Arc, Mutex, MutexGuard};
-use std::thread;
+use settings::{Entry, Level, Log, Protocol, Settings, Status};
+use std::future::Future;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
 
 pub struct Controller {
     pub listener: TcpListener,
-    pub game: Arc<Mutex<Game>>,
-    pub players_stream: Arc<Mutex<HashMap<u32, TcpStream>>>,
+    pub lobby: Lobby,
+    pub metrics: Arc<Metrics>,
+    write_timeout: Duration,
 }
 
-type BufferSize = [u8; 1024];
+/// State kept by the task reading one connection.
+struct Connection {
+    outbox: Outbox,
+    lobby: Lobby,
+    player_id: Option<u32>,
+    party: Option<PartyHandle>,
+}
+
+impl Connection {
+    /// Log entry tagged with the player of this connection, once known.
+    fn log(&self, level: Level) -> Entry {
+        match self.player_id {
+            Some(player_id) => Log::entry(level).player(player_id),
+            None => Log::entry(level),
+        }
+    }
+}
 
 impl Controller {
-    pub fn new(settings: &Settings) -> Self {
-        let listener =
-            TcpListener::bind(String::from(format!("{}:{}", settings.host, settings.port)))
-                .unwrap();
-        let game = Arc::new(Mutex::new(Game::default()));
-        let players_stream = Arc::new(Mutex::new(HashMap::new()));
+    pub async fn new(settings: &Settings, seat: Option<BotSeat>) -> Self {
+        let listener = TcpListener::bind((settings.host.as_str(), settings.port))
+            .await
+            .unwrap();
+        let metrics = Arc::new(Metrics::default());
+        let lobby = Lobby::spawn(settings, Arc::clone(&metrics), seat);
         Self {
             listener,
-            game,
-            players_stream,
+            lobby,
+            metrics,
+            write_timeout: Duration::from_secs(settings.write_timeout),
         }
     }
 
-    pub fn run(&self) {
-        let tcp_listener_c = self.listener.try_clone().unwrap();
-        for stream in tcp_listener_c.incoming() {
-            match stream {
-                Ok(tcp) => {
-                    let shared_game = Arc::clone(&self.game);
-                    let shared_players_stream = Arc::clone(&self.players_stream);
-
-                    thread::spawn(move || {
-                        Controller::process_message(&tcp, shared_game, shared_players_stream);
-                    });
-                }
+    pub fn local_addr(&self) -> SocketAddr {
+        self.listener.local_addr().unwrap()
+    }
 
-                Err(_) => Log::show(
-                    "ERROR",
-                    format!("Someting went wrong for reading the stream"),
-                ),
-            }
-        }
+    /// Serves clients until SIGINT or SIGTERM, then shuts down gracefully.
+    pub async fn run(&self) {
+        self.run_until(Controller::shutdown_signal()).await;
     }
 
-    pub fn process_message(
-        mut tcp_stream: &TcpStream,
-        game: Arc<Mutex<Game>>,
-        players: Arc<Mutex<HashMap<u32, TcpStream>>>,
-    ) {
-        let mut buffer: BufferSize = [0; 1024];
+    /// Serves clients until `shutdown` resolves. New connections are then
+    /// refused, connected clients get a `Shutdown` message, running parties
+    /// are saved and every connection is flushed before returning.
+    pub async fn run_until(&self, shutdown: impl Future<Output = ()>) {
+        let (stop_connections, stopped) = watch::channel(false);
+        let mut connections = JoinSet::new();
+        tokio::pin!(shutdown);
         loop {
-            match tcp_stream.read(&mut buffer) {
-                Ok(bytes_read) => {
-                    if bytes_read == 0 {
-                        Log::show("INFO", format!("Client disconnected"));
-                        break;
+            tokio::select! {
+                accepted = self.listener.accept() => match accepted {
+                    Ok((tcp, _)) => {
+                        let lobby = self.lobby.clone();
+                        let metrics = Arc::clone(&self.metrics);
+                        let write_timeout = self.write_timeout;
+                        let stopped = stopped.clone();
+
+                        connections.spawn(async move {
+                            Controller::process_message(tcp, lobby, metrics, write_timeout, stopped)
+                                .await;
+                        });
                     }
 
-                    let protocol: Protocol = Protocol::from_bytes(&buffer[..bytes_read]);
-
-                    Controller::handle_party(&protocol, &tcp_stream, &game, &players);
-                }
-                Err(e) => {
-                    Log::show("Error", format!("reading from socket: {:?}", e));
-                    break;
-                }
+                    Err(_) => Log::error("Someting went wrong for reading the stream"),
+                },
+                Some(_) = connections.join_next(), if !connections.is_empty() => (),
+                _ = &mut shutdown => break,
             }
         }
-    }
 
-    pub fn handle_party(
-        protocol: &Protocol,
-        tcp_stream: &TcpStream,
-        game: &Arc<Mutex<Game>>,
-        players: &Arc<Mutex<HashMap<u32, TcpStream>>>,
-    ) {
-        match protocol.party_status {
-            Status::Init => Controller::init_player(&tcp_stream, players),
-            Status::Created => Controller::create_game(&protocol, &players, &game),
-            Status::JoinParty => Controller::join_game(&protocol, &players, &game),
-            Status::Started => Controller::process_game(&protocol, &players, &game),
-            _ => Log::show("WARN", format!("No status found")),
-        }
+        Log::info("Shutting down");
+        self.lobby.shutdown().await;
+        let _ = stop_connections.send(true);
+        while connections.join_next().await.is_some() {}
+        Log::info("Server stopped");
     }
-    pub fn process_game(
-        protocol: &Protocol,
-        players: &Arc<Mutex<HashMap<u32, TcpStream>>>,
-        game: &Arc<Mutex<Game>>,
-    ) {
-        let mut game_arc = game.lock().unwrap();
-        if let Some(game_) = game_arc
-            .parties
-            .iter_mut()
-            .find(|element: &&mut Party| element.id == protocol.party_id)
-        {
-            let data = protocol.clone();
-            if let Some(current_game) = game_
-                .party_round
-                .round_played
-                .get_mut((data.round - 1) as usize)
-            {
-                if current_game.0 .0.id == 0 {
-                    current_game.0 .0 = data.player;
-                    current_game.0 .1 = data.play;
-                } else if current_game.1 .0.id == 0 {
-                    current_game.1 .0 = data.player;
-                    current_game.1 .1 = data.play;
 
-                    if current_game.0 .1 == PlayStatus::Betrail
-                        && current_game.1 .1 == PlayStatus::Betrail
-                    {
-                        current_game.0 .2 = current_game.0 .0.money as u32 - protocol.bet;
-                        current_game.0 .0.money = current_game.0 .2.into();
-                        current_game.1 .2 = current_game.1 .0.money as u32 - protocol.bet;
-                        current_game.1 .0.money = current_game.1 .2.into();
-                    } else if current_game.0 .1 == PlayStatus::Cooperate
-                        && current_game.1 .1 == PlayStatus::Cooperate
-                    {
-                        current_game.0 .2 = current_game.0 .0.money as u32 + (protocol.bet / 2);
-                        current_game.0 .0.money = current_game.0 .2.into();
-                        current_game.1 .2 = current_game.1 .0.money as u32 + (protocol.bet / 2);
-                        current_game.1 .0.money = current_game.1 .2.into();
-                    } else if current_game.0 .1 == PlayStatus::Betrail
-                        && current_game.1 .1 == PlayStatus::Cooperate
-                    {
-                        current_game.0 .2 = current_game.0 .0.money as u32 + (protocol.bet * 2);
-                        current_game.0 .0.money = current_game.0 .2.into();
-                        current_game.1 .2 = current_game.1 .0.money as u32 - (protocol.bet * 2);
-                        current_game.1 .0.money = current_game.1 .2.into();
-                    } else if current_game.0 .1 == PlayStatus::Cooperate
-                        && current_game.1 .1 == PlayStatus::Betrail
-                    {
-                        current_game.0 .2 = current_game.0 .0.money as u32 - (protocol.bet * 2);
-                        current_game.0 .0.money = current_game.0 .2.into();
-                        current_game.1 .2 = current_game.1 .0.money as u32 + (protocol.bet * 2);
-                        current_game.1 .0.money = current_game.1 .2.into();
-                    }
-                    game_.round = game_.round + 1;
-                    if game_.round > game_.total_round {
-                        let player1 = current_game.0 .0.clone();
-                        let player2 = current_game.1 .0.clone();
-                        let status = Controller::get_party_status(&player1, &player2);
-                        let mut protocol_send = protocol.clone();
-                        protocol_send.party_status = status;
-                        let tcp: TcpStream = Controller::get_stream(&players, player1.id);
-                        protocol_send.player = player1.clone();
-
-                        let bytes = protocol_send.to_bytes();
-                        Controller::send_message(&bytes, &tcp);
-
-                        let status = Controller::get_party_status(&player2, &player1);
-                        let mut protocol_send = protocol.clone();
-                        protocol_send.party_status = status;
-                        let tcp: TcpStream = Controller::get_stream(&players, player2.id);
-                        protocol_send.player = player2;
+    async fn shutdown_signal() {
+        let interrupt = async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                Log::error(format!("listening for SIGINT: {:?}", e));
+                std::future::pending::<()>().await;
+            }
+        };
 
-                        let bytes = protocol_send.to_bytes();
-                        Controller::send_message(&bytes, &tcp);
-                        let _ = Controller::write_result(&game_);
-                    } else {
-                        let players_to_send =
-                            [current_game.0 .0.clone(), current_game.1 .0.clone()];
-                        let mut protocol_send = protocol.clone();
-                        protocol_send.round = game_.round;
-                        for player in players_to_send.iter() {
-                            let tcp: TcpStream = Controller::get_stream(&players, player.id);
-                            protocol_send.player = player.clone();
-                            protocol_send.play = PlayStatus::Stanby;
-                            let bytes = protocol_send.to_bytes();
-                            Controller::send_message(&bytes, &tcp);
-                        }
-                    }
+        #[cfg(unix)]
+        let terminate = async {
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    terminate.recv().await;
+                }
+                Err(e) => {
+                    Log::error(format!("listening for SIGTERM: {:?}", e));
+                    std::future::pending::<()>().await;
                 }
             }
-        } else {
-            Log::show("WARN", format!("Not party found"));
-        }
-    }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
 
-    fn write_result(game: &Party) -> Result<(), XlsxError> {
-        let mut workbook = Workbook::new();
-        let worksheet = workbook.add_worksheet();
-        let headers = [
-            "GameId",
-            "Player1",
-            "Player 1 Play",
-            "Player 1 Money",
-            "Player2",
-            "Player 2 Play",
-            "Player 2 Money",
-        ];
-
-        for (col, &header) in headers.iter().enumerate() {
-            worksheet.write(0, col.try_into().unwrap(), header)?;
-        }
-
-        for (index, round) in game.party_round.round_played.iter().enumerate() {
-            let adjusted_index = index as u32 + 1;
-            worksheet.write(adjusted_index, 0, game.id)?;
-            worksheet.write(adjusted_index, 1, round.0 .0.id)?;
-            worksheet.write(adjusted_index, 2, round.0 .1.to_string())?;
-            worksheet.write(adjusted_index, 3, round.0 .0.money)?;
-            worksheet.write(adjusted_index, 4, round.1 .0.id)?;
-            worksheet.write(adjusted_index, 5, round.1 .1.to_string())?;
-            worksheet.write(adjusted_index, 6, round.1 .0.money)?;
+        tokio::select! {
+            _ = interrupt => (),
+            _ = terminate => (),
         }
-        workbook.save(format!("../game_{}.xlsx", game.id))?;
-        Ok(())
     }
 
-    fn get_party_status(player1: &Player, player2: &Player) -> Status {
-        if player1.money < player2.money {
-            return Status::Lose;
-        } else if player1.money > player2.money {
-            return Status::Win;
-        } else {
-            return Status::Equal;
-        }
-    }
-
-    pub fn join_game(
-        protocol: &Protocol,
-        players: &Arc<Mutex<HashMap<u32, TcpStream>>>,
-        game: &Arc<Mutex<Game>>,
+    pub async fn process_message(
+        tcp_stream: TcpStream,
+        lobby: Lobby,
+        metrics: Arc<Metrics>,
+        write_timeout: Duration,
+        mut stopped: watch::Receiver<bool>,
     ) {
-        let mut game_arc = game.lock().unwrap();
-        if let Some(element) = game_arc
-            .parties
-            .iter_mut()
-            .find(|element| element.status == Status::WaitingPlayer)
-        {
-            element.player1 = protocol.player.clone();
-            element.status = Status::Started;
-
-            let mut protocol_send = protocol.clone();
-            protocol_send.party_status = Status::Started;
-            protocol_send.bet = element.bet;
-            protocol_send.total_round = element.total_round;
-            protocol_send.party_id = element.id;
-            protocol_send.round = 1;
-            let players_to_send = [element.player1.clone(), element.player2.clone()];
-            for player in players_to_send.iter() {
-                let tcp: TcpStream = Controller::get_stream(&players, player.id);
-                protocol_send.player = player.clone();
-                let bytes = protocol_send.to_bytes();
-                Controller::send_message(&bytes, &tcp);
+        metrics.client_connected();
+        let (mut reader, writer) = tcp_stream.into_split();
+        let (outbox, writer) = Outbox::spawn(writer, write_timeout, Arc::clone(&metrics));
+        let mut connection = Connection {
+            outbox,
+            lobby,
+            player_id: None,
+            party: None,
+        };
+        loop {
+            let read = tokio::select! {
+                read = Controller::read_protocol(&mut reader) => read,
+                _ = connection.outbox.closed() => {
+                    connection.log(Level::Info).message("Client dropped");
+                    break;
+                }
+                _ = stopped.wait_for(|stopped| *stopped) => break,
+            };
+            match read {
+                Ok(protocol) => {
+                    Controller::handle_party(protocol, &mut connection).await;
+                }
+                Err(e) if e.kind() == ErrorKind::InvalidData => {
+                    connection
+                        .log(Level::Warn)
+                        .message(format!("dropping frame: {}", e));
+                    metrics.decode_error();
+                }
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    connection.log(Level::Info).message("Client disconnected");
+                    break;
+                }
+                Err(e) => {
+                    connection
+                        .log(Level::Error)
+                        .message(format!("reading from socket: {:?}", e));
+                    break;
+                }
             }
-        } else {
-            Log::show("WARN", format!("No party found"));
         }
-    }
-
-    pub fn send_message(bytes: &Vec<u8>, mut tcp_steam: &TcpStream) {
-        tcp_steam.write_all(&bytes).expect("error write");
-        tcp_steam.flush().expect("error flush");
-    }
 
-    pub fn create_game(
-        protocol: &Protocol,
-        players: &Arc<Mutex<HashMap<u32, TcpStream>>>,
-        game: &Arc<Mutex<Game>>,
-    ) {
-        let mut party = Party::default();
-        let mut rng = rand::thread_rng();
-
-        party.id = rng.gen::<u32>();
-        party.status = Status::WaitingPlayer;
-
-        let default_player = Player::default();
-        let player_from_protocol = protocol.player.clone();
-
-        party.player1 = default_player;
-        party.player2 = player_from_protocol;
-        party.bet = protocol.bet;
-        party.total_round = protocol.total_round;
-        for _ in 0..protocol.total_round {
-            party.party_round.round_played.push((
-                (Player::default(), PlayStatus::default(), 0 as u32),
-                (Player::default(), PlayStatus::default(), 0 as u32),
-            ))
+        if let Some(player_id) = connection.player_id {
+            if let Some(party) = &connection.party {
+                party.leave(player_id).await;
+            }
+            connection.lobby.unregister_player(player_id).await;
         }
 
-        let player_id: u32 = protocol.player.id;
-
-        let mut protocol_send = protocol.clone();
-        protocol_send.party_id = party.id;
-        protocol_send.party_status = Status::Created;
-
-        let mut game_mutux = game.lock().unwrap();
-
-        game_mutux.add_party(party);
-
-        let tcp: TcpStream = Controller::get_stream(&players, player_id);
+        // Let the writer flush what the lobby and the party queued last.
+        drop(connection);
+        let _ = tokio::time::timeout(write_timeout, writer).await;
+        metrics.client_disconnected();
+    }
 
-        let bytes = protocol_send.to_bytes();
-        Controller::send_message(&bytes, &tcp);
+    /// Reads one whole frame from an async stream. A frame that cannot be
+    /// decoded is consumed and reported as `ErrorKind::InvalidData`.
+    pub async fn read_protocol(reader: &mut (impl AsyncRead + Unpin)) -> std::io::Result<Protocol> {
+        let mut bytes = vec![0; Protocol::HEADER_SIZE];
+        reader.read_exact(&mut bytes).await?;
+        bytes.resize(Protocol::HEADER_SIZE + Protocol::message_size(&bytes), 0);
+        reader
+            .read_exact(&mut bytes[Protocol::HEADER_SIZE..])
+            .await?;
+        Protocol::decode(&bytes)
+            .map_err(|reason| std::io::Error::new(ErrorKind::InvalidData, reason))
     }
 
-    pub fn get_stream(players: &Arc<Mutex<HashMap<u32, TcpStream>>>, player_id: u32) -> TcpStream {
-        let players_stream_arc = players.lock().unwrap();
-        return players_stream_arc
-            .get(&player_id)
-            .expect("Error get stream 1")
-            .try_clone()
-            .expect("Error get stream 2");
+    async fn handle_party(protocol: Protocol, connection: &mut Connection) {
+        match protocol.party_status {
+            Status::Init => Controller::init_player(connection).await,
+            Status::Created => {
+                connection.party = connection.lobby.create_game(protocol).await;
+            }
+            Status::JoinParty => {
+                connection.party = connection.lobby.join_game(protocol).await;
+            }
+            Status::Started => match &connection.party {
+                Some(party) if party.id == protocol.party_id => party.play(protocol).await,
+                _ => connection
+                    .log(Level::Warn)
+                    .party(protocol.party_id)
+                    .message("Not party found"),
+            },
+            _ => connection
+                .log(Level::Warn)
+                .field("status", format!("{:?}", protocol.party_status))
+                .message("No status found"),
+        }
     }
 
-    pub fn init_player(tcp_stream: &TcpStream, players: &Arc<Mutex<HashMap<u32, TcpStream>>>) {
+    async fn init_player(connection: &mut Connection) {
         let mut protocol: Protocol = Protocol::default();
-        let mut rng = rand::thread_rng();
-        protocol.player.id = rng.gen::<u32>();
-
-        Log::show("INFO", format!("New user #{}", protocol.player.id));
-
-        let bytes = protocol.to_bytes();
-
-        let cloned_stream = tcp_stream.try_clone().expect("Failed to clone TcpStream");
+        protocol.player.id = rand::thread_rng().gen::<u32>();
 
-        Controller::send_message(&bytes, &cloned_stream);
+        connection.player_id = Some(protocol.player.id);
+        connection.log(Level::Info).message("New user");
 
-        let mut players_stream_arc = players.lock().unwrap();
-        players_stream_arc.insert(protocol.player.id, cloned_stream);
+        connection
+            .lobby
+            .register_player(protocol.player.id, connection.outbox.clone())
+            .await;
+        connection.outbox.send(&protocol);
     }
 }
@@ -0,0 +1,249 @@
+pub mod csv;
+mod jsonl;
+mod xlsx;
+
+pub use self::csv::CsvExporter;
+pub use self::jsonl::JsonLinesExporter;
+pub use self::xlsx::XlsxExporter;
+use rust_xlsxwriter::XlsxError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use settings::{ExportFormat, Log, Party, PayoffMatrix, PlayStatus, Player, Settings};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Name of a bulk export under `export_dir`, placeholders as in `export_file`.
+const BULK_FILE: &str = "parties_{date}_{time}.{ext}";
+
+#[derive(Debug)]
+pub enum ExportError {
+    /// The export directory could not be created.
+    Directory(PathBuf, io::Error),
+    /// A workbook could not be written.
+    Xlsx(PathBuf, XlsxError),
+    /// A text export could not be written.
+    Io(PathBuf, io::Error),
+    /// The export task panicked.
+    Panicked(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::Directory(path, e) => {
+                write!(f, "cannot create directory {}: {}", path.display(), e)
+            }
+            ExportError::Xlsx(path, e) => write!(f, "cannot write {}: {}", path.display(), e),
+            ExportError::Io(path, e) => write!(f, "cannot write {}: {}", path.display(), e),
+            ExportError::Panicked(e) => write!(f, "export task failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// A file format parties are exported to.
+///
+/// Every format holds the same rows, one `RoundRecord` per resolved round
+/// with the columns of `RoundRecord::COLUMNS`, so analyses do not depend on
+/// the format chosen in `export_format`.
+pub trait Exporter: Send + Sync {
+    /// Extension of the files written, without the dot.
+    fn extension(&self) -> &'static str;
+
+    /// Writes the rounds of one party.
+    fn write_party(
+        &self,
+        party: &Party,
+        payoff: &PayoffMatrix,
+        path: &Path,
+    ) -> Result<(), ExportError>;
+
+    /// Writes the rounds of several parties in one file.
+    fn write_parties(&self, parties: &[Party], path: &Path) -> Result<(), ExportError>;
+}
+
+pub fn exporter(format: ExportFormat) -> Box<dyn Exporter> {
+    match format {
+        ExportFormat::Xlsx => Box::new(XlsxExporter),
+        ExportFormat::Csv => Box::new(CsvExporter),
+        ExportFormat::Jsonl => Box::new(JsonLinesExporter),
+    }
+}
+
+/// Writes the result of a party to `export_dir`, named after `export_file`.
+pub fn export(settings: &Settings, party: &Party) -> Result<PathBuf, ExportError> {
+    let exporter = exporter(settings.export_format);
+    let name = file_name(
+        &settings.export_file,
+        party,
+        SystemTime::now(),
+        exporter.extension(),
+    );
+    let path = create_path(settings, &name)?;
+    exporter.write_party(party, &settings.payoff, &path)?;
+    Ok(path)
+}
+
+/// Writes every party given to one file of `export_dir`.
+pub fn export_all(settings: &Settings, parties: &[Party]) -> Result<PathBuf, ExportError> {
+    let exporter = exporter(settings.export_format);
+    let name = fill(BULK_FILE, SystemTime::now(), exporter.extension());
+    let path = create_path(settings, &name)?;
+    exporter.write_parties(parties, &path)?;
+    Ok(path)
+}
+
+pub(crate) fn create_path(settings: &Settings, name: &str) -> Result<PathBuf, ExportError> {
+    let path = Path::new(&settings.export_dir).join(name);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| ExportError::Directory(dir.to_path_buf(), e))?;
+    }
+    Ok(path)
+}
+
+/// Replaces the placeholders of an `export_file` template. Settings
+/// validation already rejected unknown ones.
+pub fn file_name(template: &str, party: &Party, time: SystemTime, extension: &str) -> String {
+    fill(template, time, extension)
+        .replace("{party_id}", &party.id.to_string())
+        .replace("{player1}", &party.player1.id.to_string())
+        .replace("{player2}", &party.player2.id.to_string())
+}
+
+/// Replaces the placeholders which do not depend on a party.
+pub(crate) fn fill(template: &str, time: SystemTime, extension: &str) -> String {
+    let timestamp = Log::timestamp(time);
+    template
+        .replace("{date}", &timestamp[..10])
+        .replace("{time}", &timestamp[11..19].replace(':', ""))
+        .replace("{ext}", extension)
+}
+
+/// Outcome of one resolved round, as seen from both players.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundResult {
+    pub round: u32,
+    pub player1_play: PlayStatus,
+    /// Money won or lost by player 1 in this round.
+    pub player1_payout: f64,
+    /// Balance of player 1 after this round.
+    pub player1_total: f64,
+    pub player2_play: PlayStatus,
+    pub player2_payout: f64,
+    pub player2_total: f64,
+}
+
+/// Rounds both players have played, in order. Both players start from
+/// `Player::STARTING_MONEY`.
+pub fn round_results(party: &Party) -> Vec<RoundResult> {
+    let mut totals = (Player::STARTING_MONEY, Player::STARTING_MONEY);
+    let mut results = Vec::new();
+    for (index, round) in party.party_round.round_played.iter().enumerate() {
+        if round.0 .0.id == 0 || round.1 .0.id == 0 {
+            continue;
+        }
+        let result = RoundResult {
+            round: index as u32 + 1,
+            player1_play: round.0 .1.clone(),
+            player1_payout: round.0 .0.money - totals.0,
+            player1_total: round.0 .0.money,
+            player2_play: round.1 .1.clone(),
+            player2_payout: round.1 .0.money - totals.1,
+            player2_total: round.1 .0.money,
+        };
+        totals = (result.player1_total, result.player2_total);
+        results.push(result);
+    }
+    results
+}
+
+/// One row of every export format: a resolved round with its party.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundRecord {
+    pub party_id: u32,
+    /// `Party::created`, telling apart parties of different server runs
+    /// which drew the same id.
+    #[serde(default)]
+    pub party_created: u64,
+    pub party_status: String,
+    pub bet: u32,
+    pub total_round: u32,
+    /// 0 while there is no winner.
+    pub winner_id: u32,
+    pub round: u32,
+    pub player1_id: u32,
+    pub player1_play: String,
+    pub player1_payout: f64,
+    pub player1_balance: f64,
+    pub player2_id: u32,
+    pub player2_play: String,
+    pub player2_payout: f64,
+    pub player2_balance: f64,
+}
+
+impl RoundRecord {
+    /// Field names, in the order of `values`.
+    pub const COLUMNS: [&'static str; 15] = [
+        "party_id",
+        "party_created",
+        "party_status",
+        "bet",
+        "total_round",
+        "winner_id",
+        "round",
+        "player1_id",
+        "player1_play",
+        "player1_payout",
+        "player1_balance",
+        "player2_id",
+        "player2_play",
+        "player2_payout",
+        "player2_balance",
+    ];
+
+    pub fn of(party: &Party) -> Vec<RoundRecord> {
+        round_results(party)
+            .into_iter()
+            .map(|result| RoundRecord {
+                party_id: party.id,
+                party_created: party.created,
+                party_status: format!("{:?}", party.status),
+                bet: party.bet,
+                total_round: party.total_round,
+                winner_id: party.winner.as_ref().map_or(0, |winner| winner.id),
+                round: result.round,
+                player1_id: party.player1.id,
+                player1_play: String::from(result.player1_play.to_string()),
+                player1_payout: result.player1_payout,
+                player1_balance: result.player1_total,
+                player2_id: party.player2.id,
+                player2_play: String::from(result.player2_play.to_string()),
+                player2_payout: result.player2_payout,
+                player2_balance: result.player2_total,
+            })
+            .collect()
+    }
+
+    pub fn values(&self) -> [Value; 15] {
+        [
+            json!(self.party_id),
+            json!(self.party_created),
+            json!(self.party_status),
+            json!(self.bet),
+            json!(self.total_round),
+            json!(self.winner_id),
+            json!(self.round),
+            json!(self.player1_id),
+            json!(self.player1_play),
+            json!(self.player1_payout),
+            json!(self.player1_balance),
+            json!(self.player2_id),
+            json!(self.player2_play),
+            json!(self.player2_payout),
+            json!(self.player2_balance),
+        ]
+    }
+}
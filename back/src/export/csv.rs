@@ -0,0 +1,72 @@
+use super::{ExportError, Exporter, RoundRecord};
+use serde_json::Value;
+use settings::{csv, Party, PayoffMatrix};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Comma separated values with a header line, one line per round.
+pub struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn write_party(
+        &self,
+        party: &Party,
+        _payoff: &PayoffMatrix,
+        path: &Path,
+    ) -> Result<(), ExportError> {
+        self.write_parties(std::slice::from_ref(party), path)
+    }
+
+    fn write_parties(&self, parties: &[Party], path: &Path) -> Result<(), ExportError> {
+        write(parties, path).map_err(|e| ExportError::Io(path.to_path_buf(), e))
+    }
+}
+
+fn write(parties: &[Party], path: &Path) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "{}", RoundRecord::COLUMNS.join(","))?;
+    for record in parties.iter().flat_map(RoundRecord::of) {
+        writeln!(file, "{}", line(&record.values()))?;
+    }
+    file.flush()
+}
+
+/// A CSV line of JSON values, strings unquoted unless they hold a comma, a
+/// quote or a line break, and null as an empty field.
+pub fn line(values: &[Value]) -> String {
+    let fields: Vec<String> = values
+        .iter()
+        .map(|value| match value {
+            Value::Null => String::new(),
+            Value::String(text) => csv::quoted(text),
+            other => other.to_string(),
+        })
+        .collect();
+    fields.join(",")
+}
+
+/// Fields of a CSV line, quoted ones unquoted.
+pub fn fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
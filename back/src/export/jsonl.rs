@@ -0,0 +1,36 @@
+use super::{ExportError, Exporter, RoundRecord};
+use settings::{Party, PayoffMatrix};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// JSON Lines, one object per round keyed by `RoundRecord::COLUMNS`.
+pub struct JsonLinesExporter;
+
+impl Exporter for JsonLinesExporter {
+    fn extension(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn write_party(
+        &self,
+        party: &Party,
+        _payoff: &PayoffMatrix,
+        path: &Path,
+    ) -> Result<(), ExportError> {
+        self.write_parties(std::slice::from_ref(party), path)
+    }
+
+    fn write_parties(&self, parties: &[Party], path: &Path) -> Result<(), ExportError> {
+        write(parties, path).map_err(|e| ExportError::Io(path.to_path_buf(), e))
+    }
+}
+
+fn write(parties: &[Party], path: &Path) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    for record in parties.iter().flat_map(RoundRecord::of) {
+        serde_json::to_writer(&mut file, &record)?;
+        writeln!(file)?;
+    }
+    file.flush()
+}
@@ -0,0 +1,181 @@
+use super::{round_results, ExportError, Exporter, RoundRecord, RoundResult};
+use rust_xlsxwriter::*;
+use serde_json::Value;
+use settings::{Party, PayoffMatrix, PlayStatus, Status};
+use std::path::Path;
+
+const ROUNDS_SHEET: &str = "Rounds";
+const DATA_SHEET: &str = "Data";
+/// Money with two decimals, losses in red.
+const MONEY_FORMAT: &str = "#,##0.00;[Red]-#,##0.00";
+
+/// Workbooks with a report sheet for people and a `Data` sheet holding the
+/// rows of the other formats.
+pub struct XlsxExporter;
+
+impl Exporter for XlsxExporter {
+    fn extension(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn write_party(
+        &self,
+        party: &Party,
+        payoff: &PayoffMatrix,
+        path: &Path,
+    ) -> Result<(), ExportError> {
+        write_report(party, payoff, path).map_err(|e| ExportError::Xlsx(path.to_path_buf(), e))
+    }
+
+    fn write_parties(&self, parties: &[Party], path: &Path) -> Result<(), ExportError> {
+        let records: Vec<RoundRecord> = parties.iter().flat_map(RoundRecord::of).collect();
+        let mut workbook = Workbook::new();
+        write_records(workbook.add_worksheet(), &records)
+            .and_then(|_| workbook.save(path))
+            .map_err(|e| ExportError::Xlsx(path.to_path_buf(), e))
+    }
+}
+
+fn write_records(sheet: &mut Worksheet, records: &[RoundRecord]) -> Result<(), XlsxError> {
+    sheet.set_name(DATA_SHEET)?;
+    let bold = Format::new().set_bold();
+    for (col, column) in RoundRecord::COLUMNS.iter().enumerate() {
+        sheet.write_with_format(0, col as u16, *column, &bold)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    for (index, record) in records.iter().enumerate() {
+        let row = index as u32 + 1;
+        for (col, value) in record.values().iter().enumerate() {
+            match value {
+                Value::Number(number) => {
+                    sheet.write(row, col as u16, number.as_f64().unwrap_or_default())?
+                }
+                Value::String(text) => sheet.write(row, col as u16, text)?,
+                other => sheet.write(row, col as u16, other.to_string())?,
+            };
+        }
+    }
+    Ok(())
+}
+
+/// Share of rounds in which a player cooperated, 0 before the first round.
+fn cooperation_rate(results: &[RoundResult], play: impl Fn(&RoundResult) -> &PlayStatus) -> f64 {
+    match results.len() {
+        0 => 0.0,
+        rounds => {
+            let cooperations = results
+                .iter()
+                .filter(|result| *play(result) == PlayStatus::Cooperate)
+                .count();
+            cooperations as f64 / rounds as f64
+        }
+    }
+}
+
+/// A `Summary` sheet (players, balances, winner, cooperation rates and the
+/// payoff matrix used), a `Rounds` sheet with every round and a chart of both
+/// balances, then the `Data` sheet.
+fn write_report(game: &Party, payoff: &PayoffMatrix, path: &Path) -> Result<(), XlsxError> {
+    let results = round_results(game);
+    let bold = Format::new().set_bold();
+    let money = Format::new().set_num_format(MONEY_FORMAT);
+    let percent = Format::new().set_num_format("0.0%");
+    let factor = Format::new().set_num_format("0.00\"x\"");
+    let mut workbook = Workbook::new();
+
+    let summary = workbook.add_worksheet().set_name("Summary")?;
+    summary.set_column_width(0, 20)?;
+    summary.set_column_width(1, 14)?;
+    summary.set_column_width(2, 14)?;
+    summary.set_column_width(3, 18)?;
+    summary.write_with_format(0, 0, "Party", &bold)?;
+    summary.write(0, 1, game.id)?;
+    summary.write_with_format(1, 0, "Status", &bold)?;
+    summary.write(1, 1, format!("{:?}", game.status))?;
+    summary.write_with_format(2, 0, "Rounds played", &bold)?;
+    summary.write(2, 1, format!("{}/{}", results.len(), game.total_round))?;
+    summary.write_with_format(3, 0, "Bet", &bold)?;
+    summary.write_with_format(3, 1, game.bet, &money)?;
+    summary.write_with_format(4, 0, "Winner", &bold)?;
+    match &game.winner {
+        Some(winner) => summary.write(4, 1, format!("#{}", winner.id))?,
+        None if game.status == Status::Finished => summary.write(4, 1, "Draw")?,
+        None => summary.write(4, 1, "-")?,
+    };
+
+    for (col, header) in ["Player", "Balance", "Cooperation rate"].iter().enumerate() {
+        summary.write_with_format(6, col as u16, *header, &bold)?;
+    }
+    let players = [
+        (
+            &game.player1,
+            cooperation_rate(&results, |result| &result.player1_play),
+        ),
+        (
+            &game.player2,
+            cooperation_rate(&results, |result| &result.player2_play),
+        ),
+    ];
+    for (row, (player, rate)) in players.iter().enumerate() {
+        let row = row as u32 + 7;
+        summary.write(row, 0, format!("#{}", player.id))?;
+        summary.write_with_format(row, 1, player.money, &money)?;
+        summary.write_with_format(row, 2, *rate, &percent)?;
+    }
+
+    summary.write_with_format(10, 0, "Payoff (x bet)", &bold)?;
+    summary.write_with_format(10, 1, "Other cooperates", &bold)?;
+    summary.write_with_format(10, 2, "Other betrays", &bold)?;
+    summary.write_with_format(11, 0, "Cooperate", &bold)?;
+    summary.write_with_format(11, 1, payoff.reward, &factor)?;
+    summary.write_with_format(11, 2, payoff.sucker, &factor)?;
+    summary.write_with_format(12, 0, "Betray", &bold)?;
+    summary.write_with_format(12, 1, payoff.temptation, &factor)?;
+    summary.write_with_format(12, 2, payoff.punishment, &factor)?;
+
+    let rounds = workbook.add_worksheet().set_name(ROUNDS_SHEET)?;
+    let headers = [
+        String::from("Round"),
+        format!("#{} play", game.player1.id),
+        format!("#{} payout", game.player1.id),
+        format!("#{} balance", game.player1.id),
+        format!("#{} play", game.player2.id),
+        format!("#{} payout", game.player2.id),
+        format!("#{} balance", game.player2.id),
+    ];
+    for (col, header) in headers.iter().enumerate() {
+        rounds.write_with_format(0, col as u16, header, &bold)?;
+        rounds.set_column_width(col as u16, 14)?;
+    }
+    rounds.set_freeze_panes(1, 0)?;
+    for (index, result) in results.iter().enumerate() {
+        let row = index as u32 + 1;
+        rounds.write(row, 0, result.round)?;
+        rounds.write(row, 1, result.player1_play.to_string())?;
+        rounds.write_with_format(row, 2, result.player1_payout, &money)?;
+        rounds.write_with_format(row, 3, result.player1_total, &money)?;
+        rounds.write(row, 4, result.player2_play.to_string())?;
+        rounds.write_with_format(row, 5, result.player2_payout, &money)?;
+        rounds.write_with_format(row, 6, result.player2_total, &money)?;
+    }
+
+    if !results.is_empty() {
+        let last = results.len() as u32;
+        let mut chart = Chart::new_line();
+        chart.title().set_name("Balance over rounds");
+        chart.x_axis().set_name("Round");
+        chart.y_axis().set_name("Balance");
+        for col in [3, 6] {
+            chart
+                .add_series()
+                .set_name((ROUNDS_SHEET, 0, col))
+                .set_categories((ROUNDS_SHEET, 1, 0, last, 0))
+                .set_values((ROUNDS_SHEET, 1, col, last, col));
+        }
+        rounds.insert_chart(1, 8, &chart)?;
+    }
+
+    write_records(workbook.add_worksheet(), &RoundRecord::of(game))?;
+    workbook.save(path)?;
+    Ok(())
+}
@@ -0,0 +1,11 @@
+pub mod admin;
+pub mod aggregate;
+pub mod api;
+pub mod archive;
+pub mod controller;
+pub mod export;
+pub mod lobby;
+pub mod metrics;
+pub mod outbox;
+pub mod party;
+pub mod seat;
@@ -0,0 +1,409 @@
+use crate::archive::Archive;
+use crate::metrics::Metrics;
+use crate::outbox::Outbox;
+use crate::party::PartyHandle;
+use crate::seat::BotSeat;
+use rand::Rng;
+use settings::{Level, Log, Party, PlayStatus, Player, Protocol, Settings, Status};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{JoinError, JoinSet};
+
+pub const CHANNEL_SIZE: usize = 1024;
+/// How often waiting parties are checked against `party_ttl` and `bot_wait`.
+const EXPIRY_CHECK: Duration = Duration::from_secs(1);
+
+type PartyReply = oneshot::Sender<Option<PartyHandle>>;
+
+enum LobbyMessage {
+    Register {
+        player_id: u32,
+        outbox: Outbox,
+    },
+    Unregister(u32),
+    Create {
+        protocol: Protocol,
+        reply: PartyReply,
+    },
+    Join {
+        protocol: Protocol,
+        reply: PartyReply,
+    },
+    Snapshot(oneshot::Sender<LobbySnapshot>),
+    Kick {
+        player_id: u32,
+        reply: oneshot::Sender<bool>,
+    },
+    Cancel {
+        party_id: u32,
+        reply: oneshot::Sender<bool>,
+    },
+    Broadcast(String),
+    Shutdown {
+        done: oneshot::Sender<()>,
+    },
+}
+
+/// Players and parties known by the lobby, for the admin tools.
+pub struct LobbySnapshot {
+    pub players: Vec<u32>,
+    pub parties: Vec<PartyHandle>,
+    pub archived: Vec<Party>,
+}
+
+/// Handle to the lobby actor, which knows every connected player and pairs
+/// them into parties. Once paired, a connection talks to its party actor
+/// directly, so rounds never go through the lobby.
+///
+/// The lobby also owns the party lifecycle: it archives parties whose actor
+/// stopped, expires parties nobody joined and forgets disconnected players.
+#[derive(Clone)]
+pub struct Lobby {
+    sender: mpsc::Sender<LobbyMessage>,
+}
+
+impl Lobby {
+    /// Without a `seat`, parties wait for a second player until `party_ttl`.
+    pub fn spawn(settings: &Settings, metrics: Arc<Metrics>, seat: Option<BotSeat>) -> Self {
+        let (sender, receiver) = mpsc::channel(CHANNEL_SIZE);
+        let actor = LobbyActor {
+            players: HashMap::new(),
+            parties: HashMap::new(),
+            waiting: VecDeque::new(),
+            running: JoinSet::new(),
+            archive: Archive::new(settings),
+            party_ttl: match settings.party_ttl {
+                0 => None,
+                ttl => Some(Duration::from_secs(ttl)),
+            },
+            seat: seat.map(|seat| (seat, Duration::from_secs(settings.bot_wait))),
+            settings: Arc::new(settings.clone()),
+            metrics,
+        };
+        tokio::spawn(actor.run(receiver));
+        Self { sender }
+    }
+
+    pub async fn register_player(&self, player_id: u32, outbox: Outbox) {
+        self.send(LobbyMessage::Register { player_id, outbox })
+            .await;
+    }
+
+    pub async fn unregister_player(&self, player_id: u32) {
+        self.send(LobbyMessage::Unregister(player_id)).await;
+    }
+
+    pub async fn create_game(&self, protocol: Protocol) -> Option<PartyHandle> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(LobbyMessage::Create { protocol, reply }).await;
+        receiver.await.ok().flatten()
+    }
+
+    pub async fn join_game(&self, protocol: Protocol) -> Option<PartyHandle> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(LobbyMessage::Join { protocol, reply }).await;
+        receiver.await.ok().flatten()
+    }
+
+    pub async fn snapshot(&self) -> Option<LobbySnapshot> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(LobbyMessage::Snapshot(reply)).await;
+        receiver.await.ok()
+    }
+
+    /// Every party the lobby knows: waiting and running ones first, then
+    /// the archived ones.
+    pub async fn parties(&self) -> Vec<Party> {
+        let Some(snapshot) = self.snapshot().await else {
+            return Vec::new();
+        };
+        let mut parties = Vec::new();
+        for handle in snapshot.parties.iter() {
+            if let Some(party) = handle.inspect().await {
+                parties.push(party);
+            }
+        }
+        parties.extend(snapshot.archived);
+        parties
+    }
+
+    /// Disconnects a player. Returns `false` if the player is unknown.
+    pub async fn kick(&self, player_id: u32) -> bool {
+        let (reply, receiver) = oneshot::channel();
+        self.send(LobbyMessage::Kick { player_id, reply }).await;
+        receiver.await.unwrap_or(false)
+    }
+
+    /// Cancels a party. Returns `false` if the party is unknown.
+    pub async fn cancel(&self, party_id: u32) -> bool {
+        let (reply, receiver) = oneshot::channel();
+        self.send(LobbyMessage::Cancel { party_id, reply }).await;
+        receiver.await.unwrap_or(false)
+    }
+
+    pub async fn broadcast(&self, message: String) {
+        self.send(LobbyMessage::Broadcast(message)).await;
+    }
+
+    /// Tells every player the server is going down, stops all parties and
+    /// the lobby itself. Returns once running parties are saved.
+    pub async fn shutdown(&self) {
+        let (done, receiver) = oneshot::channel();
+        self.send(LobbyMessage::Shutdown { done }).await;
+        let _ = receiver.await;
+    }
+
+    async fn send(&self, message: LobbyMessage) {
+        if self.sender.send(message).await.is_err() {
+            Log::error("Lobby is not running");
+        }
+    }
+}
+
+struct LobbyActor {
+    players: HashMap<u32, Outbox>,
+    parties: HashMap<u32, PartyHandle>,
+    waiting: VecDeque<(u32, Instant)>,
+    running: JoinSet<Party>,
+    archive: Archive,
+    party_ttl: Option<Duration>,
+    /// The bot taking the free seat of parties, and how long a party waits
+    /// for it. `None` without `bot_seat`.
+    seat: Option<(BotSeat, Duration)>,
+    settings: Arc<Settings>,
+    metrics: Arc<Metrics>,
+}
+
+impl LobbyActor {
+    async fn run(mut self, mut receiver: mpsc::Receiver<LobbyMessage>) {
+        let mut expiry = tokio::time::interval(EXPIRY_CHECK);
+        loop {
+            tokio::select! {
+                message = receiver.recv() => match message {
+                    Some(message) => {
+                        if !self.handle_message(message).await {
+                            return;
+                        }
+                    }
+                    None => return,
+                },
+                Some(ended) = self.running.join_next(), if !self.running.is_empty() => {
+                    self.party_ended(ended).await;
+                }
+                _ = expiry.tick() => {
+                    self.seat_bots().await;
+                    self.expire_parties().await;
+                }
+            }
+        }
+    }
+
+    /// Returns `false` once the lobby has shut down.
+    async fn handle_message(&mut self, message: LobbyMessage) -> bool {
+        match message {
+            LobbyMessage::Register { player_id, outbox } => {
+                self.players.insert(player_id, outbox);
+            }
+            LobbyMessage::Unregister(player_id) => {
+                self.players.remove(&player_id);
+            }
+            LobbyMessage::Create { protocol, reply } => {
+                let _ = reply.send(self.create_game(&protocol));
+            }
+            LobbyMessage::Join { protocol, reply } => {
+                let _ = reply.send(self.join_game(&protocol).await);
+            }
+            LobbyMessage::Snapshot(reply) => {
+                let _ = reply.send(LobbySnapshot {
+                    players: self.players.keys().copied().collect(),
+                    parties: self.parties.values().cloned().collect(),
+                    archived: self.archive.parties().cloned().collect(),
+                });
+            }
+            LobbyMessage::Kick { player_id, reply } => {
+                let _ = reply.send(self.kick(player_id));
+            }
+            LobbyMessage::Cancel { party_id, reply } => {
+                let _ = reply.send(match self.parties.get(&party_id) {
+                    Some(party) => {
+                        party.cancel().await;
+                        true
+                    }
+                    None => false,
+                });
+            }
+            LobbyMessage::Broadcast(message) => self.broadcast(Status::Broadcast, &message),
+            LobbyMessage::Shutdown { done } => {
+                self.shutdown().await;
+                let _ = done.send(());
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn party_ended(&mut self, ended: Result<Party, JoinError>) {
+        let party = match ended {
+            Ok(party) => party,
+            Err(e) => {
+                Log::error(format!("party stopped unexpectedly: {:?}", e));
+                return;
+            }
+        };
+        self.parties.remove(&party.id);
+        self.metrics.set_active_parties(self.parties.len());
+        self.waiting.retain(|(party_id, _)| *party_id != party.id);
+        if party.status == Status::Finished {
+            self.archive.push(party);
+        }
+    }
+
+    /// Parties waiting for longer than `bot_wait` get a bot as second player.
+    async fn seat_bots(&mut self) {
+        let Some((seat, wait)) = &self.seat else {
+            return;
+        };
+        while let Some((party_id, created)) = self.waiting.front() {
+            if created.elapsed() < *wait {
+                break;
+            }
+            if let Some(party) = self
+                .parties
+                .get(party_id)
+                .filter(|party| party.is_running())
+            {
+                seat.seat(party, Arc::clone(&self.metrics)).await;
+            }
+            self.waiting.pop_front();
+        }
+    }
+
+    async fn expire_parties(&mut self) {
+        let Some(ttl) = self.party_ttl else {
+            return;
+        };
+        while let Some((party_id, created)) = self.waiting.front() {
+            if created.elapsed() < ttl {
+                break;
+            }
+            if let Some(party) = self.parties.get(party_id) {
+                Log::entry(Level::Info)
+                    .party(*party_id)
+                    .message("Party expired");
+                party.cancel().await;
+            }
+            self.waiting.pop_front();
+        }
+    }
+
+    fn kick(&mut self, player_id: u32) -> bool {
+        let Some(outbox) = self.players.get(&player_id) else {
+            return false;
+        };
+        Log::entry(Level::Info)
+            .player(player_id)
+            .message("Kicking player");
+        outbox.send(&LobbyActor::notice(player_id, Status::Kicked, ""));
+        outbox.close();
+        true
+    }
+
+    fn broadcast(&self, status: Status, message: &str) {
+        for (player_id, outbox) in self.players.iter() {
+            outbox.send(&LobbyActor::notice(*player_id, status.clone(), message));
+        }
+    }
+
+    fn notice(player_id: u32, status: Status, message: &str) -> Protocol {
+        Protocol {
+            player: Player {
+                id: player_id,
+                ..Default::default()
+            },
+            party_status: status,
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    async fn shutdown(&mut self) {
+        self.broadcast(Status::Shutdown, "");
+
+        for party in self.parties.values() {
+            party.shutdown().await;
+        }
+        while self.running.join_next().await.is_some() {}
+        self.archive.flush().await;
+    }
+
+    fn create_game(&mut self, protocol: &Protocol) -> Option<PartyHandle> {
+        let Some(outbox) = self.players.get(&protocol.player.id) else {
+            Log::entry(Level::Warn)
+                .player(protocol.player.id)
+                .message("Unknown player");
+            return None;
+        };
+
+        let mut party = Party {
+            id: rand::thread_rng().gen::<u32>(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            status: Status::WaitingPlayer,
+            player1: Player::default(),
+            player2: protocol.player.clone(),
+            bet: protocol.bet,
+            total_round: protocol.total_round,
+            ..Default::default()
+        };
+        for _ in 0..protocol.total_round {
+            party.party_round.round_played.push((
+                (Player::default(), PlayStatus::default(), 0),
+                (Player::default(), PlayStatus::default(), 0),
+            ))
+        }
+
+        let mut protocol_send = protocol.clone();
+        protocol_send.party_id = party.id;
+        protocol_send.party_status = Status::Created;
+
+        let handle = PartyHandle::spawn(
+            &mut self.running,
+            party,
+            outbox.clone(),
+            Arc::clone(&self.settings),
+            Arc::clone(&self.metrics),
+        );
+        self.waiting.push_back((handle.id, Instant::now()));
+        self.parties.insert(handle.id, handle.clone());
+        self.metrics.set_active_parties(self.parties.len());
+
+        outbox.send(&protocol_send);
+        Some(handle)
+    }
+
+    async fn join_game(&mut self, protocol: &Protocol) -> Option<PartyHandle> {
+        let Some(outbox) = self.players.get(&protocol.player.id) else {
+            Log::entry(Level::Warn)
+                .player(protocol.player.id)
+                .message("Unknown player");
+            return None;
+        };
+
+        while let Some((party_id, _)) = self.waiting.pop_front() {
+            match self.parties.get(&party_id) {
+                Some(party) if party.is_running() => {
+                    party.join(protocol.player.clone(), outbox.clone()).await;
+                    return Some(party.clone());
+                }
+                _ => continue,
+            }
+        }
+        Log::entry(Level::Warn)
+            .player(protocol.player.id)
+            .message("No party found");
+        None
+    }
+}
@@ -1,10 +1,21 @@
-mod controller;
+use prisoner_dilemma::admin::Admin;
+use prisoner_dilemma::api::Api;
+use prisoner_dilemma::controller::Controller;
+use prisoner_dilemma::seat::BotSeat;
+use settings::{Log, Settings};
 
-use controller::Controller;
-use settings::Settings;
-
-fn main() {
-    let settings = Settings::load("../settings/settings.json");
-    let crl = Controller::new(&settings);
-    crl.run();
+#[tokio::main]
+async fn main() {
+    let settings = Settings::from_args("server");
+    let seat = match BotSeat::load(&settings) {
+        Ok(seat) => seat,
+        Err(e) => {
+            Log::error(format!("bot_seat {}", e));
+            std::process::exit(1);
+        }
+    };
+    let crl = Controller::new(&settings, seat).await;
+    Admin::spawn(&settings, crl.lobby.clone(), crl.metrics.clone());
+    Api::spawn(&settings, crl.lobby.clone(), crl.metrics.clone()).await;
+    crl.run().await;
 }
@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Export failures kept for the admin console.
+const EXPORT_FAILURES_KEPT: usize = 20;
+
+/// A party whose result could not be exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportFailure {
+    pub party_id: u32,
+    pub error: String,
+}
+
+/// Operational counters and gauges of the back server, shared by the
+/// controller, the lobby, the parties and the connection writers.
+///
+/// Rates such as rounds per second are left to the scraper, e.g.
+/// `rate(prisoner_rounds_total[1m])`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    connected_clients: AtomicU64,
+    active_parties: AtomicU64,
+    rounds: AtomicU64,
+    cooperations: AtomicU64,
+    betrayals: AtomicU64,
+    decode_errors: AtomicU64,
+    write_failures: AtomicU64,
+    dropped_clients: AtomicU64,
+    export_failures: AtomicU64,
+    recent_export_failures: Mutex<VecDeque<ExportFailure>>,
+}
+
+impl Metrics {
+    pub fn client_connected(&self) {
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_disconnected(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn set_active_parties(&self, parties: usize) {
+        self.active_parties.store(parties as u64, Ordering::Relaxed);
+    }
+
+    /// A round was resolved with `cooperations` of its two moves cooperating.
+    pub fn round_resolved(&self, cooperations: u64) {
+        self.rounds.fetch_add(1, Ordering::Relaxed);
+        self.cooperations.fetch_add(cooperations, Ordering::Relaxed);
+        self.betrayals
+            .fetch_add(2 - cooperations, Ordering::Relaxed);
+    }
+
+    pub fn decode_error(&self) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn write_failure(&self) {
+        self.write_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_dropped(&self) {
+        self.dropped_clients.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn export_failed(&self, party_id: u32, error: String) {
+        self.export_failures.fetch_add(1, Ordering::Relaxed);
+        let mut recent = self
+            .recent_export_failures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if recent.len() == EXPORT_FAILURES_KEPT {
+            recent.pop_front();
+        }
+        recent.push_back(ExportFailure { party_id, error });
+    }
+
+    /// Last export failures, oldest first.
+    pub fn export_failures(&self) -> Vec<ExportFailure> {
+        let recent = self
+            .recent_export_failures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        recent.iter().cloned().collect()
+    }
+
+    pub fn connected_clients(&self) -> u64 {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    pub fn rounds(&self) -> u64 {
+        self.rounds.load(Ordering::Relaxed)
+    }
+
+    /// Share of cooperating moves over every resolved round, 0 before the
+    /// first one.
+    pub fn cooperation_rate(&self) -> f64 {
+        let cooperations = self.cooperations.load(Ordering::Relaxed);
+        let moves = cooperations + self.betrayals.load(Ordering::Relaxed);
+        match moves {
+            0 => 0.0,
+            _ => cooperations as f64 / moves as f64,
+        }
+    }
+
+    /// Every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let load = |metric: &AtomicU64| metric.load(Ordering::Relaxed);
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(text, "# HELP {} {}", name, help);
+            let _ = writeln!(text, "# TYPE {} {}", name, kind);
+            let _ = writeln!(text, "{} {}", name, value);
+        };
+
+        metric(
+            "prisoner_connected_clients",
+            "gauge",
+            "Clients currently connected.",
+            load(&self.connected_clients).to_string(),
+        );
+        metric(
+            "prisoner_active_parties",
+            "gauge",
+            "Parties waiting for a player or being played.",
+            load(&self.active_parties).to_string(),
+        );
+        metric(
+            "prisoner_rounds_total",
+            "counter",
+            "Rounds resolved.",
+            load(&self.rounds).to_string(),
+        );
+        metric(
+            "prisoner_cooperations_total",
+            "counter",
+            "Cooperating moves in resolved rounds.",
+            load(&self.cooperations).to_string(),
+        );
+        metric(
+            "prisoner_betrayals_total",
+            "counter",
+            "Betraying moves in resolved rounds.",
+            load(&self.betrayals).to_string(),
+        );
+        metric(
+            "prisoner_cooperation_rate",
+            "gauge",
+            "Share of cooperating moves over every resolved round.",
+            self.cooperation_rate().to_string(),
+        );
+        metric(
+            "prisoner_decode_errors_total",
+            "counter",
+            "Frames received from clients that could not be decoded.",
+            load(&self.decode_errors).to_string(),
+        );
+        metric(
+            "prisoner_write_failures_total",
+            "counter",
+            "Writes to client sockets that failed or timed out.",
+            load(&self.write_failures).to_string(),
+        );
+        metric(
+            "prisoner_dropped_clients_total",
+            "counter",
+            "Clients dropped for letting their write queue fill up.",
+            load(&self.dropped_clients).to_string(),
+        );
+        metric(
+            "prisoner_export_failures_total",
+            "counter",
+            "Finished parties whose result could not be exported.",
+            load(&self.export_failures).to_string(),
+        );
+        text
+    }
+}
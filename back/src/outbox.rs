@@ -0,0 +1,152 @@
+use crate::metrics::Metrics;
+use settings::{Log, Protocol};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+
+/// Messages a connection may have pending before it is considered too slow.
+pub const OUTBOX_SIZE: usize = 64;
+
+enum Outgoing {
+    Message(Protocol),
+    /// Written messages are flushed, then the connection is dropped.
+    Close,
+}
+
+/// Reading end of a local `Outbox`.
+pub struct Inbox {
+    receiver: mpsc::Receiver<Outgoing>,
+}
+
+impl Inbox {
+    /// Next message, `None` once the outbox is closed or every clone dropped.
+    pub async fn recv(&mut self) -> Option<Protocol> {
+        match self.receiver.recv().await {
+            Some(Outgoing::Message(protocol)) => Some(protocol),
+            Some(Outgoing::Close) | None => None,
+        }
+    }
+}
+
+/// Bounded queue of messages waiting to be written to one player connection.
+///
+/// Each connection owns a writer task draining this queue, so actors only
+/// push a message and never wait on a socket while handling their state.
+/// A client that lets the queue fill up, or whose socket fails, is dropped:
+/// the writer task stops and `closed` resolves for the reading side. Once
+/// every `Outbox` clone is dropped, the writer flushes what is left and ends.
+#[derive(Clone)]
+pub struct Outbox {
+    sender: mpsc::Sender<Outgoing>,
+    drop_connection: Arc<Notify>,
+    metrics: Arc<Metrics>,
+}
+
+impl Outbox {
+    /// Starts the writer task. A single write taking longer than
+    /// `write_timeout` drops the connection.
+    pub fn spawn(
+        writer: OwnedWriteHalf,
+        write_timeout: Duration,
+        metrics: Arc<Metrics>,
+    ) -> (Self, JoinHandle<()>) {
+        let (sender, receiver) = mpsc::channel(OUTBOX_SIZE);
+        let drop_connection = Arc::new(Notify::new());
+        let writer = tokio::spawn(Outbox::write_loop(
+            writer,
+            receiver,
+            write_timeout,
+            Arc::clone(&drop_connection),
+            Arc::clone(&metrics),
+        ));
+        let outbox = Self {
+            sender,
+            drop_connection,
+            metrics,
+        };
+        (outbox, writer)
+    }
+
+    /// An outbox read in process instead of written to a socket, e.g. by a
+    /// bot seat.
+    pub fn local(metrics: Arc<Metrics>) -> (Self, Inbox) {
+        let (sender, receiver) = mpsc::channel(OUTBOX_SIZE);
+        let outbox = Self {
+            sender,
+            drop_connection: Arc::new(Notify::new()),
+            metrics,
+        };
+        (outbox, Inbox { receiver })
+    }
+
+    pub fn send(&self, protocol: &Protocol) {
+        self.push(Outgoing::Message(protocol.clone()));
+    }
+
+    /// Drops the connection once the messages already queued are written.
+    pub fn close(&self) {
+        self.push(Outgoing::Close);
+    }
+
+    fn push(&self, outgoing: Outgoing) {
+        match self.sender.try_send(outgoing) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                Log::warn("Client is too slow, dropping it");
+                self.metrics.client_dropped();
+                self.drop_connection.notify_one();
+            }
+            Err(TrySendError::Closed(_)) => {
+                Log::warn("Connection writer is closed");
+            }
+        }
+    }
+
+    /// Resolves once the writer task has stopped and the connection should
+    /// be dropped.
+    pub async fn closed(&self) {
+        self.sender.closed().await
+    }
+
+    async fn write_loop(
+        mut writer: OwnedWriteHalf,
+        mut receiver: mpsc::Receiver<Outgoing>,
+        write_timeout: Duration,
+        drop_connection: Arc<Notify>,
+        metrics: Arc<Metrics>,
+    ) {
+        loop {
+            let protocol = tokio::select! {
+                outgoing = receiver.recv() => match outgoing {
+                    Some(Outgoing::Message(protocol)) => protocol,
+                    Some(Outgoing::Close) | None => return,
+                },
+                _ = drop_connection.notified() => return,
+            };
+
+            let bytes = protocol.to_bytes();
+            let written = tokio::time::timeout(write_timeout, async {
+                writer.write_all(&bytes).await?;
+                writer.flush().await
+            })
+            .await;
+            match written {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => {
+                    Log::error(format!("writing to socket: {:?}", e));
+                    metrics.write_failure();
+                    return;
+                }
+                Err(_) => {
+                    Log::error("writing to socket: timed out");
+                    metrics.write_failure();
+                    return;
+                }
+            }
+        }
+    }
+}
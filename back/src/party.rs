@@ -0,0 +1,339 @@
+use crate::export;
+use crate::lobby::CHANNEL_SIZE;
+use crate::metrics::Metrics;
+use crate::outbox::Outbox;
+use settings::{Level, Log, Party, PlayStatus, Player, Protocol, Settings, Status};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinSet;
+
+enum PartyMessage {
+    Join { player: Player, outbox: Outbox },
+    Play(Protocol),
+    Leave(u32),
+    Cancel,
+    Inspect(oneshot::Sender<Party>),
+    Shutdown,
+}
+
+/// Handle to a party actor. The actor owns the `Party` and is the only task
+/// touching it, so parties never contend with each other. The actor stops
+/// once the party is over and hands the `Party` back through its task.
+#[derive(Clone)]
+pub struct PartyHandle {
+    pub id: u32,
+    sender: mpsc::Sender<PartyMessage>,
+}
+
+impl PartyHandle {
+    pub fn spawn(
+        parties: &mut JoinSet<Party>,
+        party: Party,
+        creator: Outbox,
+        settings: Arc<Settings>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(CHANNEL_SIZE);
+        let id = party.id;
+        let mut outboxes = HashMap::new();
+        outboxes.insert(party.player2.id, creator);
+        let actor = PartyActor {
+            party,
+            outboxes,
+            over: false,
+            settings,
+            metrics,
+        };
+        parties.spawn(actor.run(receiver));
+        Self { id, sender }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.sender.is_closed()
+    }
+
+    pub async fn join(&self, player: Player, outbox: Outbox) {
+        self.send(PartyMessage::Join { player, outbox }).await;
+    }
+
+    pub async fn play(&self, protocol: Protocol) {
+        self.send(PartyMessage::Play(protocol)).await;
+    }
+
+    /// A player disconnected: a waiting party is dropped, a running one is
+    /// won by forfeit by the other player.
+    pub async fn leave(&self, player_id: u32) {
+        self.send(PartyMessage::Leave(player_id)).await;
+    }
+
+    /// Drops the party. A running party is saved and both players are told
+    /// it is closed.
+    pub async fn cancel(&self) {
+        self.send(PartyMessage::Cancel).await;
+    }
+
+    /// Copy of the party state, `None` once the actor has stopped.
+    pub async fn inspect(&self) -> Option<Party> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(PartyMessage::Inspect(reply)).await;
+        receiver.await.ok()
+    }
+
+    /// Stops the actor, exporting a snapshot of the party if it is still
+    /// running.
+    pub async fn shutdown(&self) {
+        self.send(PartyMessage::Shutdown).await;
+    }
+
+    /// Messages to a party that has already ended, e.g. a disconnect after
+    /// the last round, are dropped.
+    async fn send(&self, message: PartyMessage) {
+        if self.sender.send(message).await.is_err() {
+            Log::debug("Party is not running");
+        }
+    }
+}
+
+struct PartyActor {
+    party: Party,
+    outboxes: HashMap<u32, Outbox>,
+    over: bool,
+    settings: Arc<Settings>,
+    metrics: Arc<Metrics>,
+}
+
+impl PartyActor {
+    async fn run(mut self, mut receiver: mpsc::Receiver<PartyMessage>) -> Party {
+        while let Some(message) = receiver.recv().await {
+            match message {
+                PartyMessage::Join { player, outbox } => self.join_game(player, outbox),
+                PartyMessage::Play(protocol) => self.process_game(&protocol).await,
+                PartyMessage::Leave(player_id) => self.leave(player_id).await,
+                PartyMessage::Cancel => self.cancel().await,
+                PartyMessage::Inspect(reply) => {
+                    let _ = reply.send(self.party.clone());
+                }
+                PartyMessage::Shutdown => {
+                    self.snapshot().await;
+                    break;
+                }
+            }
+            if self.over {
+                break;
+            }
+        }
+        self.party
+    }
+
+    async fn leave(&mut self, player_id: u32) {
+        self.outboxes.remove(&player_id);
+        match self.party.status {
+            Status::WaitingPlayer => {
+                Log::entry(Level::Info)
+                    .party(self.party.id)
+                    .message("Party abandoned");
+                self.over = true;
+            }
+            Status::Started => {
+                let (winner, looser) = if player_id == self.party.player1.id {
+                    (self.party.player2.clone(), self.party.player1.clone())
+                } else {
+                    (self.party.player1.clone(), self.party.player2.clone())
+                };
+                Log::entry(Level::Info)
+                    .party(self.party.id)
+                    .player(player_id)
+                    .message("Player left the party");
+                self.send_to(&winner, Status::Win);
+                self.party.winner = Some(winner);
+                self.party.looser = Some(looser);
+                self.finish().await;
+            }
+            _ => (),
+        }
+    }
+
+    async fn cancel(&mut self) {
+        match self.party.status {
+            Status::WaitingPlayer => {
+                Log::entry(Level::Info)
+                    .party(self.party.id)
+                    .message("Party cancelled");
+                let creator = self.party.player2.clone();
+                self.send_to(&creator, Status::Finished);
+            }
+            Status::Started => {
+                Log::entry(Level::Info)
+                    .party(self.party.id)
+                    .message("Party cancelled");
+                let players_to_send = [self.party.player1.clone(), self.party.player2.clone()];
+                for player in players_to_send.iter() {
+                    self.send_to(player, Status::Finished);
+                }
+                self.snapshot().await;
+            }
+            _ => return,
+        }
+        self.over = true;
+    }
+
+    async fn finish(&mut self) {
+        self.party.status = Status::Finished;
+        self.over = true;
+        self.export().await;
+    }
+
+    async fn snapshot(&self) {
+        if self.party.status != Status::Started {
+            return;
+        }
+        Log::entry(Level::Info)
+            .party(self.party.id)
+            .field("round", self.party.round)
+            .field("total_round", self.party.total_round)
+            .message("Saving party");
+        self.export().await;
+    }
+
+    /// Export failures are logged and kept for the admin console, the party
+    /// goes on to the archive either way.
+    async fn export(&self) {
+        let party = self.party.clone();
+        let settings = Arc::clone(&self.settings);
+        let result = tokio::task::spawn_blocking(move || export::export(&settings, &party))
+            .await
+            .unwrap_or_else(|e| Err(export::ExportError::Panicked(e.to_string())));
+        match result {
+            Ok(path) => Log::entry(Level::Info)
+                .party(self.party.id)
+                .field("path", path.display().to_string())
+                .message("Party exported"),
+            Err(e) => {
+                Log::entry(Level::Error)
+                    .party(self.party.id)
+                    .message(format!("Export failed: {}", e));
+                self.metrics.export_failed(self.party.id, e.to_string());
+            }
+        }
+    }
+
+    fn join_game(&mut self, player: Player, outbox: Outbox) {
+        if self.party.status != Status::WaitingPlayer {
+            Log::entry(Level::Warn)
+                .party(self.party.id)
+                .message("Party is not waiting");
+            return;
+        }
+        self.outboxes.insert(player.id, outbox);
+        self.party.player1 = player;
+        self.party.status = Status::Started;
+
+        let players_to_send = [self.party.player1.clone(), self.party.player2.clone()];
+        for player in players_to_send.iter() {
+            self.send_to(player, Status::Started);
+        }
+    }
+
+    async fn process_game(&mut self, protocol: &Protocol) {
+        if self.party.status != Status::Started {
+            Log::entry(Level::Warn)
+                .party(self.party.id)
+                .message("Party is not started");
+            return;
+        }
+
+        let index = (self.party.round - 1) as usize;
+        let party = &mut self.party;
+        let Some(current_game) = party.party_round.round_played.get_mut(index) else {
+            return;
+        };
+
+        let (side, player) = if protocol.player.id == party.player1.id {
+            (&mut current_game.0, &party.player1)
+        } else if protocol.player.id == party.player2.id {
+            (&mut current_game.1, &party.player2)
+        } else {
+            Log::entry(Level::Warn)
+                .party(party.id)
+                .player(protocol.player.id)
+                .message("Player is not in party");
+            return;
+        };
+        if side.0.id != 0 {
+            Log::entry(Level::Warn)
+                .party(party.id)
+                .player(protocol.player.id)
+                .message("Player already played");
+            return;
+        }
+        side.0 = player.clone();
+        side.1 = protocol.play.clone();
+
+        if current_game.0 .0.id == 0 || current_game.1 .0.id == 0 {
+            return;
+        }
+
+        let payoff = &self.settings.payoff;
+        party.player1.money += payoff.money(&current_game.0 .1, &current_game.1 .1, party.bet);
+        party.player2.money += payoff.money(&current_game.1 .1, &current_game.0 .1, party.bet);
+        current_game.0 .0.money = party.player1.money;
+        current_game.0 .2 = party.player1.money as u32;
+        current_game.1 .0.money = party.player2.money;
+        current_game.1 .2 = party.player2.money as u32;
+        self.metrics.round_resolved(
+            [&current_game.0 .1, &current_game.1 .1]
+                .iter()
+                .filter(|play| ***play == PlayStatus::Cooperate)
+                .count() as u64,
+        );
+
+        party.round += 1;
+        let players_to_send = [party.player1.clone(), party.player2.clone()];
+        if party.round > party.total_round {
+            for (player, other) in [
+                (&players_to_send[0], &players_to_send[1]),
+                (&players_to_send[1], &players_to_send[0]),
+            ] {
+                let status = PartyActor::get_party_status(player, other);
+                if status == Status::Win {
+                    self.party.winner = Some(player.clone());
+                    self.party.looser = Some(other.clone());
+                }
+                self.send_to(player, status);
+            }
+            self.finish().await;
+        } else {
+            for player in players_to_send.iter() {
+                self.send_to(player, Status::Started);
+            }
+        }
+    }
+
+    fn send_to(&self, player: &Player, status: Status) {
+        let Some(outbox) = self.outboxes.get(&player.id) else {
+            return;
+        };
+        let protocol_send = Protocol {
+            player: player.clone(),
+            party_status: status,
+            total_round: self.party.total_round,
+            round: self.party.round,
+            bet: self.party.bet,
+            party_id: self.party.id,
+            play: PlayStatus::Stanby,
+            ..Default::default()
+        };
+        outbox.send(&protocol_send);
+    }
+
+    fn get_party_status(player1: &Player, player2: &Player) -> Status {
+        if player1.money < player2.money {
+            Status::Lose
+        } else if player1.money > player2.money {
+            Status::Win
+        } else {
+            Status::Equal
+        }
+    }
+}
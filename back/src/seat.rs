@@ -0,0 +1,137 @@
+use crate::metrics::Metrics;
+use crate::outbox::{Inbox, Outbox};
+use crate::party::PartyHandle;
+use engine::{Factory, Round, Strategy, Turn};
+use rand::Rng;
+use settings::{Level, Log, Party, PlayStatus, Player, Protocol, Settings, Status};
+use std::sync::Arc;
+
+/// A bot played by the server in the free seat of a party, configured by
+/// `bot_seat`.
+///
+/// The seat is a player without connection: the party sends its frames to
+/// a local outbox, whose `Started` frames tell the seat task to play, and
+/// the history of the party is read from the party actor itself. Moves go
+/// straight to the party actor. Strategies may be scripts taking up to
+/// `move_timeout`, so moves are chosen on the blocking thread pool.
+#[derive(Clone)]
+pub struct BotSeat {
+    factory: Arc<Factory>,
+}
+
+impl BotSeat {
+    /// Reads the `bot_seat` strategy and builds it once to report its
+    /// errors. `None` without `bot_seat`.
+    pub fn load(settings: &Settings) -> Result<Option<Self>, String> {
+        if settings.bot_seat.is_empty() {
+            return Ok(None);
+        }
+        let factory = Factory::new(&settings.bot_seat, settings)?;
+        factory.build()?;
+        Ok(Some(Self {
+            factory: Arc::new(factory),
+        }))
+    }
+
+    /// Seats a new instance of the strategy in `party`, which must be
+    /// waiting for a player. The strategy is built by the seat task.
+    pub async fn seat(&self, party: &PartyHandle, metrics: Arc<Metrics>) {
+        let player = Player {
+            id: rand::thread_rng().gen::<u32>(),
+            ..Default::default()
+        };
+        let (outbox, inbox) = Outbox::local(metrics);
+        tokio::spawn(BotSeat::play(
+            party.clone(),
+            player.id,
+            Arc::clone(&self.factory),
+            inbox,
+        ));
+        party.join(player, outbox).await;
+    }
+
+    async fn play(party: PartyHandle, player_id: u32, factory: Arc<Factory>, mut inbox: Inbox) {
+        let built = tokio::task::spawn_blocking(move || factory.build())
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+        let mut strategy = match built {
+            Ok(strategy) => strategy,
+            Err(e) => {
+                Log::entry(Level::Error)
+                    .party(party.id)
+                    .player(player_id)
+                    .message(format!("Cannot seat a bot: {}", e));
+                party.leave(player_id).await;
+                return;
+            }
+        };
+        Log::entry(Level::Info)
+            .party(party.id)
+            .player(player_id)
+            .field("bot", strategy.name())
+            .message("Bot seated");
+        strategy.start();
+
+        while let Some(mut frame) = inbox.recv().await {
+            if frame.party_status != Status::Started {
+                return;
+            }
+            let Some(state) = party.inspect().await else {
+                return;
+            };
+            let chosen = tokio::task::spawn_blocking(move || {
+                frame.play = BotSeat::next_move(strategy.as_mut(), &state, &frame);
+                (strategy, frame)
+            })
+            .await;
+            (strategy, frame) = match chosen {
+                Ok(chosen) => chosen,
+                Err(e) => {
+                    Log::entry(Level::Error)
+                        .party(party.id)
+                        .player(player_id)
+                        .message(format!("Bot stopped: {}", e));
+                    party.leave(player_id).await;
+                    return;
+                }
+            };
+            party.play(frame).await;
+        }
+    }
+
+    /// The move of the seat for the round `frame` started, from the rounds
+    /// `party` already resolved.
+    fn next_move(strategy: &mut dyn Strategy, party: &Party, frame: &Protocol) -> PlayStatus {
+        let first = party.player1.id == frame.player.id;
+        let history: Vec<Round> = party
+            .party_round
+            .round_played
+            .iter()
+            .take(frame.round.saturating_sub(1) as usize)
+            .map(|(player1, player2)| {
+                let (mine, other) = if first {
+                    (player1, player2)
+                } else {
+                    (player2, player1)
+                };
+                Round {
+                    play: mine.1.clone(),
+                    other: other.1.clone(),
+                }
+            })
+            .collect();
+        let other_money = if first {
+            party.player2.money
+        } else {
+            party.player1.money
+        };
+        strategy.next_move(&Turn {
+            round: frame.round,
+            total_round: frame.total_round,
+            bet: frame.bet,
+            money: frame.player.money,
+            other_money,
+            history: &history,
+        })
+    }
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::{connect, create, receive};
+use prisoner_dilemma::admin::Admin;
+use settings::{Protocol, Settings, Status};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start() -> (SocketAddr, Admin) {
+        let controller = common::controller(Settings::default()).await;
+        let admin = Admin::new(
+            controller.lobby.clone(),
+            controller.metrics.clone(),
+            Arc::new(Settings::default()),
+        );
+        (common::serve(controller), admin)
+    }
+
+    #[tokio::test]
+    async fn lists_players_and_parties() {
+        let (addr, admin) = start().await;
+        let (mut stream, protocol) = connect(addr).await;
+        let mut created = protocol.clone();
+        create(&mut stream, &mut created, 3).await;
+
+        let players = admin.execute("players").await;
+        assert!(players.contains(&format!("player #{}", protocol.player.id)));
+        assert!(players.contains(&format!("party #{}", created.party_id)));
+
+        let parties = admin.execute("parties").await;
+        assert!(parties.contains(&format!("party #{}  WaitingPlayer", created.party_id)));
+        assert!(admin
+            .execute(&format!("party {}", created.party_id))
+            .await
+            .contains("round 1/3"));
+    }
+
+    #[tokio::test]
+    async fn broadcast_reaches_players() {
+        let (addr, admin) = start().await;
+        let (mut stream, _) = connect(addr).await;
+
+        assert_eq!(admin.execute("broadcast hello all").await, "Message sent");
+
+        let protocol = receive(&mut stream).await;
+        assert_eq!(protocol.party_status, Status::Broadcast);
+        assert_eq!(protocol.message, "hello all");
+    }
+
+    #[tokio::test]
+    async fn kicked_player_is_disconnected() {
+        let (addr, admin) = start().await;
+        let (mut stream, protocol) = connect(addr).await;
+
+        let answer = admin.execute(&format!("kick {}", protocol.player.id)).await;
+        assert_eq!(answer, format!("Player #{} kicked", protocol.player.id));
+        assert_eq!(receive(&mut stream).await.party_status, Status::Kicked);
+
+        let mut buffer = [0; Protocol::HEADER_SIZE];
+        assert_eq!(stream.read(&mut buffer).await.unwrap(), 0);
+        assert_eq!(admin.execute("kick 0").await, "No player #0");
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_commands() {
+        let (_, admin) = start().await;
+
+        assert!(admin
+            .execute("frobnicate")
+            .await
+            .starts_with("Unknown command"));
+        assert!(admin.execute("kick nobody").await.starts_with("Usage"));
+        assert!(admin
+            .execute("loglevel LOUD")
+            .await
+            .starts_with("Unknown level"));
+    }
+}
@@ -0,0 +1,246 @@
+use prisoner_dilemma::aggregate::{self, Aggregate, HeadToHead, PlayerTotals};
+use prisoner_dilemma::export::{self, RoundRecord};
+use serde_json::json;
+use settings::{ExportFormat, Party, PartyRound, PlayStatus, Player, Settings, Status};
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aggregate-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// A party between `player1` and `player2` with the given moves, the
+    /// default payoff and a bet of 10.
+    fn party(id: u32, player1: u32, player2: u32, plays: &[(PlayStatus, PlayStatus)]) -> Party {
+        let payoff = Settings::default().payoff;
+        let mut balances = (Player::STARTING_MONEY, Player::STARTING_MONEY);
+        let mut round_played = Vec::new();
+        for (play1, play2) in plays {
+            balances.0 += payoff.money(play1, play2, 10);
+            balances.1 += payoff.money(play2, play1, 10);
+            round_played.push((
+                (
+                    Player {
+                        id: player1,
+                        money: balances.0,
+                    },
+                    play1.clone(),
+                    0,
+                ),
+                (
+                    Player {
+                        id: player2,
+                        money: balances.1,
+                    },
+                    play2.clone(),
+                    0,
+                ),
+            ));
+        }
+        let (one, two) = (
+            Player {
+                id: player1,
+                money: balances.0,
+            },
+            Player {
+                id: player2,
+                money: balances.1,
+            },
+        );
+        let winner = match balances.0.partial_cmp(&balances.1) {
+            Some(std::cmp::Ordering::Greater) => Some(one.clone()),
+            Some(std::cmp::Ordering::Less) => Some(two.clone()),
+            _ => None,
+        };
+        Party {
+            id,
+            created: 1_700_000_000,
+            total_round: plays.len() as u32,
+            round: plays.len() as u32 + 1,
+            status: Status::Finished,
+            bet: 10,
+            player1: one,
+            player2: two,
+            looser: None,
+            winner,
+            party_round: PartyRound { round_played },
+        }
+    }
+
+    fn parties() -> Vec<Party> {
+        let (c, d) = (PlayStatus::Cooperate, PlayStatus::Betrail);
+        vec![
+            // 1 exploits 2 in the second round
+            party(1, 1, 2, &[(c.clone(), c.clone()), (d.clone(), c.clone())]),
+            // 1 and 3 cooperate throughout: a draw
+            party(2, 1, 3, &[(c.clone(), c.clone()), (c.clone(), c.clone())]),
+            // 2 and 1 betray each other, then 2 is exploited
+            party(3, 2, 1, &[(d.clone(), d.clone()), (c, d)]),
+        ]
+    }
+
+    fn records(parties: &[Party]) -> Vec<RoundRecord> {
+        parties.iter().flat_map(RoundRecord::of).collect()
+    }
+
+    #[test]
+    fn totals_head_to_head_and_cooperation() {
+        let aggregate = Aggregate::new(&records(&parties()));
+
+        assert_eq!(aggregate.players.len(), 3);
+        assert_eq!(
+            aggregate.players[0],
+            PlayerTotals {
+                player_id: 1,
+                parties: 3,
+                wins: 2,
+                losses: 0,
+                draws: 1,
+                unfinished: 0,
+                rounds: 6,
+                cooperations: 3,
+                balance: 125.0 + 110.0 + 110.0,
+            }
+        );
+        assert_eq!(
+            aggregate.head_to_head[0],
+            HeadToHead {
+                player_id: 1,
+                opponent_id: 2,
+                parties: 2,
+                wins: 2,
+                losses: 0,
+                draws: 0,
+                balance: 125.0 + 110.0,
+            }
+        );
+
+        let cooperation = &aggregate.cooperation;
+        assert_eq!(cooperation.rounds, 6);
+        assert_eq!(cooperation.mutual_cooperations, 3);
+        assert_eq!(cooperation.mutual_betrayals, 1);
+        assert_eq!(cooperation.exploitations, 2);
+        assert_eq!(cooperation.rate(), 8.0 / 12.0);
+        assert_eq!(cooperation.by_round, vec![(4, 6), (4, 6)]);
+    }
+
+    #[test]
+    fn parties_of_other_runs_keep_their_ids_apart() {
+        let mut parties = parties();
+        let mut later = parties[0].clone();
+        later.created += 3600;
+        parties.push(later);
+        let aggregate = Aggregate::new(&records(&parties));
+
+        assert_eq!(aggregate.players[0].parties, 4);
+        assert_eq!(aggregate.cooperation.rounds, 8);
+    }
+
+    #[test]
+    fn cancelled_parties_are_unfinished() {
+        let mut cancelled = parties().remove(0);
+        cancelled.status = Status::Started;
+        cancelled.winner = None;
+        let aggregate = Aggregate::new(&records(&[cancelled]));
+
+        assert!(aggregate
+            .players
+            .iter()
+            .all(|totals| totals.unfinished == 1 && totals.wins + totals.draws == 0));
+    }
+
+    #[test]
+    fn exports_are_read_back_once() {
+        let dir = temp_dir("read");
+        let parties = parties();
+        for format in [ExportFormat::Csv, ExportFormat::Jsonl] {
+            let settings = Settings {
+                export_dir: dir.to_string_lossy().to_string(),
+                export_format: format,
+                ..Default::default()
+            };
+            export::export_all(&settings, &parties).unwrap();
+            for party in parties.iter() {
+                export::export(&settings, party).unwrap();
+            }
+        }
+        std::fs::write(dir.join("notes.csv"), "some,other,file\n").unwrap();
+
+        let read = aggregate::read_exports(&dir).unwrap();
+        assert_eq!(read, records(&parties));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn workbooks_alone_are_an_error() {
+        let dir = temp_dir("workbooks");
+        let settings = Settings {
+            export_dir: dir.to_string_lossy().to_string(),
+            export_format: ExportFormat::Xlsx,
+            ..Default::default()
+        };
+        let path = export::export_all(&settings, &parties()).unwrap();
+
+        let error = aggregate::read_exports(&dir).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(error.to_string().contains(&path.display().to_string()));
+
+        // Read along with the exports that can be
+        let settings = Settings {
+            export_format: ExportFormat::Csv,
+            ..settings
+        };
+        export::export_all(&settings, &parties()).unwrap();
+        assert_eq!(aggregate::read_exports(&dir).unwrap(), records(&parties()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn csv_fields_are_quoted() {
+        let values = [
+            json!(7),
+            json!("a,b"),
+            json!("say \"hi\""),
+            json!(null),
+            json!(0.5),
+        ];
+        let line = export::csv::line(&values);
+        assert_eq!(line, "7,\"a,b\",\"say \"\"hi\"\"\",,0.5");
+        assert_eq!(
+            export::csv::fields(&line),
+            ["7", "a,b", "say \"hi\"", "", "0.5"]
+        );
+    }
+
+    #[test]
+    fn aggregate_is_written_in_every_format() {
+        let dir = temp_dir("write");
+        let aggregate = Aggregate::new(&records(&parties()));
+
+        for format in [ExportFormat::Xlsx, ExportFormat::Csv, ExportFormat::Jsonl] {
+            let settings = Settings {
+                export_dir: dir.to_string_lossy().to_string(),
+                export_format: format,
+                ..Default::default()
+            };
+            let path = aggregate.export(&settings).unwrap();
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            if format == ExportFormat::Csv {
+                let csv = std::fs::read_to_string(&path).unwrap();
+                assert!(csv.starts_with("table,player_id,opponent_id,metric,value\n"));
+                assert!(csv.contains("players,1,,wins,2\n"));
+                assert!(csv.contains("head_to_head,2,1,losses,2\n"));
+                assert!(csv.contains("cooperation,,,mutual_betrayals,1\n"));
+            }
+        }
+        // Aggregates are not mistaken for party exports
+        let error = aggregate::read_exports(&dir).unwrap_err().to_string();
+        assert!(error.starts_with("no CSV or JSON Lines party export"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::{connect, receive, send, started_party};
+use prisoner_dilemma::api::Api;
+use settings::{PlayStatus, Settings, Status};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start() -> (SocketAddr, Api) {
+        let controller = common::controller(Settings::default()).await;
+        let api = Api::new(controller.lobby.clone(), controller.metrics.clone());
+        (common::serve(controller), api)
+    }
+
+    #[tokio::test]
+    async fn reports_players_and_waiting_parties() {
+        let (addr, api) = start().await;
+        let (mut stream, mut protocol) = connect(addr).await;
+        protocol.party_status = Status::Created;
+        protocol.total_round = 3;
+        send(&mut stream, &protocol).await;
+        let created = receive(&mut stream).await;
+
+        let (status, players) = api.respond("GET", "/players").await;
+        assert_eq!(status, 200);
+        assert_eq!(players[0]["id"], protocol.player.id);
+        assert_eq!(players[0]["party_id"], created.party_id);
+
+        let (_, waiting) = api.respond("GET", "/parties?status=waiting").await;
+        assert_eq!(waiting.as_array().unwrap().len(), 1);
+        assert_eq!(waiting[0]["id"], created.party_id);
+        let (_, running) = api.respond("GET", "/parties?status=running").await;
+        assert!(running.as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_round_history_and_stats() {
+        let (addr, api) = start().await;
+        let [(mut creator, first), (mut joiner, second)] = started_party(addr, 2).await;
+
+        for (stream, protocol, play) in [
+            (&mut creator, &first, PlayStatus::Cooperate),
+            (&mut joiner, &second, PlayStatus::Betrail),
+        ] {
+            let mut protocol = protocol.clone();
+            protocol.party_status = Status::Started;
+            protocol.play = play;
+            send(stream, &protocol).await;
+        }
+        receive(&mut creator).await;
+        receive(&mut joiner).await;
+
+        let (status, party) = api
+            .respond("GET", &format!("/parties/{}", first.party_id))
+            .await;
+        assert_eq!(status, 200);
+        assert_eq!(party["state"], "running");
+        assert_eq!(party["rounds"].as_array().unwrap().len(), 1);
+        assert_eq!(party["rounds"][0]["round"], 1);
+
+        let (_, stats) = api.respond("GET", "/stats").await;
+        assert_eq!(stats["players"], 2);
+        assert_eq!(stats["parties"]["running"], 1);
+        assert_eq!(stats["rounds_played"], 1);
+        assert_eq!(stats["cooperation_rate"], 0.5);
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_requests() {
+        let (_, api) = start().await;
+
+        assert_eq!(api.respond("POST", "/players").await.0, 405);
+        assert_eq!(api.respond("GET", "/nowhere").await.0, 404);
+        assert_eq!(api.respond("GET", "/parties/abc").await.0, 400);
+        assert_eq!(api.respond("GET", "/parties/1").await.0, 404);
+        assert_eq!(api.respond("GET", "/parties?status=lost").await.0, 400);
+    }
+
+    #[tokio::test]
+    async fn serves_json_over_http() {
+        let (_, api) = start().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(api.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /stats HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/json"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let stats: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(stats["players"], 0);
+    }
+
+    #[tokio::test]
+    async fn drops_oversized_requests() {
+        let (_, api) = start().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(api.serve(listener));
+
+        // a single line longer than any head, never ended
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let line = format!("GET /{}", "a".repeat(16 * 1024));
+        let _ = stream.write_all(line.as_bytes()).await;
+        let mut response = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut response))
+            .await
+            .expect("the server should close the connection");
+        assert!(read.is_err() || response.is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+//! Fixtures shared by the integration tests: a server on a free port of the
+//! loopback and clients talking to it.
+#![allow(dead_code)]
+
+use prisoner_dilemma::controller::Controller;
+use prisoner_dilemma::seat::BotSeat;
+use settings::{Protocol, Settings, Status};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Once;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+static WORKDIR: Once = Once::new();
+
+/// Finished parties are exported next to the working directory, keep them
+/// out of the repository: the process moves to a `run` directory under the
+/// temporary directory returned.
+pub fn workdir() -> PathBuf {
+    let dir = std::env::temp_dir().join("prisoner-dilemma-tests");
+    WORKDIR.call_once(|| {
+        let workdir = dir.join("run");
+        std::fs::create_dir_all(&workdir).unwrap();
+        std::env::set_current_dir(&workdir).unwrap();
+    });
+    dir
+}
+
+/// A controller on a free port, seating the `bot_seat` of `settings` if
+/// any, not yet accepting connections.
+pub async fn controller(settings: Settings) -> Controller {
+    workdir();
+    let settings = Settings {
+        host: String::from("127.0.0.1"),
+        port: 0,
+        admin_console: false,
+        ..settings
+    };
+    let seat = BotSeat::load(&settings).unwrap();
+    Controller::new(&settings, seat).await
+}
+
+/// Accepts connections until the test ends.
+pub fn serve(controller: Controller) -> SocketAddr {
+    let addr = controller.local_addr();
+    tokio::spawn(async move { controller.run_until(std::future::pending()).await });
+    addr
+}
+
+pub async fn start(settings: Settings) -> SocketAddr {
+    serve(controller(settings).await)
+}
+
+pub async fn send(stream: &mut TcpStream, protocol: &Protocol) {
+    stream.write_all(&protocol.to_bytes()).await.unwrap();
+}
+
+pub async fn receive(stream: &mut TcpStream) -> Protocol {
+    tokio::time::timeout(Duration::from_secs(5), Controller::read_protocol(stream))
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+/// A registered player and the frame giving its id.
+pub async fn connect(addr: SocketAddr) -> (TcpStream, Protocol) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    send(&mut stream, &Protocol::default()).await;
+    let protocol = receive(&mut stream).await;
+    (stream, protocol)
+}
+
+/// Creates a party of `total_round` rounds betting 10, `protocol` becoming
+/// the reply of the server.
+pub async fn create(stream: &mut TcpStream, protocol: &mut Protocol, total_round: u32) {
+    protocol.party_status = Status::Created;
+    protocol.total_round = total_round;
+    protocol.bet = 10;
+    send(stream, protocol).await;
+    *protocol = receive(stream).await;
+}
+
+/// Two players in a started party of `total_round` rounds, with the frame
+/// starting it for each.
+pub async fn started_party(addr: SocketAddr, total_round: u32) -> [(TcpStream, Protocol); 2] {
+    let (mut creator, mut created) = connect(addr).await;
+    create(&mut creator, &mut created, total_round).await;
+
+    let (mut joiner, mut joined) = connect(addr).await;
+    joined.party_status = Status::JoinParty;
+    send(&mut joiner, &joined).await;
+    let joined = receive(&mut joiner).await;
+    let started = receive(&mut creator).await;
+    assert_eq!(started.party_id, created.party_id);
+
+    [(creator, started), (joiner, joined)]
+}
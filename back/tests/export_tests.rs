@@ -0,0 +1,320 @@
+mod common;
+
+use common::{receive, send, started_party};
+use prisoner_dilemma::admin::Admin;
+use prisoner_dilemma::archive::Archive;
+use prisoner_dilemma::export::{
+    self, ExportError, Exporter, RoundRecord, RoundResult, XlsxExporter,
+};
+use settings::{
+    ExportFormat, Party, PartyRound, PayoffMatrix, PlayStatus, Player, Settings, Status,
+};
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("export-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn party() -> Party {
+        Party {
+            id: 7,
+            created: 1_700_000_000,
+            total_round: 1,
+            round: 2,
+            status: Status::Finished,
+            bet: 10,
+            player1: Player { id: 3, money: 5.0 },
+            player2: Player { id: 4, money: 5.0 },
+            winner: None,
+            looser: None,
+            party_round: PartyRound {
+                round_played: Vec::new(),
+            },
+        }
+    }
+
+    /// Two rounds played out of three from the starting balance: both
+    /// cooperate, then player 3 betrays.
+    fn played_party() -> Party {
+        let side = |id, play, money| (Player { id, money }, play, 0);
+        let (c, d) = (PlayStatus::Cooperate, PlayStatus::Betrail);
+        Party {
+            total_round: 3,
+            round: 3,
+            status: Status::Started,
+            player1: Player {
+                id: 3,
+                money: 125.0,
+            },
+            player2: Player { id: 4, money: 85.0 },
+            party_round: PartyRound {
+                round_played: vec![
+                    (side(3, c.clone(), 105.0), side(4, c.clone(), 105.0)),
+                    (side(3, d, 125.0), side(4, c, 85.0)),
+                    (
+                        side(0, PlayStatus::Stanby, 0.0),
+                        side(0, PlayStatus::Stanby, 0.0),
+                    ),
+                ],
+            },
+            ..party()
+        }
+    }
+
+    #[test]
+    fn file_name_replaces_placeholders() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        let name = export::file_name(
+            "{date}/game_{party_id}_{player1}_vs_{player2}_{time}.{ext}",
+            &party(),
+            time,
+            "csv",
+        );
+        assert_eq!(name, "2024-02-29/game_7_3_vs_4_123456.csv");
+    }
+
+    #[test]
+    fn round_results_split_payouts_and_balances() {
+        let results = export::round_results(&played_party());
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0],
+            RoundResult {
+                round: 1,
+                player1_play: PlayStatus::Cooperate,
+                player1_payout: 5.0,
+                player1_total: 105.0,
+                player2_play: PlayStatus::Cooperate,
+                player2_payout: 5.0,
+                player2_total: 105.0,
+            }
+        );
+        assert_eq!(
+            results[1],
+            RoundResult {
+                round: 2,
+                player1_play: PlayStatus::Betrail,
+                player1_payout: 20.0,
+                player1_total: 125.0,
+                player2_play: PlayStatus::Cooperate,
+                player2_payout: -20.0,
+                player2_total: 85.0,
+            }
+        );
+    }
+
+    fn entry(workbook: &mut zip::ZipArchive<File>, name: &str) -> String {
+        let mut xml = String::new();
+        workbook
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        xml
+    }
+
+    #[test]
+    fn report_is_written_with_and_without_rounds() {
+        let dir = temp_dir("report");
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, party) in [("played.xlsx", played_party()), ("empty.xlsx", party())] {
+            let path = dir.join(name);
+            XlsxExporter
+                .write_party(&party, &PayoffMatrix::default(), &path)
+                .unwrap();
+            let mut workbook = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+            let workbook_xml = entry(&mut workbook, "xl/workbook.xml");
+            for sheet in ["Summary", "Rounds", "Data"] {
+                assert!(workbook_xml.contains(&format!("name=\"{}\"", sheet)));
+            }
+            // Summary B1 holds the party id, the rounds start with a header
+            let summary = entry(&mut workbook, "xl/worksheets/sheet1.xml");
+            assert!(summary.contains("<c r=\"B1\"><v>7</v></c>"), "{}", summary);
+            let strings = entry(&mut workbook, "xl/sharedStrings.xml");
+            assert!(strings.contains("#3 play"));
+            // only played parties get a chart
+            let charted = workbook.by_name("xl/charts/chart1.xml").is_ok();
+            assert_eq!(charted, name == "played.xlsx");
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn every_format_has_the_same_rows() {
+        let dir = temp_dir("formats");
+        let parties = [
+            played_party(),
+            Party {
+                id: 8,
+                ..played_party()
+            },
+        ];
+
+        let mut files = Vec::new();
+        for format in [ExportFormat::Xlsx, ExportFormat::Csv, ExportFormat::Jsonl] {
+            let settings = Settings {
+                export_dir: dir.to_string_lossy().to_string(),
+                export_format: format,
+                ..Default::default()
+            };
+            let path = export::export_all(&settings, &parties).unwrap();
+            assert_eq!(path.extension().unwrap(), format.as_str());
+            files.push(path);
+        }
+
+        let csv = std::fs::read_to_string(&files[1]).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], RoundRecord::COLUMNS.join(","));
+        assert_eq!(
+            lines[4],
+            "8,1700000000,Started,10,3,0,2,3,Betrayal,20.0,125.0,4,Cooperate,-20.0,85.0"
+        );
+
+        let jsonl = std::fs::read_to_string(&files[2]).unwrap();
+        let records: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 4);
+        let record = records[3].as_object().unwrap();
+        assert_eq!(record.len(), RoundRecord::COLUMNS.len());
+        for (column, value) in RoundRecord::COLUMNS
+            .iter()
+            .zip(RoundRecord::of(&parties[1])[1].values())
+        {
+            assert_eq!(record[*column], value, "{}", column);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn storage_file_loads_back() {
+        let dir = temp_dir("storage");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("parties.jsonl");
+        let line = serde_json::to_string(&played_party()).unwrap();
+        std::fs::write(&path, format!("{}\n\n{}\n", line, line)).unwrap();
+        assert_eq!(Archive::load(&path).unwrap().len(), 2);
+
+        std::fs::write(&path, format!("{}\nnot json\n", line)).unwrap();
+        let error = Archive::load(&path).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn archive_writes_parties_in_order() {
+        let dir = temp_dir("archive");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("parties.jsonl");
+        let settings = Settings {
+            storage_path: path.to_string_lossy().to_string(),
+            archive_size: 1,
+            ..Default::default()
+        };
+        let mut archive = Archive::new(&settings);
+        for id in 1..=3 {
+            archive.push(Party { id, ..party() });
+        }
+        assert_eq!(archive.parties().count(), 1);
+        archive.flush().await;
+
+        let ids: Vec<u32> = Archive::load(&path)
+            .unwrap()
+            .iter()
+            .map(|party| party.id)
+            .collect();
+        assert_eq!(ids, [1, 2, 3]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn export_creates_the_directory() {
+        let dir = temp_dir("create");
+        let settings = Settings {
+            export_dir: dir.join("results").to_string_lossy().to_string(),
+            export_file: String::from("{party_id}/game.{ext}"),
+            ..Default::default()
+        };
+
+        let path = export::export(&settings, &party()).unwrap();
+        assert_eq!(path, dir.join("results").join("7").join("game.xlsx"));
+        assert!(path.is_file());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn export_reports_errors() {
+        let dir = temp_dir("error");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("not_a_dir");
+        std::fs::write(&file, "").unwrap();
+        let settings = Settings {
+            export_dir: file.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+
+        let error = export::export(&settings, &party()).unwrap_err();
+        assert!(matches!(error, ExportError::Directory(..)));
+        assert!(error.to_string().contains("not_a_dir"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn failed_exports_reach_the_admin() {
+        let dir = temp_dir("admin");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("not_a_dir");
+        std::fs::write(&file, "").unwrap();
+        let settings = Settings {
+            export_dir: file.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let controller = common::controller(settings.clone()).await;
+        let metrics = controller.metrics.clone();
+        let admin = Admin::new(
+            controller.lobby.clone(),
+            metrics.clone(),
+            Arc::new(settings),
+        );
+        let addr = common::serve(controller);
+
+        let [(mut creator, started), (mut joiner, joined)] = started_party(addr, 1).await;
+        let party_id = started.party_id;
+        for (stream, protocol) in [(&mut creator, started), (&mut joiner, joined)] {
+            let mut protocol = protocol;
+            protocol.play = PlayStatus::Betrail;
+            send(stream, &protocol).await;
+        }
+        receive(&mut creator).await;
+
+        for _ in 0..100 {
+            if !metrics.export_failures().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let answer = admin.execute("exports").await;
+        assert!(answer.starts_with("1 failed export(s)"));
+        assert!(answer.contains(&format!("party #{}  cannot create", party_id)));
+        assert!(metrics
+            .render()
+            .contains("prisoner_export_failures_total 1\n"));
+        assert!(admin
+            .execute("exportall")
+            .await
+            .starts_with("Export failed"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::{connect, create, receive, send, start, workdir};
+use settings::{Party, PlayStatus, Settings, Status};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waiting_party_expires() {
+        let addr = start(Settings {
+            party_ttl: 1,
+            ..Default::default()
+        })
+        .await;
+        let (mut creator, mut protocol) = connect(addr).await;
+        create(&mut creator, &mut protocol, 3).await;
+
+        let expired = receive(&mut creator).await;
+        assert_eq!(expired.party_status, Status::Finished);
+        assert_eq!(expired.party_id, protocol.party_id);
+    }
+
+    #[tokio::test]
+    async fn forfeited_party_is_archived() {
+        let storage_path = workdir().join(format!("archive_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&storage_path);
+        let addr = start(Settings {
+            storage_path: storage_path.to_string_lossy().to_string(),
+            ..Default::default()
+        })
+        .await;
+
+        let (mut creator, mut creator_protocol) = connect(addr).await;
+        let (mut joiner, mut joiner_protocol) = connect(addr).await;
+        create(&mut creator, &mut creator_protocol, 3).await;
+        joiner_protocol.party_status = Status::JoinParty;
+        send(&mut joiner, &joiner_protocol).await;
+        joiner_protocol = receive(&mut joiner).await;
+        receive(&mut creator).await;
+
+        joiner_protocol.play = PlayStatus::Cooperate;
+        send(&mut joiner, &joiner_protocol).await;
+        drop(creator);
+
+        let result = receive(&mut joiner).await;
+        assert_eq!(result.party_status, Status::Win);
+
+        let mut archived = None;
+        for _ in 0..50 {
+            if let Ok(content) = std::fs::read_to_string(&storage_path) {
+                if let Some(line) = content.lines().next() {
+                    archived = Some(serde_json::from_str::<Party>(line).unwrap());
+                    break;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let archived = archived.unwrap();
+        assert_eq!(archived.id, joiner_protocol.party_id);
+        assert_eq!(archived.status, Status::Finished);
+        assert_eq!(archived.winner.unwrap().id, joiner_protocol.player.id);
+        assert_eq!(archived.looser.unwrap().id, creator_protocol.player.id);
+    }
+}
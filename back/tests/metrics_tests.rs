@@ -0,0 +1,82 @@
+mod common;
+
+use common::{connect, create, receive, send};
+use prisoner_dilemma::metrics::Metrics;
+use settings::{PlayStatus, Protocol, Settings, Status};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start() -> (SocketAddr, Arc<Metrics>) {
+        let controller = common::controller(Settings::default()).await;
+        let metrics = Arc::clone(&controller.metrics);
+        (common::serve(controller), metrics)
+    }
+
+    /// Metrics are updated by other tasks, give them a moment.
+    async fn eventually(check: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if check() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("metric never reached the expected value");
+    }
+
+    #[tokio::test]
+    async fn counts_clients_and_decode_errors() {
+        let (addr, metrics) = start().await;
+        let (mut stream, _) = connect(addr).await;
+        assert_eq!(metrics.connected_clients(), 1);
+
+        let mut bytes = Protocol::default().to_bytes();
+        bytes[12] = 200;
+        stream.write_all(&bytes).await.unwrap();
+        eventually(|| {
+            metrics
+                .render()
+                .contains("prisoner_decode_errors_total 1\n")
+        })
+        .await;
+
+        drop(stream);
+        eventually(|| metrics.connected_clients() == 0).await;
+    }
+
+    #[tokio::test]
+    async fn counts_rounds_and_cooperation() {
+        let (addr, metrics) = start().await;
+        let (mut creator, mut protocol) = connect(addr).await;
+        create(&mut creator, &mut protocol, 3).await;
+        assert!(metrics.render().contains("prisoner_active_parties 1\n"));
+
+        let (mut joiner, mut joined) = connect(addr).await;
+        joined.party_status = Status::JoinParty;
+        send(&mut joiner, &joined).await;
+        let joined = receive(&mut joiner).await;
+        let started = receive(&mut creator).await;
+
+        for (stream, protocol, play) in [
+            (&mut creator, started, PlayStatus::Cooperate),
+            (&mut joiner, joined, PlayStatus::Cooperate),
+        ] {
+            let mut protocol = protocol;
+            protocol.party_status = Status::Started;
+            protocol.play = play;
+            send(stream, &protocol).await;
+        }
+        receive(&mut creator).await;
+
+        assert_eq!(metrics.rounds(), 1);
+        assert_eq!(metrics.cooperation_rate(), 1.0);
+        let text = metrics.render();
+        assert!(text.contains("# TYPE prisoner_rounds_total counter\n"));
+        assert!(text.contains("prisoner_cooperations_total 2\n"));
+    }
+}
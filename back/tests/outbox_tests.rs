@@ -0,0 +1,74 @@
+use prisoner_dilemma::controller::Controller;
+use prisoner_dilemma::outbox::{Outbox, OUTBOX_SIZE};
+use settings::Protocol;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn connected_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn messages_are_written_in_order() {
+        let (server, mut client) = connected_pair().await;
+        let (_reader, writer) = server.into_split();
+        let (outbox, _writer) = Outbox::spawn(writer, Duration::from_secs(5), Arc::default());
+
+        for round in 1..=3 {
+            outbox.send(&Protocol {
+                round,
+                ..Default::default()
+            });
+        }
+
+        for round in 1..=3 {
+            let protocol = Controller::read_protocol(&mut client).await.unwrap();
+            assert_eq!(protocol.round, round);
+        }
+    }
+
+    #[tokio::test]
+    async fn full_queue_drops_the_connection() {
+        let (server, _client) = connected_pair().await;
+        let (_reader, writer) = server.into_split();
+        let (outbox, _writer) = Outbox::spawn(writer, Duration::from_secs(5), Arc::default());
+
+        // Nothing yields to the writer task, so the queue overflows.
+        for _ in 0..=OUTBOX_SIZE {
+            outbox.send(&Protocol::default());
+        }
+
+        let closed = tokio::time::timeout(Duration::from_secs(1), outbox.closed()).await;
+        assert!(closed.is_ok());
+    }
+
+    #[tokio::test]
+    async fn write_failure_drops_the_connection() {
+        let (server, client) = connected_pair().await;
+        let (_reader, writer) = server.into_split();
+        let (outbox, _writer) = Outbox::spawn(writer, Duration::from_secs(5), Arc::default());
+        drop(client);
+
+        let closed = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                outbox.send(&Protocol::default());
+                tokio::select! {
+                    _ = outbox.closed() => break,
+                    _ = tokio::time::sleep(Duration::from_millis(10)) => (),
+                }
+            }
+        })
+        .await;
+        assert!(closed.is_ok());
+    }
+}
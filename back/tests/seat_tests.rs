@@ -0,0 +1,84 @@
+mod common;
+
+use common::{connect, create, receive, send};
+use prisoner_dilemma::seat::BotSeat;
+use settings::{PlayStatus, Settings, Status};
+use std::net::SocketAddr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start(bot_seat: &str) -> SocketAddr {
+        common::start(Settings {
+            bot_seat: bot_seat.to_string(),
+            bot_wait: 0,
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Creates a party against the seat and plays `plays`, one per round.
+    /// Returns the money of each frame received and the final status.
+    async fn play(addr: SocketAddr, plays: &[PlayStatus]) -> (Vec<f64>, Status) {
+        let (mut stream, mut protocol) = connect(addr).await;
+        create(&mut stream, &mut protocol, plays.len() as u32).await;
+        assert_eq!(protocol.party_status, Status::Created);
+
+        let mut money = Vec::new();
+        for play in plays.iter().chain([&PlayStatus::Stanby]) {
+            let mut frame = receive(&mut stream).await;
+            money.push(frame.player.money);
+            if frame.party_status != Status::Started {
+                return (money, frame.party_status);
+            }
+            frame.play = play.clone();
+            send(&mut stream, &frame).await;
+        }
+        panic!("the party did not end");
+    }
+
+    #[tokio::test]
+    async fn a_bot_takes_the_free_seat() {
+        let addr = start("betray").await;
+        let plays = [PlayStatus::Cooperate, PlayStatus::Cooperate];
+        // Betrayed twice
+        assert_eq!(
+            play(addr, &plays).await,
+            (vec![100.0, 80.0, 60.0], Status::Lose)
+        );
+    }
+
+    #[tokio::test]
+    async fn the_bot_sees_the_party_history() {
+        let addr = start("tit-for-tat").await;
+        let plays = [
+            PlayStatus::Betrail,
+            PlayStatus::Cooperate,
+            PlayStatus::Cooperate,
+        ];
+        // The bot cooperates, betrays back, then cooperates again
+        assert_eq!(
+            play(addr, &plays).await,
+            (vec![100.0, 120.0, 100.0, 105.0], Status::Equal)
+        );
+    }
+
+    #[test]
+    fn load_the_bot_seat() {
+        assert!(BotSeat::load(&Settings::default()).unwrap().is_none());
+        let settings = Settings {
+            bot_seat: String::from("grudger"),
+            ..Default::default()
+        };
+        assert!(BotSeat::load(&settings).unwrap().is_some());
+        let settings = Settings {
+            bot_seat: String::from("nobody.rhai"),
+            ..Default::default()
+        };
+        assert!(BotSeat::load(&settings)
+            .err()
+            .unwrap()
+            .starts_with("nobody.rhai: cannot read script"));
+    }
+}
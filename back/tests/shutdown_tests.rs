@@ -0,0 +1,43 @@
+mod common;
+
+use common::{connect, receive};
+use settings::{Protocol, Settings, Status};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::oneshot;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clients_are_told_about_shutdown() {
+        let controller = common::controller(Settings::default()).await;
+        let addr = controller.local_addr();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            controller
+                .run_until(async {
+                    let _ = stopped.await;
+                })
+                .await
+        });
+
+        let (mut client, protocol) = connect(addr).await;
+        let player = protocol.player;
+
+        stop.send(()).unwrap();
+
+        let protocol = receive(&mut client).await;
+        assert_eq!(protocol.party_status, Status::Shutdown);
+        assert_eq!(protocol.player.id, player.id);
+
+        let mut buffer = [0; Protocol::HEADER_SIZE];
+        assert_eq!(client.read(&mut buffer).await.unwrap(), 0);
+
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+use client::{ClientState, RoundPlayed};
+use engine::strategy::{self, STRATEGY_NAMES};
+use engine::{Round, Strategy};
+use settings::{PlayStatus, Settings};
+
+/// What a bot knows when it has to play.
+pub struct Turn<'a> {
+    pub party_id: u32,
+    /// Round to play, from 1.
+    pub round: u32,
+    pub total_round: u32,
+    pub bet: u32,
+    /// Balance of the bot so far.
+    pub money: f64,
+    /// Balance of the other player so far, see `ClientState::other_money`.
+    pub other_money: f64,
+    /// Rounds already played in this party, in order.
+    pub history: &'a [RoundPlayed],
+}
+
+impl<'a> Turn<'a> {
+    /// The turn of the party `state` is in, for the round the server started.
+    pub fn of(state: &'a ClientState, round: u32, money: f64) -> Self {
+        let protocol = state.protocol();
+        Self {
+            party_id: protocol.party_id,
+            round,
+            total_round: protocol.total_round,
+            bet: protocol.bet,
+            money,
+            other_money: state.other_money(),
+            history: state.history(),
+        }
+    }
+
+    /// Last move of the other player, `None` in the first round or when the
+    /// payout did not tell it.
+    pub fn last_other(&self) -> Option<&PlayStatus> {
+        self.history.last().and_then(|played| played.other.as_ref())
+    }
+}
+
+/// A player over the network, driven by a `Runner`. `StrategyBot` plays
+/// any `Strategy` of the engine.
+pub trait Bot: Send {
+    fn name(&self) -> &str;
+
+    /// Called when a party starts, before its first move.
+    fn start(&mut self) {}
+
+    /// The move for `turn.round`.
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus;
+}
+
+/// Names of the built-in bots, see `by_name`.
+pub const BOT_NAMES: [&str; 5] = STRATEGY_NAMES;
+
+/// A bot playing one of the built-in strategies of the engine.
+pub fn by_name(name: &str) -> Option<Box<dyn Bot>> {
+    strategy::by_name(name).map(|strategy| Box::new(StrategyBot::new(strategy)) as Box<dyn Bot>)
+}
+
+/// A bot playing a built-in strategy by name, else a rules file or a
+/// script, see `engine::strategy::load`.
+pub fn load(spec: &str, settings: &Settings) -> Result<Box<dyn Bot>, String> {
+    strategy::load(spec, settings)
+        .map(|strategy| Box::new(StrategyBot::new(strategy)) as Box<dyn Bot>)
+}
+
+/// Plays a `Strategy` of the engine over the network.
+pub struct StrategyBot {
+    strategy: Box<dyn Strategy>,
+}
+
+impl StrategyBot {
+    pub fn new(strategy: Box<dyn Strategy>) -> Self {
+        Self { strategy }
+    }
+}
+
+impl Bot for StrategyBot {
+    fn name(&self) -> &str {
+        self.strategy.name()
+    }
+
+    fn start(&mut self) {
+        self.strategy.start();
+    }
+
+    /// A move of the other player the payout did not tell is `Stanby`, as
+    /// the engine expects, so the history stays aligned with the rounds.
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus {
+        let history: Vec<Round> = turn
+            .history
+            .iter()
+            .map(|played| Round {
+                play: played.play.clone(),
+                other: played.other.clone().unwrap_or(PlayStatus::Stanby),
+            })
+            .collect();
+        self.strategy.next_move(&engine::Turn {
+            round: turn.round,
+            total_round: turn.total_round,
+            bet: turn.bet,
+            money: turn.money,
+            other_money: turn.other_money,
+            history: &history,
+        })
+    }
+}
@@ -0,0 +1,5 @@
+pub mod bots;
+pub mod runner;
+
+pub use bots::{Bot, Turn, BOT_NAMES};
+pub use runner::{Mode, PartyResult, Runner, Stop};
@@ -0,0 +1,136 @@
+use bot::bots;
+use bot::{Mode, Runner, Stop};
+use settings::{Log, PlayStatus, Settings};
+use std::time::Duration;
+
+const USAGE: &str = "Usage: bot [options] [--bot <name>] [--create | --join | --auto]
+           [--rounds <n>] [--bet <n>] [--parties <n>] [--wait <seconds>]
+
+Plays parties with a built-in bot, one after the other, until the server
+stops or --parties are played. Prints one line per party.
+  --bot <name>          cooperate, betray, tit-for-tat, grudger, random, a
+                        memory-one strategy (memory-one:, extortion: or
+                        generous:), a rules file (.toml or .json), a script
+                        (.rhai) or a WebAssembly plugin (.wasm or .wat),
+                        defaults to tit-for-tat
+  --create              create every party and wait for a player
+  --join                join open parties only
+  --auto                join an open party, else create one (default)
+  --rounds <n>          rounds of a created party, defaults to default_rounds
+  --bet <n>             bet of a created party, defaults to default_bet
+  --parties <n>         stop after n parties
+  --wait <seconds>      how long a join may stay unanswered, defaults to 5";
+
+struct Arguments {
+    bot: String,
+    mode: Mode,
+    parties: Option<u32>,
+    wait: Duration,
+}
+
+impl Arguments {
+    fn parse(args: Vec<String>, settings: &Settings) -> Result<Self, String> {
+        let mut bot = String::from("tit-for-tat");
+        let (mut rounds, mut bet) = (settings.default_rounds, settings.default_bet);
+        let mut mode = "--auto";
+        let mut parties = None;
+        let mut wait = Duration::from_secs(5);
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("{} expects a value", arg))
+            };
+            let number = |value: String| {
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|number| *number > 0)
+                    .ok_or_else(|| format!("{} expects a positive number, got {}", arg, value))
+            };
+            match arg.as_str() {
+                "--bot" => bot = value()?,
+                "--create" => mode = "--create",
+                "--join" => mode = "--join",
+                "--auto" => mode = "--auto",
+                "--rounds" => rounds = number(value()?)?,
+                "--bet" => bet = number(value()?)?,
+                "--parties" => parties = Some(number(value()?)?),
+                "--wait" => wait = Duration::from_secs(number(value()?)? as u64),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        let mode = match mode {
+            "--create" => Mode::Create { rounds, bet },
+            "--join" => Mode::Join,
+            _ => Mode::Auto { rounds, bet },
+        };
+        Ok(Self {
+            bot,
+            mode,
+            parties,
+            wait,
+        })
+    }
+}
+
+fn main() {
+    let (settings, args) = Settings::from_args_with("client", USAGE);
+    let arguments = match Arguments::parse(args, &settings) {
+        Ok(arguments) => arguments,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut bot = match bots::load(&arguments.bot, &settings) {
+        Ok(bot) => bot,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut runner = match Runner::connect(&settings, arguments.mode) {
+        Ok(runner) => runner,
+        Err(e) => {
+            Log::error(format!(
+                "connecting to {}:{}: {}",
+                settings.host, settings.port, e
+            ));
+            std::process::exit(1);
+        }
+    };
+    runner.parties = arguments.parties;
+    runner.join_timeout = arguments.wait;
+
+    let stop = runner.run(bot.as_mut(), |result| {
+        let cooperations = result
+            .rounds
+            .iter()
+            .filter(|played| played.play == PlayStatus::Cooperate)
+            .count();
+        println!(
+            "Party #{}: {} with {:.2} after {} rounds, {} cooperation(s)",
+            result.party_id,
+            result.end.text(),
+            result.money,
+            result.rounds.len(),
+            cooperations
+        );
+    });
+    match stop {
+        Ok(Stop::Done) => (),
+        Ok(Stop::Ended(end)) => {
+            println!("{}", end.text());
+            std::process::exit(1);
+        }
+        Err(e) => {
+            Log::error(format!("connection lost: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
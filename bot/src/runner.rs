@@ -0,0 +1,203 @@
+use crate::bots::{Bot, Turn};
+use client::{Client, ClientError, Event, PartyEnd, RoundPlayed};
+use rand::Rng;
+use settings::{Level, Log, Settings};
+use std::io;
+use std::net::TcpStream;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How the runner finds its parties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Creates every party and waits for a player. A party nobody joined
+    /// before the server's `party_ttl` is created again.
+    Create { rounds: u32, bet: u32 },
+    /// Joins open parties, waiting until one is open.
+    Join,
+    /// Joins an open party, creates one when none is.
+    Auto { rounds: u32, bet: u32 },
+}
+
+/// A party the bot played.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartyResult {
+    pub party_id: u32,
+    pub end: PartyEnd,
+    /// Balance at the end of the party.
+    pub money: f64,
+    pub rounds: Vec<RoundPlayed>,
+}
+
+/// Why a runner stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The number of parties asked for was played.
+    Done,
+    /// The server shut down, kicked the bot, or the connection was lost.
+    Ended(PartyEnd),
+}
+
+/// Plays parties with a bot, one after the other, on one connection.
+pub struct Runner {
+    client: Client,
+    events: Receiver<Event>,
+    pub mode: Mode,
+    /// Parties to play, `None` to play until the server stops.
+    pub parties: Option<u32>,
+    /// How long a join may stay unanswered before trying again, or creating
+    /// a party in `Mode::Auto`.
+    pub join_timeout: Duration,
+}
+
+impl Runner {
+    pub fn connect(settings: &Settings, mode: Mode) -> io::Result<Self> {
+        let (client, events) = Client::connect(settings)?;
+        Ok(Runner::with_client(client, events, mode))
+    }
+
+    /// Same as `connect` on an open stream.
+    pub fn new(stream: TcpStream, settings: &Settings, mode: Mode) -> io::Result<Self> {
+        let (client, events) = Client::new(stream, settings)?;
+        Ok(Runner::with_client(client, events, mode))
+    }
+
+    fn with_client(client: Client, events: Receiver<Event>, mode: Mode) -> Self {
+        Self {
+            client,
+            events,
+            mode,
+            parties: None,
+            join_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Plays until `parties` are played or the server stops the bot,
+    /// handing each finished party to `report`.
+    pub fn run(
+        &mut self,
+        bot: &mut dyn Bot,
+        mut report: impl FnMut(&PartyResult),
+    ) -> io::Result<Stop> {
+        let stop = self.play(bot, &mut report);
+        self.client.close();
+        stop
+    }
+
+    fn play(
+        &mut self,
+        bot: &mut dyn Bot,
+        report: &mut impl FnMut(&PartyResult),
+    ) -> io::Result<Stop> {
+        match self.events.recv() {
+            Ok(Event::Connected { player_id }) => Log::entry(Level::Info)
+                .player(player_id)
+                .field("bot", bot.name())
+                .message("Bot connected"),
+            _ => return Ok(Stop::Ended(PartyEnd::Disconnected)),
+        }
+
+        let mut played = 0;
+        while self.parties.is_none_or(|parties| played < parties) {
+            if let Some(end) = self.enter_party()? {
+                return Ok(Stop::Ended(end));
+            }
+            bot.start();
+            let result = self.party(bot)?;
+            report(&result);
+            played += 1;
+            match result.end {
+                PartyEnd::Shutdown | PartyEnd::Kicked | PartyEnd::Disconnected => {
+                    return Ok(Stop::Ended(result.end))
+                }
+                _ => {
+                    self.client.state().back();
+                }
+            }
+        }
+        Ok(Stop::Done)
+    }
+
+    /// Creates or joins a party, until one starts. Returns why the bot must
+    /// stop if the connection ended first.
+    fn enter_party(&mut self) -> io::Result<Option<PartyEnd>> {
+        let mut join = false;
+        loop {
+            join = match self.mode {
+                Mode::Create { .. } => false,
+                Mode::Join => true,
+                // Joins first. When no party was open, joins again or creates
+                // one at random, so that two bots in auto mode do not keep
+                // creating parties at the same time.
+                Mode::Auto { .. } => !join || rand::thread_rng().gen_bool(0.5),
+            };
+            match self.mode {
+                Mode::Create { rounds, bet } | Mode::Auto { rounds, bet } if !join => {
+                    self.client.create(rounds, bet).map_err(io_error)?
+                }
+                _ => self.client.join().map_err(io_error)?,
+            }
+
+            loop {
+                let event = if join {
+                    self.events.recv_timeout(self.join_timeout)
+                } else {
+                    self.events
+                        .recv()
+                        .map_err(|_| RecvTimeoutError::Disconnected)
+                };
+                match event {
+                    Ok(Event::PartyStarted { .. }) => return Ok(None),
+                    // Nobody joined before `party_ttl`
+                    Ok(Event::PartyEnded {
+                        end: PartyEnd::Closed,
+                        ..
+                    }) => {
+                        self.client.state().back();
+                        break;
+                    }
+                    Ok(Event::PartyEnded { end, .. }) => return Ok(Some(end)),
+                    Ok(_) => (),
+                    // Unless the party started meanwhile
+                    Err(RecvTimeoutError::Timeout) if self.client.state().back() => break,
+                    Err(RecvTimeoutError::Timeout) => (),
+                    Err(RecvTimeoutError::Disconnected) => return Ok(Some(PartyEnd::Disconnected)),
+                }
+            }
+        }
+    }
+
+    /// Plays the party just started, until it ends.
+    fn party(&mut self, bot: &mut dyn Bot) -> io::Result<PartyResult> {
+        loop {
+            // The channel only closes after a last `PartyEnded`.
+            let event = self.events.recv().unwrap_or(Event::PartyEnded {
+                end: PartyEnd::Disconnected,
+                money: 0.0,
+            });
+            match event {
+                Event::RoundStarted { round, money } => {
+                    let play = bot.next_move(&Turn::of(&self.client.state(), round, money));
+                    self.client.play(play).map_err(io_error)?;
+                }
+                Event::PartyEnded { end, money } => {
+                    let state = self.client.state();
+                    return Ok(PartyResult {
+                        party_id: state.protocol().party_id,
+                        end,
+                        money,
+                        rounds: state.history().to_vec(),
+                    });
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn io_error(e: ClientError) -> io::Error {
+    match e {
+        ClientError::Io(e) => e,
+        e => io::Error::other(e.to_string()),
+    }
+}
@@ -0,0 +1,168 @@
+use bot::bots::{self, Turn};
+use bot::{Mode, PartyResult, Runner, Stop};
+use client::{PartyEnd, RoundPlayed};
+use settings::{PlayStatus, Player, Protocol, Settings, Status};
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(status: Status, round: u32, money: f64) -> Protocol {
+        Protocol {
+            player: Player { id: 42, money },
+            party_status: status,
+            total_round: 2,
+            round,
+            bet: 10,
+            party_id: 7,
+            ..Default::default()
+        }
+    }
+
+    fn send(stream: &mut TcpStream, protocol: &Protocol) {
+        stream.write_all(&protocol.to_bytes()).unwrap();
+    }
+
+    fn turn(history: &[RoundPlayed]) -> Turn<'_> {
+        Turn {
+            party_id: 7,
+            round: history.len() as u32 + 1,
+            total_round: 5,
+            bet: 10,
+            money: 100.0,
+            other_money: 100.0,
+            history,
+        }
+    }
+
+    fn played(other: PlayStatus) -> RoundPlayed {
+        RoundPlayed {
+            round: 1,
+            play: PlayStatus::Cooperate,
+            other: Some(other),
+            payout: 0.0,
+            balance: 100.0,
+        }
+    }
+
+    #[test]
+    fn auto_mode_creates_when_no_party_is_open() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            Protocol::read_from(&mut stream).unwrap();
+            send(&mut stream, &frame(Status::Init, 0, 100.0));
+            // Nobody to join: no answer, until the bot creates a party
+            let mut joins = 0;
+            let created = loop {
+                let protocol = Protocol::read_from(&mut stream).unwrap();
+                if protocol.party_status != Status::JoinParty {
+                    break protocol;
+                }
+                joins += 1;
+            };
+            assert!(joins > 0);
+            assert_eq!(created.party_status, Status::Created);
+            assert_eq!((created.total_round, created.bet), (2, 10));
+            send(&mut stream, &frame(Status::Created, 1, 100.0));
+            send(&mut stream, &frame(Status::Started, 1, 100.0));
+
+            let plays = [Protocol::read_from(&mut stream).unwrap().play, {
+                // The other player betrayed
+                send(&mut stream, &frame(Status::Started, 2, 80.0));
+                Protocol::read_from(&mut stream).unwrap().play
+            }];
+            send(&mut stream, &frame(Status::Lose, 3, 70.0));
+            plays
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mode = Mode::Auto { rounds: 2, bet: 10 };
+        let mut runner = Runner::new(stream, &Settings::default(), mode).unwrap();
+        runner.parties = Some(1);
+        runner.join_timeout = Duration::from_millis(50);
+        let mut bot = bots::by_name("tit-for-tat").unwrap();
+        let mut results: Vec<PartyResult> = Vec::new();
+        let stop = runner
+            .run(bot.as_mut(), |result| results.push(result.clone()))
+            .unwrap();
+
+        assert_eq!(stop, Stop::Done);
+        assert_eq!(
+            server.join().unwrap(),
+            [PlayStatus::Cooperate, PlayStatus::Betrail]
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].party_id, 7);
+        assert_eq!(results[0].end, PartyEnd::Lose);
+        assert_eq!(results[0].money, 70.0);
+        assert_eq!(results[0].rounds[0].other, Some(PlayStatus::Betrail));
+        assert_eq!(results[0].rounds[1].other, Some(PlayStatus::Betrail));
+    }
+
+    #[test]
+    fn stops_when_the_server_shuts_down() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            Protocol::read_from(&mut stream).unwrap();
+            send(&mut stream, &frame(Status::Init, 0, 100.0));
+            Protocol::read_from(&mut stream).unwrap();
+            send(&mut stream, &frame(Status::Shutdown, 0, 100.0));
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut runner = Runner::new(stream, &Settings::default(), Mode::Join).unwrap();
+        let mut bot = bots::by_name("random").unwrap();
+        let stop = runner.run(bot.as_mut(), |_| panic!("no party played"));
+        server.join().unwrap();
+        assert_eq!(stop.unwrap(), Stop::Ended(PartyEnd::Shutdown));
+    }
+
+    #[test]
+    fn built_in_bots() {
+        assert!(bots::by_name("nobody").is_none());
+        for name in bots::BOT_NAMES {
+            assert_eq!(bots::by_name(name).unwrap().name(), name);
+        }
+
+        let mut tit_for_tat = bots::by_name("tit-for-tat").unwrap();
+        assert_eq!(tit_for_tat.next_move(&turn(&[])), PlayStatus::Cooperate);
+        let betrayed = [played(PlayStatus::Betrail)];
+        assert_eq!(tit_for_tat.next_move(&turn(&betrayed)), PlayStatus::Betrail);
+        // an unknown last move is not answered, nor an older one
+        let unknown = [
+            played(PlayStatus::Betrail),
+            RoundPlayed {
+                other: None,
+                ..played(PlayStatus::Cooperate)
+            },
+        ];
+        assert_eq!(
+            tit_for_tat.next_move(&turn(&unknown)),
+            PlayStatus::Cooperate
+        );
+
+        let mut grudger = bots::by_name("grudger").unwrap();
+        grudger.next_move(&turn(&betrayed));
+        let forgiven = [played(PlayStatus::Betrail), played(PlayStatus::Cooperate)];
+        assert_eq!(grudger.next_move(&turn(&forgiven)), PlayStatus::Betrail);
+        grudger.start();
+        assert_eq!(grudger.next_move(&turn(&[])), PlayStatus::Cooperate);
+
+        let pavlov = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../engine/strategies/pavlov.toml"
+        );
+        let mut pavlov = bots::load(pavlov, &Settings::default()).unwrap();
+        assert_eq!(pavlov.name(), "pavlov");
+        assert_eq!(pavlov.next_move(&turn(&betrayed)), PlayStatus::Betrail);
+        assert!(bots::load("nobody", &Settings::default()).is_err());
+    }
+}
@@ -0,0 +1,2 @@
+pub mod moves;
+pub mod session;
@@ -0,0 +1,109 @@
+use cli::moves::Moves;
+use cli::session::{Command, Session};
+use settings::{Log, Settings};
+use std::io::{BufReader, IsTerminal};
+use std::time::Duration;
+
+const USAGE: &str = "Usage: cli [options] create [--rounds <n>] [--bet <n>] [moves]
+       cli [options] join [--wait <seconds>] [moves]
+
+Moves are asked for on the terminal, else read one per line from the
+standard input (c or cooperate, b or betray, # starts a comment), or:
+  --moves <letters>     play these moves in a loop, e.g. --moves ccb
+  --script <file>       read the moves from a file, one per line
+
+  --rounds <n>          rounds of a created party, defaults to default_rounds
+  --bet <n>             bet of a created party, defaults to default_bet
+  --wait <seconds>      how long to wait for a party to join, defaults to 10";
+
+struct Arguments {
+    command: Command,
+    moves: Moves,
+    wait: Duration,
+}
+
+impl Arguments {
+    fn parse(args: Vec<String>, settings: &Settings) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let mut command = match args.next().as_deref() {
+            Some("create") => Command::Create {
+                rounds: settings.default_rounds,
+                bet: settings.default_bet,
+            },
+            Some("join") => Command::Join,
+            Some(other) => return Err(format!("unknown command {}", other)),
+            None => return Err(String::from("missing command, create or join")),
+        };
+        let mut moves = None;
+        let mut wait = Duration::from_secs(10);
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("{} expects a value", arg))
+            };
+            let number = |value: String| {
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|number| *number > 0)
+                    .ok_or_else(|| format!("{} expects a positive number, got {}", arg, value))
+            };
+            match (arg.as_str(), &mut command) {
+                ("--rounds", Command::Create { rounds, .. }) => *rounds = number(value()?)?,
+                ("--bet", Command::Create { bet, .. }) => *bet = number(value()?)?,
+                ("--wait", Command::Join) => wait = Duration::from_secs(number(value()?)? as u64),
+                ("--moves", _) => moves = Some(Moves::sequence(&value()?)?),
+                ("--script", _) => {
+                    let path = value()?;
+                    let file = std::fs::File::open(&path)
+                        .map_err(|e| format!("cannot open {}: {}", path, e))?;
+                    moves = Some(Moves::lines(BufReader::new(file), false));
+                }
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        let moves = moves.unwrap_or_else(|| {
+            let stdin = std::io::stdin();
+            let interactive = stdin.is_terminal();
+            Moves::lines(stdin.lock(), interactive)
+        });
+        Ok(Self {
+            command,
+            moves,
+            wait,
+        })
+    }
+}
+
+fn main() {
+    let (settings, args) = Settings::from_args_with("client", USAGE);
+    let mut arguments = match Arguments::parse(args, &settings) {
+        Ok(arguments) => arguments,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let mut session = match Session::connect(&settings, std::io::stdout()) {
+        Ok(session) => session,
+        Err(e) => {
+            Log::error(format!(
+                "connecting to {}:{}: {}",
+                settings.host, settings.port, e
+            ));
+            std::process::exit(1);
+        }
+    };
+    session.join_timeout = arguments.wait;
+    match session.run(arguments.command, &mut arguments.moves) {
+        Ok(outcome) => std::process::exit(outcome.exit_code()),
+        Err(e) => {
+            Log::error(format!("connection lost: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
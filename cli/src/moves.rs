@@ -0,0 +1,77 @@
+use settings::PlayStatus;
+use std::io::{self, BufRead, Write};
+
+/// Where the moves of a party come from.
+pub enum Moves {
+    /// A fixed sequence, started over once played out.
+    Sequence { moves: Vec<PlayStatus>, next: usize },
+    /// One move per line, prompting for each when `interactive`. Blank lines
+    /// and lines starting with `#` are skipped.
+    Lines {
+        lines: Box<dyn Iterator<Item = io::Result<String>>>,
+        interactive: bool,
+    },
+}
+
+impl Moves {
+    /// Moves written as letters, e.g. `ccbd`: `c` cooperates, `b` or `d`
+    /// betrays.
+    pub fn sequence(letters: &str) -> Result<Self, String> {
+        let moves = letters
+            .chars()
+            .filter(|letter| !letter.is_whitespace() && *letter != ',')
+            .map(|letter| Moves::parse(&letter.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if moves.is_empty() {
+            return Err(String::from("no move given"));
+        }
+        Ok(Moves::Sequence { moves, next: 0 })
+    }
+
+    pub fn lines(reader: impl BufRead + 'static, interactive: bool) -> Self {
+        Moves::Lines {
+            lines: Box::new(reader.lines()),
+            interactive,
+        }
+    }
+
+    /// `c`, `cooperate`, `b`, `d`, `betray` or `defect`, in any case.
+    pub fn parse(word: &str) -> Result<PlayStatus, String> {
+        match word.trim().to_ascii_lowercase().as_str() {
+            "c" | "cooperate" => Ok(PlayStatus::Cooperate),
+            "b" | "d" | "betray" | "defect" => Ok(PlayStatus::Betrail),
+            other => Err(format!(
+                "unknown move {}, use c(ooperate) or b(etray)",
+                other
+            )),
+        }
+    }
+
+    /// Move of the next round, `None` once the lines are exhausted.
+    pub fn next(&mut self, prompt: &str, out: &mut impl Write) -> io::Result<Option<PlayStatus>> {
+        match self {
+            Moves::Sequence { moves, next } => {
+                let play = moves[*next % moves.len()].clone();
+                *next += 1;
+                Ok(Some(play))
+            }
+            Moves::Lines { lines, interactive } => loop {
+                if *interactive {
+                    write!(out, "{} [c]ooperate or [b]etray? ", prompt)?;
+                    out.flush()?;
+                }
+                let Some(line) = lines.next().transpose()? else {
+                    return Ok(None);
+                };
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                match Moves::parse(line) {
+                    Ok(play) => return Ok(Some(play)),
+                    Err(e) => writeln!(out, "{}", e)?,
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::moves::Moves;
+use client::{Client, ClientError, Event, PartyEnd, RoundPlayed};
+use settings::{PayoffMatrix, Settings};
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+pub enum Command {
+    Create {
+        rounds: u32,
+        bet: u32,
+    },
+    /// Joins the party waiting the longest.
+    Join,
+}
+
+/// How a session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Lose,
+    Equal,
+    /// The party was cancelled by the server.
+    Closed,
+    Shutdown,
+    Kicked,
+    Disconnected,
+    /// No party answered the join request in time.
+    NoParty,
+    /// The moves ran out before the end of the party.
+    Quit,
+}
+
+impl Outcome {
+    fn of(end: PartyEnd) -> Outcome {
+        match end {
+            PartyEnd::Win => Outcome::Win,
+            PartyEnd::Lose => Outcome::Lose,
+            PartyEnd::Equal => Outcome::Equal,
+            PartyEnd::Closed => Outcome::Closed,
+            PartyEnd::Shutdown => Outcome::Shutdown,
+            PartyEnd::Kicked => Outcome::Kicked,
+            PartyEnd::Disconnected => Outcome::Disconnected,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Win | Outcome::Lose | Outcome::Equal | Outcome::Closed => 0,
+            Outcome::Shutdown
+            | Outcome::Kicked
+            | Outcome::Disconnected
+            | Outcome::NoParty
+            | Outcome::Quit => 1,
+        }
+    }
+}
+
+/// One player connected to the back server, playing a single party and
+/// printing what happens to `out`.
+pub struct Session<W: Write> {
+    client: Client,
+    events: Receiver<Event>,
+    /// How long a join request may stay unanswered.
+    pub join_timeout: Duration,
+    out: W,
+}
+
+impl<W: Write> Session<W> {
+    pub fn connect(settings: &Settings, out: W) -> io::Result<Self> {
+        let (client, events) = Client::connect(settings)?;
+        Ok(Session::with_client(client, events, out))
+    }
+
+    /// `payoff` is only used to tell the move of the other player from a
+    /// payout, it should match the one of the server.
+    pub fn new(stream: TcpStream, payoff: PayoffMatrix, out: W) -> io::Result<Self> {
+        let settings = Settings {
+            payoff,
+            ..Default::default()
+        };
+        let (client, events) = Client::new(stream, &settings)?;
+        Ok(Session::with_client(client, events, out))
+    }
+
+    fn with_client(client: Client, events: Receiver<Event>, out: W) -> Self {
+        Self {
+            client,
+            events,
+            join_timeout: Duration::from_secs(10),
+            out,
+        }
+    }
+
+    pub fn into_output(self) -> W {
+        self.out
+    }
+
+    pub fn run(&mut self, command: Command, moves: &mut Moves) -> io::Result<Outcome> {
+        let outcome = self.play(command, moves);
+        self.client.close();
+        outcome
+    }
+
+    fn play(&mut self, command: Command, moves: &mut Moves) -> io::Result<Outcome> {
+        let joining = matches!(command, Command::Join);
+        let mut started = false;
+        let mut total_round = 0;
+        loop {
+            let event = if joining && !started && self.client.state().player_id() != 0 {
+                match self.events.recv_timeout(self.join_timeout) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => {
+                        writeln!(self.out, "No party to join")?;
+                        return Ok(Outcome::NoParty);
+                    }
+                    Err(RecvTimeoutError::Disconnected) => None,
+                }
+            } else {
+                self.events.recv().ok()
+            };
+            let Some(event) = event else {
+                writeln!(self.out, "Disconnected by the server")?;
+                return Ok(Outcome::Disconnected);
+            };
+
+            match event {
+                Event::Connected { player_id } => {
+                    writeln!(self.out, "Connected as player #{}", player_id)?;
+                    // Parties are only created or joined once the server gave an id.
+                    let sent = match command {
+                        Command::Create { rounds, bet } => self.client.create(rounds, bet),
+                        Command::Join => self.client.join(),
+                    };
+                    sent.map_err(Session::<W>::io_error)?;
+                }
+                Event::PartyCreated {
+                    party_id,
+                    rounds,
+                    bet,
+                } => writeln!(
+                    self.out,
+                    "Party #{} created: {} rounds, bet {}, waiting for a player",
+                    party_id, rounds, bet
+                )?,
+                Event::Broadcast(message) => writeln!(self.out, "Server: {}", message)?,
+                Event::PartyStarted {
+                    party_id,
+                    rounds,
+                    bet,
+                } => {
+                    started = true;
+                    total_round = rounds;
+                    writeln!(
+                        self.out,
+                        "Party #{} started: {} rounds, bet {}",
+                        party_id, rounds, bet
+                    )?;
+                }
+                Event::RoundResolved(played) => self.report(&played)?,
+                Event::RoundStarted { round, .. } => {
+                    let prompt = format!("Round {}/{}", round, total_round);
+                    let Some(play) = moves.next(&prompt, &mut self.out)? else {
+                        writeln!(self.out, "No more moves, leaving the party")?;
+                        return Ok(Outcome::Quit);
+                    };
+                    self.client.play(play).map_err(Session::<W>::io_error)?;
+                }
+                Event::PartyEnded { end, money } => {
+                    let outcome = Outcome::of(end);
+                    self.announce(outcome, money)?;
+                    return Ok(outcome);
+                }
+            }
+        }
+    }
+
+    /// Prints a resolved round.
+    fn report(&mut self, played: &RoundPlayed) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "Round {}: you {}, other {}, payout {:+.2}, balance {:.2}",
+            played.round,
+            played.play.to_string(),
+            played.other.as_ref().map_or("?", |other| other.to_string()),
+            played.payout,
+            played.balance
+        )
+    }
+
+    fn announce(&mut self, outcome: Outcome, money: f64) -> io::Result<()> {
+        match outcome {
+            Outcome::Win => writeln!(self.out, "You win with {:.2}", money),
+            Outcome::Lose => writeln!(self.out, "You lose with {:.2}", money),
+            Outcome::Equal => writeln!(self.out, "Equal game with {:.2}", money),
+            Outcome::Closed => writeln!(self.out, "Party closed"),
+            Outcome::Shutdown => writeln!(self.out, "Server shut down"),
+            Outcome::Kicked => writeln!(self.out, "Kicked by the server"),
+            Outcome::Disconnected => writeln!(self.out, "Disconnected by the server"),
+            _ => Ok(()),
+        }
+    }
+
+    fn io_error(e: ClientError) -> io::Error {
+        match e {
+            ClientError::Io(e) => e,
+            e => io::Error::other(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// The scripted server of the client tests
+#[path = "../../client/tests/common/mod.rs"]
+mod common;
+
+use cli::moves::Moves;
+use cli::session::{Command, Outcome, Session};
+use common::{frame, send};
+use settings::{PayoffMatrix, PlayStatus, Protocol, Status};
+use std::io::Cursor;
+use std::net::TcpStream;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serve(script: impl FnOnce(&mut TcpStream) + Send + 'static) -> (TcpStream, JoinHandle<()>) {
+        let (addr, server) = common::serve(script);
+        (TcpStream::connect(addr).unwrap(), server)
+    }
+
+    fn run(stream: TcpStream, command: Command, mut moves: Moves) -> (Outcome, String) {
+        let mut session = Session::new(stream, PayoffMatrix::default(), Vec::new()).unwrap();
+        session.join_timeout = Duration::from_millis(200);
+        let outcome = session.run(command, &mut moves).unwrap();
+        (outcome, String::from_utf8(session.into_output()).unwrap())
+    }
+
+    #[test]
+    fn creates_and_plays_a_party() {
+        let (stream, server) = serve(|stream| {
+            let created = Protocol::read_from(stream).unwrap();
+            assert_eq!(created.party_status, Status::Created);
+            assert_eq!((created.total_round, created.bet), (2, 10));
+            send(stream, &frame(Status::Created, 1, 0.0));
+            send(stream, &frame(Status::Started, 1, 0.0));
+
+            let play = Protocol::read_from(stream).unwrap();
+            assert_eq!((play.party_id, play.play), (7, PlayStatus::Cooperate));
+            send(stream, &frame(Status::Broadcast, 0, 0.0));
+            send(stream, &frame(Status::Started, 2, 5.0));
+
+            let play = Protocol::read_from(stream).unwrap();
+            assert_eq!(play.play, PlayStatus::Betrail);
+            send(stream, &frame(Status::Win, 3, 25.0));
+        });
+
+        let moves = Moves::sequence("cb").unwrap();
+        let (outcome, output) = run(stream, Command::Create { rounds: 2, bet: 10 }, moves);
+        server.join().unwrap();
+
+        assert_eq!(outcome, Outcome::Win);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "Connected as player #42",
+                "Party #7 created: 2 rounds, bet 10, waiting for a player",
+                "Party #7 started: 2 rounds, bet 10",
+                "Server: ",
+                "Round 1: you Cooperate, other Cooperate, payout +5.00, balance 5.00",
+                "Round 2: you Betrayal, other Cooperate, payout +20.00, balance 25.00",
+                "You win with 25.00",
+            ]
+        );
+    }
+
+    #[test]
+    fn join_without_waiting_party_times_out() {
+        let (stream, server) = serve(|stream| {
+            let join = Protocol::read_from(stream).unwrap();
+            assert_eq!(join.party_status, Status::JoinParty);
+            thread::sleep(Duration::from_millis(500));
+        });
+
+        let (outcome, output) = run(stream, Command::Join, Moves::sequence("c").unwrap());
+        server.join().unwrap();
+        assert_eq!(outcome, Outcome::NoParty);
+        assert!(output.ends_with("No party to join\n"));
+        assert_eq!(outcome.exit_code(), 1);
+    }
+
+    #[test]
+    fn leaves_when_moves_run_out() {
+        let (stream, server) = serve(|stream| {
+            Protocol::read_from(stream).unwrap();
+            send(stream, &frame(Status::Started, 1, 0.0));
+        });
+
+        let script = Cursor::new("# no move at all\n\n");
+        let (outcome, output) = run(stream, Command::Join, Moves::lines(script, false));
+        server.join().unwrap();
+        assert_eq!(outcome, Outcome::Quit);
+        assert_eq!(outcome.exit_code(), 1);
+        assert!(output.ends_with("No more moves, leaving the party\n"));
+    }
+
+    #[test]
+    fn parse_moves() {
+        assert_eq!(Moves::parse(" Cooperate "), Ok(PlayStatus::Cooperate));
+        assert_eq!(Moves::parse("d"), Ok(PlayStatus::Betrail));
+        assert!(Moves::parse("x").is_err());
+        assert!(Moves::sequence("cbx").is_err());
+        assert!(Moves::sequence("").is_err());
+
+        let mut moves = Moves::sequence("c, b").unwrap();
+        let mut out = Vec::new();
+        let played: Vec<PlayStatus> = (0..3)
+            .map(|_| moves.next("", &mut out).unwrap().unwrap())
+            .collect();
+        assert_eq!(
+            played,
+            [
+                PlayStatus::Cooperate,
+                PlayStatus::Betrail,
+                PlayStatus::Cooperate
+            ]
+        );
+
+        let mut moves = Moves::lines(Cursor::new("oops\nbetray\n"), true);
+        let play = moves.next("Round 1/2", &mut out).unwrap();
+        assert_eq!(play, Some(PlayStatus::Betrail));
+        let prompts = String::from_utf8(out).unwrap();
+        assert_eq!(
+            prompts,
+            "Round 1/2 [c]ooperate or [b]etray? unknown move oops, use c(ooperate) or b(etray)\n\
+             Round 1/2 [c]ooperate or [b]etray? "
+        );
+    }
+}
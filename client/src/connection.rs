@@ -0,0 +1,166 @@
+use crate::event::Event;
+use crate::state::{ClientState, PartyEnd, Screen};
+use settings::{PlayStatus, Protocol, Settings};
+use std::fmt;
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The action does not apply to the current screen, e.g. playing
+    /// while waiting for the other player.
+    Unavailable(&'static str, Screen),
+    /// The frame could not be sent.
+    Io(io::Error),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Unavailable(action, screen) => {
+                write!(f, "cannot {} from the {:?} screen", action, screen)
+            }
+            ClientError::Io(e) => write!(f, "cannot send to the server: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+/// A connection to the back server.
+///
+/// A thread reads the frames of the server, applies them to the shared
+/// `ClientState` and sends the resulting events to every subscriber. Clones
+/// share the connection, so any thread may act on it.
+#[derive(Clone)]
+pub struct Client {
+    stream: Arc<Mutex<TcpStream>>,
+    state: Arc<Mutex<ClientState>>,
+    subscribers: Arc<Mutex<Vec<Sender<Event>>>>,
+}
+
+impl Client {
+    /// Connects to `host:port` of the settings. The receiver gets every
+    /// event from the start, `Connected` first.
+    pub fn connect(settings: &Settings) -> io::Result<(Client, Receiver<Event>)> {
+        let stream = TcpStream::connect((settings.host.as_str(), settings.port))?;
+        Client::new(stream, settings)
+    }
+
+    /// Same as `connect` on an open stream.
+    pub fn new(stream: TcpStream, settings: &Settings) -> io::Result<(Client, Receiver<Event>)> {
+        let reader = stream.try_clone()?;
+        let client = Client {
+            stream: Arc::new(Mutex::new(stream)),
+            state: Arc::new(Mutex::new(ClientState::new(settings))),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        };
+        let events = client.subscribe();
+        let hello = client.state().hello();
+        client.send(&hello)?;
+
+        let reading = client.clone();
+        thread::spawn(move || reading.read_frames(reader));
+        Ok((client, events))
+    }
+
+    /// Events from now on. The channel closes once the connection is lost.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if *self.state().screen() != Screen::End(PartyEnd::Disconnected) {
+            subscribers.push(sender);
+        }
+        receiver
+    }
+
+    /// The current state, locked: do not keep it while acting on the client.
+    pub fn state(&self) -> MutexGuard<'_, ClientState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Creates a party, from the create game form or the menu.
+    pub fn create(&self, rounds: u32, bet: u32) -> Result<(), ClientError> {
+        self.act("create a party", |state| {
+            state.open_create();
+            state.create(rounds, bet)
+        })
+    }
+
+    /// Joins the party waiting the longest. Nothing happens until one is open.
+    pub fn join(&self) -> Result<(), ClientError> {
+        self.act("join a party", ClientState::join)
+    }
+
+    pub fn play(&self, play: PlayStatus) -> Result<(), ClientError> {
+        self.act("play", |state| state.play(play))
+    }
+
+    /// Applies an action of the state and sends the frame it returns, if
+    /// any. The lock is kept while sending, so frames leave in the order of
+    /// the actions.
+    pub fn update(
+        &self,
+        action: impl FnOnce(&mut ClientState) -> Option<Protocol>,
+    ) -> io::Result<bool> {
+        let mut state = self.state();
+        match action(&mut state) {
+            Some(protocol) => self.send(&protocol).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Closes the connection: the reading thread stops and subscribers get
+    /// a last `PartyEnded`.
+    pub fn close(&self) {
+        let _ = self.stream.lock().unwrap().shutdown(Shutdown::Both);
+    }
+
+    fn act(
+        &self,
+        name: &'static str,
+        action: impl FnOnce(&mut ClientState) -> Option<Protocol>,
+    ) -> Result<(), ClientError> {
+        if self.update(action)? {
+            Ok(())
+        } else {
+            Err(ClientError::Unavailable(
+                name,
+                self.state().screen().clone(),
+            ))
+        }
+    }
+
+    fn send(&self, protocol: &Protocol) -> io::Result<()> {
+        let mut stream = self.stream.lock().unwrap();
+        stream.write_all(&protocol.to_bytes())?;
+        stream.flush()
+    }
+
+    fn read_frames(&self, mut reader: TcpStream) {
+        while let Ok(frame) = Protocol::read_from(&mut reader) {
+            let events = self.state().receive(frame);
+            self.publish(events);
+        }
+        let events = self.state().disconnected();
+        self.publish(events);
+        self.subscribers.lock().unwrap().clear();
+    }
+
+    /// Sends to every subscriber, forgetting those who dropped their receiver.
+    fn publish(&self, events: Vec<Event>) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        for event in events {
+            subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::state::{PartyEnd, RoundPlayed};
+
+/// What a `Client` tells its subscribers, in the order frames arrive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The server gave the player an id.
+    Connected { player_id: u32 },
+    /// The party created is open, waiting for a player.
+    PartyCreated {
+        party_id: u32,
+        rounds: u32,
+        bet: u32,
+    },
+    /// Both players are in, the first round starts.
+    PartyStarted {
+        party_id: u32,
+        rounds: u32,
+        bet: u32,
+    },
+    /// A move is expected for `round`, `money` is the balance so far.
+    RoundStarted { round: u32, money: f64 },
+    /// Both players played a round.
+    RoundResolved(RoundPlayed),
+    /// Last event of a party, and of the client when the connection is lost.
+    PartyEnded { end: PartyEnd, money: f64 },
+    /// A message of the server operator.
+    Broadcast(String),
+}
@@ -0,0 +1,7 @@
+pub mod connection;
+pub mod event;
+pub mod state;
+
+pub use connection::{Client, ClientError};
+pub use event::Event;
+pub use state::{ClientState, PartyEnd, RoundPlayed, Screen};
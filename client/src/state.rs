@@ -0,0 +1,308 @@
+use crate::event::Event;
+use settings::{Level, Log, PayoffMatrix, PlayStatus, Player, Protocol, Settings, Status};
+
+/// What the player sees, one variant per view of `front/ui/appwindow.slint`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Screen {
+    /// Choice between creating and joining a party.
+    Menu,
+    /// Rounds and bet of the party to create.
+    CreateGame,
+    /// Join sent, waiting for the server to find an open party.
+    Lobby,
+    /// Waiting for a player to join, or for the other player to play.
+    Waiting,
+    /// The player chooses a move for the current round.
+    Game,
+    End(PartyEnd),
+}
+
+/// Why a party ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartyEnd {
+    Win,
+    Lose,
+    Equal,
+    /// The party was cancelled by the server.
+    Closed,
+    Shutdown,
+    Kicked,
+    /// The connection to the server was lost.
+    Disconnected,
+}
+
+impl PartyEnd {
+    fn of(status: &Status) -> Option<PartyEnd> {
+        match status {
+            Status::Win => Some(PartyEnd::Win),
+            Status::Lose => Some(PartyEnd::Lose),
+            Status::Equal => Some(PartyEnd::Equal),
+            Status::Finished => Some(PartyEnd::Closed),
+            Status::Shutdown => Some(PartyEnd::Shutdown),
+            Status::Kicked => Some(PartyEnd::Kicked),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            PartyEnd::Win => "Win",
+            PartyEnd::Lose => "Lose",
+            PartyEnd::Equal => "Equal game",
+            PartyEnd::Closed => "Party closed",
+            PartyEnd::Shutdown => "Server shut down",
+            PartyEnd::Kicked => "Kicked by the server",
+            PartyEnd::Disconnected => "Disconnected",
+        }
+    }
+}
+
+/// A resolved round, as seen by the player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundPlayed {
+    pub round: u32,
+    pub play: PlayStatus,
+    /// Move of the other player, told from the payout. `None` when the
+    /// payout matches no move of the payoff matrix.
+    pub other: Option<PlayStatus>,
+    pub payout: f64,
+    pub balance: f64,
+}
+
+/// Client side of a connection to the back server, without any IO.
+///
+/// The user interface calls the actions (`create`, `join`, `play`...) and
+/// sends the frame they return, then hands every frame read from the server
+/// to `receive`. Actions which do not apply to the current screen are
+/// ignored and return `None`.
+pub struct ClientState {
+    screen: Screen,
+    /// Last frame of the server, the base of the frames sent.
+    protocol: Protocol,
+    player_id: u32,
+    history: Vec<RoundPlayed>,
+    /// Move sent for the current round, until the server resolves it.
+    played: Option<PlayStatus>,
+    server_message: String,
+    payoff: PayoffMatrix,
+    /// Defaults of the create game form.
+    pub rounds: u32,
+    pub bet: u32,
+}
+
+impl ClientState {
+    /// `settings.payoff` should match the one of the server, it is used to
+    /// tell the move of the other player.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            screen: Screen::Menu,
+            protocol: Protocol::default(),
+            player_id: 0,
+            history: Vec::new(),
+            played: None,
+            server_message: String::new(),
+            payoff: settings.payoff,
+            rounds: settings.default_rounds,
+            bet: settings.default_bet,
+        }
+    }
+
+    /// First frame to send once connected.
+    pub fn hello(&self) -> Protocol {
+        Protocol::default()
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// Last frame received: party id, round, total rounds and money.
+    pub fn protocol(&self) -> &Protocol {
+        &self.protocol
+    }
+
+    /// Id given by the server, 0 until it answered `hello`.
+    pub fn player_id(&self) -> u32 {
+        self.player_id
+    }
+
+    /// Rounds resolved in the current party, in order.
+    pub fn history(&self) -> &[RoundPlayed] {
+        &self.history
+    }
+
+    /// Balance of the other player, from the moves told by the payouts.
+    /// Rounds where the move is unknown are left out.
+    pub fn other_money(&self) -> f64 {
+        let payouts: f64 = self
+            .history
+            .iter()
+            .filter_map(|played| {
+                let other = played.other.as_ref()?;
+                Some(self.payoff.money(other, &played.play, self.protocol.bet))
+            })
+            .sum();
+        Player::default().money + payouts
+    }
+
+    /// Last broadcast of the server, empty if none.
+    pub fn server_message(&self) -> &str {
+        &self.server_message
+    }
+
+    /// Menu → create game form.
+    pub fn open_create(&mut self) -> bool {
+        self.go(Screen::Menu, Screen::CreateGame)
+    }
+
+    /// Create game form → waiting for a player.
+    pub fn create(&mut self, rounds: u32, bet: u32) -> Option<Protocol> {
+        if !self.go(Screen::CreateGame, Screen::Waiting) {
+            return None;
+        }
+        (self.rounds, self.bet) = (rounds, bet);
+        let mut protocol = self.protocol.clone();
+        protocol.party_status = Status::Created;
+        protocol.total_round = rounds;
+        protocol.bet = bet;
+        Some(protocol)
+    }
+
+    /// Menu → lobby, until the server pairs the player.
+    pub fn join(&mut self) -> Option<Protocol> {
+        if !self.go(Screen::Menu, Screen::Lobby) {
+            return None;
+        }
+        let mut protocol = self.protocol.clone();
+        protocol.party_status = Status::JoinParty;
+        Some(protocol)
+    }
+
+    /// Game → waiting for the other player.
+    pub fn play(&mut self, play: PlayStatus) -> Option<Protocol> {
+        if !self.go(Screen::Game, Screen::Waiting) {
+            return None;
+        }
+        let mut protocol = self.protocol.clone();
+        protocol.party_status = Status::Started;
+        protocol.play = play.clone();
+        self.played = Some(play);
+        Some(protocol)
+    }
+
+    /// Back to the menu from the create game form, the lobby or the end of
+    /// a party. The server does not answer a join when no party is open,
+    /// so leaving the lobby is how a player gives up.
+    pub fn back(&mut self) -> bool {
+        match self.screen {
+            Screen::CreateGame | Screen::Lobby => self.screen = Screen::Menu,
+            Screen::End(PartyEnd::Disconnected) => return false,
+            Screen::End(_) => {
+                self.screen = Screen::Menu;
+                self.history.clear();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Applies a frame of the server, returning what happened.
+    pub fn receive(&mut self, frame: Protocol) -> Vec<Event> {
+        Log::entry(Level::Debug)
+            .player(frame.player.id)
+            .party(frame.party_id)
+            .message(format!("Protocol : {:?}", frame));
+        let mut events = Vec::new();
+        match frame.party_status {
+            // Broadcasts carry no party state, keep the current one.
+            Status::Broadcast => {
+                self.server_message = frame.message.clone();
+                events.push(Event::Broadcast(frame.message));
+                return events;
+            }
+            Status::Init => {
+                self.player_id = frame.player.id;
+                events.push(Event::Connected {
+                    player_id: frame.player.id,
+                });
+            }
+            Status::Created => events.push(Event::PartyCreated {
+                party_id: frame.party_id,
+                rounds: frame.total_round,
+                bet: frame.bet,
+            }),
+            Status::Started => {
+                if self.protocol.party_status != Status::Started
+                    || frame.party_id != self.protocol.party_id
+                {
+                    self.history.clear();
+                    self.played = None;
+                    events.push(Event::PartyStarted {
+                        party_id: frame.party_id,
+                        rounds: frame.total_round,
+                        bet: frame.bet,
+                    });
+                }
+                events.extend(self.resolve(&frame));
+                self.screen = Screen::Game;
+                events.push(Event::RoundStarted {
+                    round: frame.round,
+                    money: frame.player.money,
+                });
+            }
+            ref status => {
+                if let Some(end) = PartyEnd::of(status) {
+                    events.extend(self.resolve(&frame));
+                    self.played = None;
+                    self.screen = Screen::End(end);
+                    events.push(Event::PartyEnded {
+                        end,
+                        money: frame.player.money,
+                    });
+                }
+            }
+        }
+        self.protocol = frame;
+        events
+    }
+
+    /// The connection to the server is lost.
+    pub fn disconnected(&mut self) -> Vec<Event> {
+        self.played = None;
+        self.screen = Screen::End(PartyEnd::Disconnected);
+        vec![Event::PartyEnded {
+            end: PartyEnd::Disconnected,
+            money: self.protocol.player.money,
+        }]
+    }
+
+    /// Records the round the server resolved with `frame`, if any.
+    fn resolve(&mut self, frame: &Protocol) -> Option<Event> {
+        let play = self.played.take()?;
+        if frame.round <= self.protocol.round {
+            self.played = Some(play);
+            return None;
+        }
+        let payout = frame.player.money - self.protocol.player.money;
+        let other = [PlayStatus::Cooperate, PlayStatus::Betrail]
+            .into_iter()
+            .find(|other| (self.payoff.money(&play, other, frame.bet) - payout).abs() < 1e-9);
+        let played = RoundPlayed {
+            round: self.protocol.round,
+            play,
+            other,
+            payout,
+            balance: frame.player.money,
+        };
+        self.history.push(played.clone());
+        Some(Event::RoundResolved(played))
+    }
+
+    fn go(&mut self, from: Screen, to: Screen) -> bool {
+        if self.screen != from {
+            return false;
+        }
+        self.screen = to;
+        true
+    }
+}
@@ -0,0 +1,42 @@
+//! A scripted server and the frames it sends, shared by the tests of the
+//! client and of the clients built on it.
+#![allow(dead_code)]
+
+use settings::{Player, Protocol, Status};
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+
+/// A frame of party #7, two rounds betting 10, for player #42.
+pub fn frame(status: Status, round: u32, money: f64) -> Protocol {
+    Protocol {
+        player: Player { id: 42, money },
+        party_status: status,
+        total_round: 2,
+        round,
+        bet: 10,
+        party_id: 7,
+        ..Default::default()
+    }
+}
+
+pub fn send(stream: &mut TcpStream, protocol: &Protocol) {
+    stream.write_all(&protocol.to_bytes()).unwrap();
+}
+
+/// Plays the server side with `script` once a client connected and was
+/// welcomed as player #42.
+pub fn serve(script: impl FnOnce(&mut TcpStream) + Send + 'static) -> (SocketAddr, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        assert_eq!(
+            Protocol::read_from(&mut stream).unwrap().party_status,
+            Status::Init
+        );
+        send(&mut stream, &frame(Status::Init, 0, 0.0));
+        script(&mut stream);
+    });
+    (addr, server)
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use client::{Client, ClientError, Event, PartyEnd, Screen};
+use common::{frame, send, serve};
+use settings::{PlayStatus, Protocol, Settings, Status};
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(events: &Receiver<Event>) -> Event {
+        events.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn events_follow_the_party() {
+        let (addr, server) = serve(|stream| {
+            let join = Protocol::read_from(stream).unwrap();
+            assert_eq!(join.party_status, Status::JoinParty);
+            send(stream, &frame(Status::Started, 1, 100.0));
+            let play = Protocol::read_from(stream).unwrap();
+            assert_eq!(play.play, PlayStatus::Cooperate);
+            send(stream, &frame(Status::Equal, 2, 105.0));
+        });
+        let settings = Settings {
+            host: addr.ip().to_string(),
+            port: addr.port(),
+            ..Default::default()
+        };
+
+        let (client, events) = Client::connect(&settings).unwrap();
+        assert_eq!(next(&events), Event::Connected { player_id: 42 });
+        assert!(matches!(
+            client.play(PlayStatus::Cooperate),
+            Err(ClientError::Unavailable("play", Screen::Menu))
+        ));
+
+        client.join().unwrap();
+        assert!(matches!(
+            next(&events),
+            Event::PartyStarted { party_id: 7, .. }
+        ));
+        assert!(matches!(
+            next(&events),
+            Event::RoundStarted { round: 1, .. }
+        ));
+        let other = client.clone();
+        thread::spawn(move || other.play(PlayStatus::Cooperate).unwrap())
+            .join()
+            .unwrap();
+
+        let Event::RoundResolved(round) = next(&events) else {
+            panic!("round not resolved");
+        };
+        assert_eq!(round.other, Some(PlayStatus::Cooperate));
+        assert_eq!(
+            next(&events),
+            Event::PartyEnded {
+                end: PartyEnd::Equal,
+                money: 105.0
+            }
+        );
+        server.join().unwrap();
+
+        // The server closed the connection
+        assert_eq!(
+            next(&events),
+            Event::PartyEnded {
+                end: PartyEnd::Disconnected,
+                money: 105.0
+            }
+        );
+        assert!(events.recv().is_err());
+        assert_eq!(client.state().history().len(), 1);
+    }
+}
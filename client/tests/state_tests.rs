@@ -0,0 +1,132 @@
+mod common;
+
+use client::{ClientState, Event, PartyEnd, RoundPlayed, Screen};
+use common::frame;
+use settings::{PlayStatus, Settings, Status};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connected() -> ClientState {
+        let mut state = ClientState::new(&Settings::default());
+        state.receive(frame(Status::Init, 0, 100.0));
+        state
+    }
+
+    #[test]
+    fn create_and_play_a_party() {
+        let mut state = connected();
+        assert_eq!(state.player_id(), 42);
+        assert_eq!(state.screen(), &Screen::Menu);
+
+        assert!(state.create(2, 10).is_none());
+        assert!(state.open_create());
+        let created = state.create(2, 10).unwrap();
+        assert_eq!(created.party_status, Status::Created);
+        assert_eq!((created.total_round, created.bet), (2, 10));
+        assert_eq!(state.screen(), &Screen::Waiting);
+
+        state.receive(frame(Status::Created, 1, 100.0));
+        assert_eq!(state.screen(), &Screen::Waiting);
+        assert_eq!(
+            state.receive(frame(Status::Started, 1, 100.0)),
+            [
+                Event::PartyStarted {
+                    party_id: 7,
+                    rounds: 2,
+                    bet: 10
+                },
+                Event::RoundStarted {
+                    round: 1,
+                    money: 100.0
+                }
+            ]
+        );
+        assert_eq!(state.screen(), &Screen::Game);
+
+        let play = state.play(PlayStatus::Cooperate).unwrap();
+        assert_eq!((play.party_status, play.party_id), (Status::Started, 7));
+        assert_eq!(play.play, PlayStatus::Cooperate);
+        assert!(state.play(PlayStatus::Betrail).is_none());
+
+        let mut notice = frame(Status::Broadcast, 0, 0.0);
+        notice.message = String::from("maintenance at noon");
+        state.receive(notice);
+        assert_eq!(state.server_message(), "maintenance at noon");
+        assert_eq!(state.protocol().round, 1);
+
+        let events = state.receive(frame(Status::Started, 2, 105.0));
+        assert!(matches!(
+            events[..],
+            [
+                Event::RoundResolved(RoundPlayed { round: 1, .. }),
+                Event::RoundStarted { round: 2, .. }
+            ]
+        ));
+        state.play(PlayStatus::Betrail).unwrap();
+        let events = state.receive(frame(Status::Win, 3, 125.0));
+        assert_eq!(
+            events.last(),
+            Some(&Event::PartyEnded {
+                end: PartyEnd::Win,
+                money: 125.0
+            })
+        );
+
+        assert_eq!(state.screen(), &Screen::End(PartyEnd::Win));
+        assert_eq!(
+            state.history(),
+            [
+                RoundPlayed {
+                    round: 1,
+                    play: PlayStatus::Cooperate,
+                    other: Some(PlayStatus::Cooperate),
+                    payout: 5.0,
+                    balance: 105.0,
+                },
+                RoundPlayed {
+                    round: 2,
+                    play: PlayStatus::Betrail,
+                    other: Some(PlayStatus::Cooperate),
+                    payout: 20.0,
+                    balance: 125.0,
+                },
+            ]
+        );
+        // reward, then sucker
+        assert_eq!(state.other_money(), 100.0 + 5.0 - 20.0);
+
+        assert!(state.back());
+        assert_eq!(state.screen(), &Screen::Menu);
+        assert!(state.history().is_empty());
+    }
+
+    #[test]
+    fn join_from_the_lobby() {
+        let mut state = connected();
+        assert_eq!(state.join().unwrap().party_status, Status::JoinParty);
+        assert_eq!(state.screen(), &Screen::Lobby);
+        assert!(state.back());
+        assert_eq!(state.screen(), &Screen::Menu);
+
+        state.join().unwrap();
+        state.receive(frame(Status::Started, 1, 100.0));
+        assert_eq!(state.screen(), &Screen::Game);
+        state.play(PlayStatus::Betrail).unwrap();
+        // The other player left: the round is never resolved.
+        state.receive(frame(Status::Finished, 1, 100.0));
+        assert_eq!(state.screen(), &Screen::End(PartyEnd::Closed));
+        assert!(state.history().is_empty());
+    }
+
+    #[test]
+    fn disconnection_ends_the_party() {
+        let mut state = connected();
+        state.join().unwrap();
+        state.disconnected();
+        assert_eq!(state.screen(), &Screen::End(PartyEnd::Disconnected));
+        assert!(!state.back());
+        assert_eq!(PartyEnd::Disconnected.text(), "Disconnected");
+    }
+}
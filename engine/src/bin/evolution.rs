@@ -0,0 +1,157 @@
+use engine::strategy::{self, STRATEGY_NAMES};
+use engine::{Dynamics, Evolution, Strategy};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use settings::{Log, Settings};
+use std::io;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: evolution [options] [--generations <n>] [--moran <players>]
+           [--selection <w>] [--mutation <p>] [--rounds <n>] [--bet <n>]
+           [--repeat <n>] [--seed <n>] [--output <file>]
+           <strategy> <strategy>...
+
+Plays every strategy against every other one and itself, then evolves a
+population starting from equal shares, and prints the share of each
+strategy at each generation as CSV.
+  <strategy>            as for the tournament binary
+  --generations <n>     generations to run, defaults to 100
+  --moran <players>     Moran process over <players>, instead of replicator
+                        dynamics over an infinite population
+  --selection <w>       intensity of selection from 0 (drift) to 1, the
+                        default
+  --mutation <p>        chance an offspring takes a random strategy,
+                        defaults to 0
+  --rounds <n>          rounds of each party, defaults to default_rounds
+  --bet <n>             bet of each party, defaults to default_bet
+  --repeat <n>          parties played by each pair, defaults to 1
+  --seed <n>            seed of the Moran sampling only: the parties of
+                        random strategies are not seeded
+  --output <file>       writes to a .csv or .xlsx file instead";
+
+fn parse(
+    args: Vec<String>,
+    evolution: &mut Evolution,
+    output: &mut Option<PathBuf>,
+    seed: &mut Option<u64>,
+) -> Result<Vec<String>, String> {
+    let mut specs = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{} expects a value", arg))
+        };
+        let number = |value: String| {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| format!("{} expects a positive number, got {}", arg, value))
+        };
+        let probability = |value: String| {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|probability| (0.0..=1.0).contains(probability))
+                .ok_or_else(|| format!("{} expects a number from 0 to 1, got {}", arg, value))
+        };
+        match arg.as_str() {
+            "--generations" => evolution.generations = number(value()?)?,
+            "--moran" => {
+                let population = number(value()?)?;
+                if population < 2 {
+                    return Err(String::from("--moran expects at least 2 players"));
+                }
+                evolution.dynamics = Dynamics::Moran { population };
+            }
+            "--selection" => evolution.selection = probability(value()?)?,
+            "--mutation" => evolution.mutation = probability(value()?)?,
+            "--rounds" => evolution.tournament.rounds = number(value()?)?,
+            "--bet" => evolution.tournament.bet = number(value()?)?,
+            "--repeat" => evolution.tournament.repeat = number(value()?)?,
+            "--seed" => {
+                let value = value()?;
+                *seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("--seed expects a number, got {}", value))?,
+                );
+            }
+            "--output" => *output = Some(PathBuf::from(value()?)),
+            option if option.starts_with("--") => return Err(format!("unknown option {}", option)),
+            _ => specs.push(arg),
+        }
+    }
+    if specs.len() < 2 {
+        return Err(format!(
+            "give at least two strategies, e.g. {}",
+            STRATEGY_NAMES[..2].join(" ")
+        ));
+    }
+    Ok(specs)
+}
+
+fn main() {
+    // Before reading the settings, which are logged: the CSV of shares
+    // may be on the standard output
+    Log::use_stderr(true);
+    let (settings, args) = Settings::from_args_with("server", USAGE);
+    let mut evolution = Evolution::new(&settings);
+    let mut output = None;
+    let mut seed = None;
+    let specs = match parse(args, &mut evolution, &mut output, &mut seed) {
+        Ok(specs) => specs,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // Two instances of each strategy, to play against itself
+    let mut strategies: Vec<Box<dyn Strategy>> = Vec::new();
+    let mut twins: Vec<Box<dyn Strategy>> = Vec::new();
+    let mut failed = false;
+    for spec in &specs {
+        match strategy::load(spec, &settings)
+            .and_then(|strategy| strategy::load(spec, &settings).map(|twin| (strategy, twin)))
+        {
+            Ok((strategy, twin)) => {
+                strategies.push(strategy);
+                twins.push(twin);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(2);
+    }
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let generations = evolution.run(&mut strategies, &mut twins, &mut rng);
+    let written = match &output {
+        Some(path) => generations.write(path),
+        None => generations
+            .write_csv_to(&mut io::stdout().lock())
+            .map_err(|e| e.to_string()),
+    };
+    if let Err(e) = written {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Some(path) = output {
+        println!(
+            "{} generation(s) of {} strategies written to {}",
+            evolution.generations,
+            specs.len(),
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use engine::{Graph, Neighbourhood, Spatial, Stats};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use settings::{Log, Settings};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: spatial [options] [--lattice <w>x<h> | --graph <file>]
+           [--von-neumann] [--cooperators <p>] [--generations <n>] [--no-self]
+           [--frames <dir>] [--frame-format <png|ppm>] [--scale <n>]
+           [--seed <n>] [--output <file>]
+
+Plays the spatial prisoner's dilemma of Nowak and May: agents cooperate or
+betray, play a round with each neighbour every generation with the payoff
+matrix of the settings, then take the move of the best scoring agent among
+themselves and their neighbours. Prints the statistics of each generation
+as CSV.
+  --lattice <w>x<h>     lattice wrapping around its edges, defaults to 50x50
+  --von-neumann         play with the 4 side neighbours on the lattice
+                        instead of the 8 around
+  --graph <file>        edge list, one pair of node names per line, instead
+                        of a lattice
+  --cooperators <p>     starting chance of an agent to cooperate, defaults
+                        to 0.9
+  --generations <n>     generations to run, defaults to 50
+  --no-self             agents do not play against themselves
+  --frames <dir>        draws the lattice at each generation in <dir>
+  --frame-format <fmt>  png (default) or ppm
+  --scale <n>           pixels per cell of frames, defaults to 4
+  --seed <n>            seed of the starting moves, for the same run each
+                        time
+  --output <file>       writes the statistics to a .csv file instead";
+
+struct Options {
+    lattice: (usize, usize),
+    neighbourhood: Neighbourhood,
+    graph: Option<PathBuf>,
+    cooperators: f64,
+    generations: u32,
+    self_play: bool,
+    frames: Option<PathBuf>,
+    frame_format: String,
+    scale: usize,
+    seed: Option<u64>,
+    output: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            lattice: (50, 50),
+            neighbourhood: Neighbourhood::Moore,
+            graph: None,
+            cooperators: 0.9,
+            generations: 50,
+            self_play: true,
+            frames: None,
+            frame_format: String::from("png"),
+            scale: 4,
+            seed: None,
+            output: None,
+        }
+    }
+}
+
+fn parse(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{} expects a value", arg))
+        };
+        let number = |value: String| {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| format!("{} expects a positive number, got {}", arg, value))
+        };
+        match arg.as_str() {
+            "--lattice" => {
+                let size = value()?;
+                options.lattice = size
+                    .split_once('x')
+                    .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                    .filter(|(width, height)| *width > 0 && *height > 0)
+                    .ok_or_else(|| format!("--lattice expects <width>x<height>, got {}", size))?;
+            }
+            "--von-neumann" => options.neighbourhood = Neighbourhood::VonNeumann,
+            "--graph" => options.graph = Some(PathBuf::from(value()?)),
+            "--cooperators" => {
+                let chance = value()?;
+                options.cooperators = chance
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|chance| (0.0..=1.0).contains(chance))
+                    .ok_or_else(|| {
+                        format!("--cooperators expects a number from 0 to 1, got {}", chance)
+                    })?;
+            }
+            "--generations" => options.generations = number(value()?)?,
+            "--no-self" => options.self_play = false,
+            "--frames" => options.frames = Some(PathBuf::from(value()?)),
+            "--frame-format" => match value()?.as_str() {
+                format @ ("png" | "ppm") => options.frame_format = format.to_string(),
+                other => return Err(format!("--frame-format expects png or ppm, got {}", other)),
+            },
+            "--scale" => options.scale = number(value()?)? as usize,
+            "--seed" => {
+                let seed = value()?;
+                options.seed = Some(
+                    seed.parse()
+                        .map_err(|_| format!("--seed expects a number, got {}", seed))?,
+                );
+            }
+            "--output" => options.output = Some(PathBuf::from(value()?)),
+            other => return Err(format!("unknown option {}", other)),
+        }
+    }
+    if options.graph.is_some() && options.frames.is_some() {
+        return Err(String::from("--frames draws lattices only, not --graph"));
+    }
+    Ok(options)
+}
+
+fn frame(spatial: &Spatial, options: &Options) -> Result<(), String> {
+    let Some(dir) = &options.frames else {
+        return Ok(());
+    };
+    let name = format!("frame_{:05}.{}", spatial.generation(), options.frame_format);
+    spatial
+        .write_frame(&dir.join(name), options.scale)
+        .map_err(|e| e.to_string())
+}
+
+fn run(options: &Options, settings: &Settings, out: &mut dyn Write) -> Result<(), String> {
+    let graph = match &options.graph {
+        Some(path) => Graph::load(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => Graph::lattice(options.lattice.0, options.lattice.1, options.neighbourhood),
+    };
+    if let Some(dir) = &options.frames {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut spatial = Spatial::random(graph, settings.payoff, options.cooperators, &mut rng);
+    spatial.self_play = options.self_play;
+
+    let write = |out: &mut dyn Write, stats: &Stats| {
+        writeln!(out, "{}", stats.csv()).map_err(|e| e.to_string())
+    };
+    writeln!(out, "{}", Stats::COLUMNS.join(",")).map_err(|e| e.to_string())?;
+    write(out, &spatial.stats())?;
+    frame(&spatial, options)?;
+    for _ in 0..options.generations {
+        let stats = spatial.step();
+        write(out, &stats)?;
+        frame(&spatial, options)?;
+    }
+    out.flush().map_err(|e| e.to_string())
+}
+
+fn main() {
+    // Logs go to the standard error from the start, before the
+    // settings are read, so that stdout only has the statistics
+    Log::use_stderr(true);
+    let (settings, args) = Settings::from_args_with("server", USAGE);
+    let options = match parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let result = match &options.output {
+        Some(path) => File::create(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+            .and_then(|file| run(&options, &settings, &mut BufWriter::new(file))),
+        None => run(&options, &settings, &mut io::stdout().lock()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Some(path) = &options.output {
+        println!(
+            "{} generation(s) written to {}{}",
+            options.generations,
+            path.display(),
+            match &options.frames {
+                Some(dir) => format!(", frames in {}", dir.display()),
+                None => String::new(),
+            }
+        );
+    }
+}
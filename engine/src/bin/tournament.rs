@@ -0,0 +1,93 @@
+use engine::strategy::{self, STRATEGY_NAMES};
+use engine::{Strategy, Tournament};
+use settings::Settings;
+
+const USAGE: &str = "Usage: tournament [options] [--rounds <n>] [--bet <n>] [--repeat <n>]
+           <strategy> <strategy>...
+
+Plays every strategy against every other one, without the server, and prints
+the standings. The payoff matrix is the one of the settings.
+  <strategy>            cooperate, betray, tit-for-tat, grudger, random, a
+                        memory-one strategy (memory-one:, extortion: or
+                        generous:), a rules file (.toml or .json), a script
+                        (.rhai) or a WebAssembly plugin (.wasm or .wat)
+  --rounds <n>          rounds of each party, defaults to default_rounds
+  --bet <n>             bet of each party, defaults to default_bet
+  --repeat <n>          parties played by each pair, defaults to 1";
+
+fn parse(args: Vec<String>, tournament: &mut Tournament) -> Result<Vec<String>, String> {
+    let mut specs = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut number = || {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} expects a value", arg))?;
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| format!("{} expects a positive number, got {}", arg, value))
+        };
+        match arg.as_str() {
+            "--rounds" => tournament.rounds = number()?,
+            "--bet" => tournament.bet = number()?,
+            "--repeat" => tournament.repeat = number()?,
+            option if option.starts_with("--") => return Err(format!("unknown option {}", option)),
+            _ => specs.push(arg),
+        }
+    }
+    if specs.len() < 2 {
+        return Err(format!(
+            "give at least two strategies, e.g. {}",
+            STRATEGY_NAMES[..2].join(" ")
+        ));
+    }
+    Ok(specs)
+}
+
+fn main() {
+    let (settings, args) = Settings::from_args_with("server", USAGE);
+    let mut tournament = Tournament::new(&settings);
+    let specs = match parse(args, &mut tournament) {
+        Ok(specs) => specs,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let mut strategies: Vec<Box<dyn Strategy>> = Vec::new();
+    let mut failed = false;
+    for spec in &specs {
+        match strategy::load(spec, &settings) {
+            Ok(strategy) => strategies.push(strategy),
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(2);
+    }
+
+    let standings = tournament.run(&mut strategies);
+    println!(
+        "{:<4} {:<24} {:>10} {:>5} {:>5} {:>5} {:>12}",
+        "Rank", "Strategy", "Payoff", "Won", "Lost", "Equal", "Cooperation"
+    );
+    for (rank, standing) in standings.iter().enumerate() {
+        println!(
+            "{:<4} {:<24} {:>10.2} {:>5} {:>5} {:>5} {:>11.0}%",
+            rank + 1,
+            standing.name,
+            standing.payoff,
+            standing.wins,
+            standing.losses,
+            standing.draws,
+            standing.cooperation() * 100.0
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use crate::strategy::Strategy;
+use crate::tournament::Tournament;
+use rand::Rng;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use settings::{csv, Settings};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Number format of population shares in workbooks.
+const SHARE_FORMAT: &str = "0.00%";
+
+/// How the population changes from one generation to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dynamics {
+    /// Discrete replicator dynamics over an infinite population: each share
+    /// grows in proportion to its fitness over the mean fitness.
+    Replicator,
+    /// Moran process over `population` players: at each step one player,
+    /// picked in proportion to its fitness, has an offspring replacing a
+    /// player picked at random. A generation is `population` steps.
+    Moran { population: u32 },
+}
+
+/// Population shares of strategies over the generations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generations {
+    pub names: Vec<String>,
+    /// Mean payoff per party of each strategy (row) against each one
+    /// (column), itself included.
+    pub payoffs: Vec<Vec<f64>>,
+    /// Shares of each strategy, from 0 to 1, the first one being the
+    /// starting population.
+    pub shares: Vec<Vec<f64>>,
+}
+
+/// Evolutionary simulation: strategies play a tournament and the ones
+/// earning more take a larger share of the population, generation after
+/// generation.
+///
+/// The payoff of a strategy is the mean it earns against the population.
+/// Fitness is `1 - selection + selection * payoff`, payoffs being scaled to
+/// 0..1 between the worst and the best party, so `selection` goes from 0
+/// (neutral drift) to 1. With `mutation`, an offspring takes a strategy at
+/// random instead of the one of its parent.
+#[derive(Debug, Clone)]
+pub struct Evolution {
+    pub tournament: Tournament,
+    pub dynamics: Dynamics,
+    pub generations: u32,
+    pub selection: f64,
+    pub mutation: f64,
+}
+
+impl Evolution {
+    /// Replicator dynamics over 100 generations, with the parties of
+    /// `Tournament::new`, full selection and no mutation.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            tournament: Tournament::new(settings),
+            dynamics: Dynamics::Replicator,
+            generations: 100,
+            selection: 1.0,
+            mutation: 0.0,
+        }
+    }
+
+    /// Mean payoff per party of each strategy against each one, over
+    /// `tournament.repeat` parties. The payoffs of a pair do not depend on
+    /// the population, so they are played once for every generation.
+    /// `twins` are second instances of `strategies`, in the same order, for
+    /// the parties of a strategy against itself.
+    pub fn payoffs(
+        &self,
+        strategies: &mut [Box<dyn Strategy>],
+        twins: &mut [Box<dyn Strategy>],
+    ) -> Vec<Vec<f64>> {
+        let count = strategies.len();
+        let repeat = self.tournament.repeat.max(1);
+        let mut payoffs = vec![vec![0.0; count]; count];
+        let pairs = (0..count).flat_map(|first| (first..count).map(move |second| (first, second)));
+        for (first, second) in pairs {
+            for _ in 0..repeat {
+                if first == second {
+                    let played = self
+                        .tournament
+                        .play(strategies[first].as_mut(), twins[first].as_mut());
+                    payoffs[first][first] += (played.payoffs.0 + played.payoffs.1) / 2.0;
+                } else {
+                    let (head, tail) = strategies.split_at_mut(second);
+                    let played = self.tournament.play(head[first].as_mut(), tail[0].as_mut());
+                    payoffs[first][second] += played.payoffs.0;
+                    payoffs[second][first] += played.payoffs.1;
+                }
+            }
+        }
+        for payoff in payoffs.iter_mut().flatten() {
+            *payoff /= repeat as f64;
+        }
+        payoffs
+    }
+
+    /// Plays the tournament then runs `generations` from equal shares,
+    /// drawing the Moran steps from `rng`. Random strategies still draw
+    /// their own moves, so their payoffs change from one run to the next.
+    pub fn run(
+        &self,
+        strategies: &mut [Box<dyn Strategy>],
+        twins: &mut [Box<dyn Strategy>],
+        rng: &mut impl Rng,
+    ) -> Generations {
+        let payoffs = self.payoffs(strategies, twins);
+        let start = vec![1.0 / strategies.len() as f64; strategies.len()];
+        Generations {
+            names: strategies
+                .iter()
+                .map(|strategy| strategy.name().to_string())
+                .collect(),
+            shares: self.evolve(&payoffs, &start, rng),
+            payoffs,
+        }
+    }
+
+    /// Shares of each generation from `start`, for a table of `payoffs`.
+    /// A Moran population is rounded to whole players, and draws its steps
+    /// from `rng`: the same seed and `payoffs` give the same generations.
+    pub fn evolve(&self, payoffs: &[Vec<f64>], start: &[f64], rng: &mut impl Rng) -> Vec<Vec<f64>> {
+        let fitness = Fitness::new(payoffs, self.selection);
+        match self.dynamics {
+            Dynamics::Replicator => {
+                let mut shares = vec![start.to_vec()];
+                for _ in 0..self.generations {
+                    let last = &shares[shares.len() - 1];
+                    let next = self.replicate(&fitness, last);
+                    shares.push(next);
+                }
+                shares
+            }
+            Dynamics::Moran { population } => {
+                let mut counts = whole_players(start, population);
+                let mut shares = vec![proportions(&counts)];
+                for _ in 0..self.generations {
+                    for _ in 0..population {
+                        self.moran_step(&fitness, &mut counts, rng);
+                    }
+                    shares.push(proportions(&counts));
+                }
+                shares
+            }
+        }
+    }
+
+    fn replicate(&self, fitness: &Fitness, shares: &[f64]) -> Vec<f64> {
+        let count = shares.len();
+        let fitnesses: Vec<f64> = (0..count)
+            .map(|strategy| {
+                let payoff = (0..count)
+                    .map(|other| shares[other] * fitness.payoffs[strategy][other])
+                    .sum();
+                fitness.of(payoff)
+            })
+            .collect();
+        let mean: f64 = shares.iter().zip(&fitnesses).map(|(x, f)| x * f).sum();
+        shares
+            .iter()
+            .zip(&fitnesses)
+            .map(|(share, strategy_fitness)| match mean {
+                mean if mean > 0.0 => share * strategy_fitness / mean,
+                _ => *share,
+            })
+            .map(|share| (1.0 - self.mutation) * share + self.mutation / count as f64)
+            .collect()
+    }
+
+    fn moran_step(&self, fitness: &Fitness, counts: &mut [u32], rng: &mut impl Rng) {
+        let count = counts.len();
+        let population: u32 = counts.iter().sum();
+        if population < 2 {
+            return;
+        }
+        // Players meet every other player, not themselves
+        let weights: Vec<f64> = (0..count)
+            .map(|strategy| {
+                if counts[strategy] == 0 {
+                    return 0.0;
+                }
+                let total: f64 = (0..count)
+                    .map(|other| counts[other] as f64 * fitness.payoffs[strategy][other])
+                    .sum::<f64>()
+                    - fitness.payoffs[strategy][strategy];
+                counts[strategy] as f64 * fitness.of(total / (population - 1) as f64)
+            })
+            .collect();
+        let parent = match weights.iter().sum::<f64>() {
+            total if total > 0.0 => pick(&weights, rng.gen_range(0.0..total)),
+            _ => pick_player(counts, rng.gen_range(0..population)),
+        };
+        let offspring = if rng.gen_bool(self.mutation.clamp(0.0, 1.0)) {
+            rng.gen_range(0..count)
+        } else {
+            parent
+        };
+        let dead = pick_player(counts, rng.gen_range(0..population));
+        counts[dead] -= 1;
+        counts[offspring] += 1;
+    }
+}
+
+/// Payoffs scaled to 0..1 and the intensity of selection.
+struct Fitness {
+    payoffs: Vec<Vec<f64>>,
+    selection: f64,
+}
+
+impl Fitness {
+    fn new(payoffs: &[Vec<f64>], selection: f64) -> Self {
+        let all = payoffs.iter().flatten();
+        let worst = all.clone().copied().fold(f64::INFINITY, f64::min);
+        let best = all.copied().fold(f64::NEG_INFINITY, f64::max);
+        let scaled = payoffs
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|payoff| match best - worst {
+                        range if range > 0.0 => (payoff - worst) / range,
+                        // Every party ends the same: no selection
+                        _ => 1.0,
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            payoffs: scaled,
+            selection,
+        }
+    }
+
+    fn of(&self, payoff: f64) -> f64 {
+        1.0 - self.selection + self.selection * payoff
+    }
+}
+
+/// Rounds `shares` to `population` players, the largest remainders first.
+fn whole_players(shares: &[f64], population: u32) -> Vec<u32> {
+    let total: f64 = shares.iter().sum();
+    let exact: Vec<f64> = shares
+        .iter()
+        .map(|share| share / total * population as f64)
+        .collect();
+    let mut counts: Vec<u32> = exact.iter().map(|players| *players as u32).collect();
+    let mut order: Vec<usize> = (0..shares.len()).collect();
+    order.sort_by(|a, b| {
+        (exact[*b] - exact[*b].floor()).total_cmp(&(exact[*a] - exact[*a].floor()))
+    });
+    let missing = population.saturating_sub(counts.iter().sum());
+    for strategy in order.into_iter().cycle().take(missing as usize) {
+        counts[strategy] += 1;
+    }
+    counts
+}
+
+fn proportions(counts: &[u32]) -> Vec<f64> {
+    let population: u32 = counts.iter().sum();
+    counts
+        .iter()
+        .map(|count| *count as f64 / population.max(1) as f64)
+        .collect()
+}
+
+/// Index whose cumulated weight first exceeds `at`.
+fn pick(weights: &[f64], at: f64) -> usize {
+    let mut cumulated = 0.0;
+    for (index, weight) in weights.iter().enumerate() {
+        cumulated += weight;
+        if at < cumulated {
+            return index;
+        }
+    }
+    weights
+        .iter()
+        .rposition(|weight| *weight > 0.0)
+        .unwrap_or(0)
+}
+
+/// Strategy of the player at `at`, players being ordered by strategy.
+fn pick_player(counts: &[u32], at: u32) -> usize {
+    let weights: Vec<f64> = counts.iter().map(|count| *count as f64).collect();
+    pick(&weights, at as f64)
+}
+
+impl Generations {
+    /// Writes the shares to `path`: CSV with one line per generation, or a
+    /// workbook (`.xlsx`) with the shares and the payoffs.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => self.write_csv(path).map_err(|e| e.to_string()),
+            Some("xlsx") => self.write_xlsx(path).map_err(|e| e.to_string()),
+            _ => Err(String::from("unknown format, use a .csv or .xlsx file")),
+        }
+        .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Header then one line per generation, from 0.
+    pub fn write_csv_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let names: Vec<String> = self.names.iter().map(|name| csv::quoted(name)).collect();
+        writeln!(out, "generation,{}", names.join(","))?;
+        for (generation, shares) in self.shares.iter().enumerate() {
+            let shares: Vec<String> = shares.iter().map(|share| share.to_string()).collect();
+            writeln!(out, "{},{}", generation, shares.join(","))?;
+        }
+        Ok(())
+    }
+
+    fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_csv_to(&mut file)?;
+        file.flush()
+    }
+
+    fn write_xlsx(&self, path: &Path) -> Result<(), XlsxError> {
+        let bold = Format::new().set_bold();
+        let percent = Format::new().set_num_format(SHARE_FORMAT);
+        let mut workbook = Workbook::new();
+
+        let sheet = workbook.add_worksheet().set_name("Shares")?;
+        sheet.write_with_format(0, 0, "Generation", &bold)?;
+        for (col, name) in self.names.iter().enumerate() {
+            sheet.write_with_format(0, col as u16 + 1, name, &bold)?;
+            sheet.set_column_width(col as u16 + 1, 16)?;
+        }
+        sheet.set_column_width(0, 12)?;
+        sheet.set_freeze_panes(1, 1)?;
+        for (generation, shares) in self.shares.iter().enumerate() {
+            let row = generation as u32 + 1;
+            sheet.write(row, 0, generation as u32)?;
+            for (col, share) in shares.iter().enumerate() {
+                sheet.write_with_format(row, col as u16 + 1, *share, &percent)?;
+            }
+        }
+
+        let sheet = workbook.add_worksheet().set_name("Payoffs")?;
+        sheet.write_with_format(0, 0, "Strategy", &bold)?;
+        sheet.set_column_width(0, 20)?;
+        for (index, name) in self.names.iter().enumerate() {
+            sheet.write_with_format(0, index as u16 + 1, name, &bold)?;
+            sheet.write_with_format(index as u32 + 1, 0, name, &bold)?;
+            sheet.set_column_width(index as u16 + 1, 16)?;
+        }
+        for (row, payoffs) in self.payoffs.iter().enumerate() {
+            for (col, payoff) in payoffs.iter().enumerate() {
+                sheet.write(row as u32 + 1, col as u16 + 1, *payoff)?;
+            }
+        }
+
+        workbook.save(path)
+    }
+}
@@ -0,0 +1,17 @@
+pub mod evolution;
+pub mod memory_one;
+pub mod plugin;
+pub mod rules;
+pub mod script;
+pub mod spatial;
+pub mod strategy;
+pub mod tournament;
+
+pub use evolution::{Dynamics, Evolution, Generations};
+pub use memory_one::MemoryOne;
+pub use plugin::{Plugin, PluginError};
+pub use rules::{Rules, RulesError};
+pub use script::{Script, ScriptError};
+pub use spatial::{Graph, Neighbourhood, Spatial, SpatialError, Stats};
+pub use strategy::{Factory, Round, Strategy, Turn, STRATEGY_NAMES};
+pub use tournament::{Match, Standing, Tournament};
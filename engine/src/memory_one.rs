@@ -0,0 +1,298 @@
+use crate::strategy::{choose, Round, Strategy, Turn};
+use settings::{PayoffMatrix, PlayStatus};
+
+/// Outcomes of a round seen from a player, own move first, in the order of
+/// `MemoryOne` probabilities.
+pub const OUTCOMES: [&str; 4] = ["CC", "CD", "DC", "DD"];
+/// Rounds averaged when the chain between two strategies has no single
+/// stationary distribution.
+const HORIZON: usize = 100_000;
+
+/// A memory-one strategy: the chance to cooperate only depends on the last
+/// round, `p_cc`, `p_cd`, `p_dc` and `p_dd` after each outcome (own move
+/// then the other's).
+///
+/// Zero-determinant strategies (Press and Dyson) are memory-one strategies
+/// enforcing a linear relation between both long-run payoffs, whatever the
+/// other player does: `extortion` and `generous` build them for a payoff
+/// matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryOne {
+    name: String,
+    first: f64,
+    p: [f64; 4],
+}
+
+impl MemoryOne {
+    /// Cooperates with probability `first` in the first round, then with
+    /// `p[outcome]` after each outcome of `OUTCOMES`.
+    pub fn new(name: &str, first: f64, p: [f64; 4]) -> Result<Self, String> {
+        let probabilities = std::iter::once(("first", first)).chain(
+            OUTCOMES
+                .iter()
+                .zip(p)
+                .map(|(outcome, probability)| (*outcome, probability)),
+        );
+        for (field, probability) in probabilities {
+            if !(0.0..=1.0).contains(&probability) {
+                return Err(format!(
+                    "{} must be between 0 and 1, got {}",
+                    field, probability
+                ));
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            first,
+            p,
+        })
+    }
+
+    /// Extortionate zero-determinant strategy: in the long run, its payoff
+    /// above the punishment is `chi` times the one of the other player, who
+    /// cannot do better without giving it even more. `phi`, from 0
+    /// (excluded) to 1, scales how fast it reacts, 1 being the fastest
+    /// keeping probabilities within 0..1. It betrays first.
+    pub fn extortion(payoff: &PayoffMatrix, chi: f64, phi: f64) -> Result<Self, String> {
+        let (r, s, t, p) = dilemma(payoff, chi, phi)?;
+        let phi = phi
+            * largest_phi([
+                (chi - 1.0) * (r - p),
+                (p - s) + chi * (t - p),
+                (t - p) + chi * (p - s),
+            ]);
+        MemoryOne::new(
+            &format!("extortion-{}", chi),
+            0.0,
+            [
+                1.0 - phi * (chi - 1.0) * (r - p),
+                1.0 - phi * ((p - s) + chi * (t - p)),
+                phi * ((t - p) + chi * (p - s)),
+                0.0,
+            ],
+        )
+    }
+
+    /// Generous zero-determinant strategy: in the long run, its payoff below
+    /// the reward is `chi` times the one of the other player: it gives more
+    /// than it takes, yet betraying it does not pay. `phi` is as for
+    /// `extortion`. It cooperates first.
+    pub fn generous(payoff: &PayoffMatrix, chi: f64, phi: f64) -> Result<Self, String> {
+        let (r, s, t, p) = dilemma(payoff, chi, phi)?;
+        let phi = phi
+            * largest_phi([
+                (r - s) + chi * (t - r),
+                (t - r) + chi * (r - s),
+                (chi - 1.0) * (r - p),
+            ]);
+        MemoryOne::new(
+            &format!("generous-{}", chi),
+            1.0,
+            [
+                1.0,
+                1.0 - phi * ((r - s) + chi * (t - r)),
+                phi * ((t - r) + chi * (r - s)),
+                phi * (chi - 1.0) * (r - p),
+            ],
+        )
+    }
+
+    /// Reads `memory-one:<p_cc>,<p_cd>,<p_dc>,<p_dd>[,<first>]` (`first`
+    /// defaults to 1), `extortion:<chi>[,<phi>]` or `generous:<chi>[,<phi>]`
+    /// (`phi` defaults to 1). `None` if `spec` is none of these.
+    pub fn parse(spec: &str, payoff: &PayoffMatrix) -> Option<Result<Self, String>> {
+        let (kind, values) = spec.split_once(':')?;
+        if !["memory-one", "extortion", "generous"].contains(&kind) {
+            return None;
+        }
+        let values: Result<Vec<f64>, String> = values
+            .split(',')
+            .map(|value| {
+                value
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("{} is not a number", value.trim()))
+            })
+            .collect();
+        let strategy = values.and_then(|values| match (kind, values.as_slice()) {
+            ("memory-one", [cc, cd, dc, dd]) => MemoryOne::new(spec, 1.0, [*cc, *cd, *dc, *dd]),
+            ("memory-one", [cc, cd, dc, dd, first]) => {
+                MemoryOne::new(spec, *first, [*cc, *cd, *dc, *dd])
+            }
+            ("memory-one", _) => Err(String::from(
+                "expected memory-one:<p_cc>,<p_cd>,<p_dc>,<p_dd>[,<first>]",
+            )),
+            ("extortion", [chi]) => MemoryOne::extortion(payoff, *chi, 1.0),
+            ("extortion", [chi, phi]) => MemoryOne::extortion(payoff, *chi, *phi),
+            ("generous", [chi]) => MemoryOne::generous(payoff, *chi, 1.0),
+            ("generous", [chi, phi]) => MemoryOne::generous(payoff, *chi, *phi),
+            _ => Err(format!("expected {}:<chi>[,<phi>]", kind)),
+        });
+        Some(strategy)
+    }
+
+    /// Chance to cooperate in the first round.
+    pub fn first(&self) -> f64 {
+        self.first
+    }
+
+    /// Chances to cooperate after each outcome of `OUTCOMES`.
+    pub fn probabilities(&self) -> [f64; 4] {
+        self.p
+    }
+
+    /// Chance to cooperate after `history`, the rounds of the party in order.
+    pub fn cooperation(&self, history: &[Round]) -> f64 {
+        match history.last().and_then(outcome) {
+            Some(outcome) => self.p[outcome],
+            // First round, or the other move is not known
+            None => self.first,
+        }
+    }
+}
+
+impl Strategy for MemoryOne {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus {
+        choose(self.cooperation(turn.history))
+    }
+}
+
+/// Index of the outcome of `round` in `OUTCOMES`.
+fn outcome(round: &Round) -> Option<usize> {
+    let betrays = |play: &PlayStatus| match play {
+        PlayStatus::Cooperate => Some(0),
+        PlayStatus::Betrail => Some(1),
+        PlayStatus::Stanby => None,
+    };
+    Some(2 * betrays(&round.play)? + betrays(&round.other)?)
+}
+
+/// Largest `phi` keeping each of `factors` times `phi` within 1, the factors
+/// of the probabilities moving with `phi`.
+fn largest_phi(factors: [f64; 3]) -> f64 {
+    1.0 / factors.into_iter().fold(0.0, f64::max)
+}
+
+/// Reward, sucker, temptation and punishment, checked to make a dilemma,
+/// and the parameters of a zero-determinant strategy.
+fn dilemma(payoff: &PayoffMatrix, chi: f64, phi: f64) -> Result<(f64, f64, f64, f64), String> {
+    let PayoffMatrix {
+        reward,
+        sucker,
+        temptation,
+        punishment,
+    } = *payoff;
+    if temptation <= reward || reward <= punishment || punishment <= sucker {
+        return Err(String::from(
+            "payoff must satisfy temptation > reward > punishment > sucker",
+        ));
+    }
+    if !(1.0..).contains(&chi) {
+        return Err(format!("chi must be at least 1, got {}", chi));
+    }
+    if phi.is_nan() || phi <= 0.0 || phi > 1.0 {
+        return Err(format!("phi must be above 0 and at most 1, got {}", phi));
+    }
+    Ok((reward, sucker, temptation, punishment))
+}
+
+/// Long-run share of the rounds ending in each outcome of `OUTCOMES`, seen
+/// from `x`, when `x` plays `y` forever.
+///
+/// This is the stationary distribution of the Markov chain over outcomes.
+/// When it is not unique (deterministic strategies locked in a cycle, say),
+/// the rounds are averaged from the first one instead.
+pub fn stationary(x: &MemoryOne, y: &MemoryOne) -> [f64; 4] {
+    // `y` sees the outcome of `x` with both moves swapped
+    let q = [y.p[0], y.p[2], y.p[1], y.p[3]];
+    let next = |cooperate: f64, other: f64| {
+        [
+            cooperate * other,
+            cooperate * (1.0 - other),
+            (1.0 - cooperate) * other,
+            (1.0 - cooperate) * (1.0 - other),
+        ]
+    };
+    let transitions: Vec<[f64; 4]> = (0..4).map(|state| next(x.p[state], q[state])).collect();
+
+    // v (M - I) = 0 with the sum of v being 1, in place of the last equation
+    let mut system = [[0.0; 5]; 4];
+    for (row, equation) in system.iter_mut().enumerate() {
+        for (state, transition) in transitions.iter().enumerate() {
+            equation[state] = transition[row] - if row == state { 1.0 } else { 0.0 };
+        }
+    }
+    system[3] = [1.0, 1.0, 1.0, 1.0, 1.0];
+    if let Some(distribution) = solve(system) {
+        return distribution;
+    }
+
+    let mut distribution = next(x.first, y.first);
+    let mut average = [0.0; 4];
+    for _ in 0..HORIZON {
+        let mut following = [0.0; 4];
+        for (state, share) in distribution.iter().enumerate() {
+            average[state] += share / HORIZON as f64;
+            for (to, transition) in transitions[state].iter().enumerate() {
+                following[to] += share * transition;
+            }
+        }
+        distribution = following;
+    }
+    average
+}
+
+/// Long-run payoff per round of `x` and `y` playing each other, for a bet
+/// of 1.
+pub fn expected_payoffs(payoff: &PayoffMatrix, x: &MemoryOne, y: &MemoryOne) -> (f64, f64) {
+    let distribution = stationary(x, y);
+    let own = [
+        payoff.reward,
+        payoff.sucker,
+        payoff.temptation,
+        payoff.punishment,
+    ];
+    let other = [own[0], own[2], own[1], own[3]];
+    let expected = |payoffs: [f64; 4]| {
+        distribution
+            .iter()
+            .zip(payoffs)
+            .map(|(share, payoff)| share * payoff)
+            .sum()
+    };
+    (expected(own), expected(other))
+}
+
+/// Gaussian elimination with partial pivoting, `None` if the system is
+/// singular.
+fn solve(mut system: [[f64; 5]; 4]) -> Option<[f64; 4]> {
+    for column in 0..4 {
+        let pivot = (column..4).max_by(|a, b| {
+            system[*a][column]
+                .abs()
+                .total_cmp(&system[*b][column].abs())
+        })?;
+        if system[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        system.swap(column, pivot);
+        let pivot = system[column];
+        for (row, equation) in system.iter_mut().enumerate() {
+            if row != column {
+                let factor = equation[column] / pivot[column];
+                for (value, pivot) in equation.iter_mut().zip(pivot).skip(column) {
+                    *value -= factor * pivot;
+                }
+            }
+        }
+    }
+    let mut solution = [0.0; 4];
+    for (index, value) in solution.iter_mut().enumerate() {
+        *value = system[index][4] / system[index][index];
+    }
+    Some(solution)
+}
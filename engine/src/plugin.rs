@@ -0,0 +1,231 @@
+use crate::strategy::{Strategy, Turn};
+use settings::{Level, Log, PlayStatus};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use wasmi::core::TrapCode;
+use wasmi::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+/// Function a plugin must export, taking the history and returning the move.
+pub const ENTRY_POINT: &str = "next_move";
+/// Largest linear memory a plugin may grow to.
+const MAX_MEMORY: usize = 16 << 20;
+
+/// Byte written for a move in the history given to `next_move`.
+fn byte_of(play: &PlayStatus) -> u8 {
+    match play {
+        PlayStatus::Betrail => 0,
+        PlayStatus::Cooperate => 1,
+        PlayStatus::Stanby => 2,
+    }
+}
+
+#[derive(Debug)]
+pub enum PluginError {
+    /// The file could not be read.
+    Read(String),
+    /// The module is invalid, imports something or misses an export.
+    Compile(String),
+    /// The module trapped, ran out of fuel or returned something else than
+    /// a move.
+    Run(String),
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PluginError::Read(message) => write!(f, "cannot read plugin: {}", message),
+            PluginError::Compile(message) => write!(f, "cannot compile plugin: {}", message),
+            PluginError::Run(message) => write!(f, "plugin failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+/// A strategy compiled to WebAssembly. The module exports:
+///
+/// - `memory`, its linear memory;
+/// - `alloc(len: i32) -> i32`, the address of `len` free bytes;
+/// - `init()`, optional, called at the start of each party;
+/// - `next_move(history: i32, rounds: i32) -> i32`, returning 1 to
+///   cooperate and 0 to betray.
+///
+/// Before each move the history is written at the address returned by
+/// `alloc`, two bytes per round played: the own move then the other's, 0
+/// for betray, 1 for cooperate and 2 when the other move was not told.
+///
+/// Modules run in a sandbox: they may not import anything, their memory is
+/// bounded and each move (and `init`) must be chosen within `fuel`, about
+/// one unit per instruction run. A plugin failing or running out of fuel
+/// cooperates for that round and the failure is logged.
+pub struct Plugin {
+    name: String,
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    next_move: TypedFunc<(i32, i32), i32>,
+    init: Option<TypedFunc<(), ()>>,
+    fuel: u64,
+}
+
+impl Plugin {
+    /// Reads a `.wasm` module, or its `.wat` text. The name is the file name.
+    pub fn load(path: &Path, fuel: u64) -> Result<Self, PluginError> {
+        let bytes = fs::read(path).map_err(|e| PluginError::Read(e.to_string()))?;
+        let bytes = wat::parse_bytes(&bytes).map_err(|e| PluginError::Compile(e.to_string()))?;
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("plugin");
+        Plugin::compile(name, &bytes, fuel)
+    }
+
+    /// Compiles and instantiates the module, running its start function
+    /// within `fuel`.
+    pub fn compile(name: &str, bytes: &[u8], fuel: u64) -> Result<Self, PluginError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module =
+            Module::new(&engine, bytes).map_err(|e| PluginError::Compile(e.to_string()))?;
+        if let Some(import) = module.imports().next() {
+            return Err(PluginError::Compile(format!(
+                "plugins may not import anything, found {}::{}",
+                import.module(),
+                import.name()
+            )));
+        }
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(fuel)
+            .map_err(|e| PluginError::Compile(e.to_string()))?;
+        let instance = Linker::<StoreLimits>::new(&engine)
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| PluginError::Compile(Plugin::describe(e, fuel)))?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| PluginError::Compile(String::from("no memory exported")))?;
+        let function = |name: &str, signature: &str| {
+            PluginError::Compile(format!("no function {}{} exported", name, signature))
+        };
+        let alloc = instance
+            .get_typed_func(&store, "alloc")
+            .map_err(|_| function("alloc", "(i32) -> i32"))?;
+        let next_move = instance
+            .get_typed_func(&store, ENTRY_POINT)
+            .map_err(|_| function(ENTRY_POINT, "(i32, i32) -> i32"))?;
+        let init = match instance.get_func(&store, "init") {
+            Some(init) => Some(init.typed(&store).map_err(|_| function("init", "()"))?),
+            None => None,
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            store,
+            memory,
+            alloc,
+            next_move,
+            init,
+            fuel,
+        })
+    }
+
+    /// Runs `init`, if exported.
+    pub fn init(&mut self) -> Result<(), PluginError> {
+        let Some(init) = self.init else {
+            return Ok(());
+        };
+        self.refuel()?;
+        init.call(&mut self.store, ())
+            .map_err(|e| PluginError::Run(Plugin::describe(e, self.fuel)))
+    }
+
+    /// Runs `next_move` for `turn`.
+    pub fn call(&mut self, turn: &Turn) -> Result<PlayStatus, PluginError> {
+        let history: Vec<u8> = turn
+            .history
+            .iter()
+            .flat_map(|round| [byte_of(&round.play), byte_of(&round.other)])
+            .collect();
+        self.refuel()?;
+        let run = |e| PluginError::Run(Plugin::describe(e, self.fuel));
+        let address = self
+            .alloc
+            .call(&mut self.store, history.len() as i32)
+            .map_err(run)?;
+        self.memory
+            .write(&mut self.store, address as u32 as usize, &history)
+            .map_err(|_| {
+                PluginError::Run(format!(
+                    "alloc returned {}, which has no room for {} bytes",
+                    address,
+                    history.len()
+                ))
+            })?;
+        let result = self
+            .next_move
+            .call(&mut self.store, (address, turn.history.len() as i32))
+            .map_err(run)?;
+        match result {
+            1 => Ok(PlayStatus::Cooperate),
+            0 => Ok(PlayStatus::Betrail),
+            other => Err(PluginError::Run(format!(
+                "{} returned {}, expected 1 (cooperate) or 0 (betray)",
+                ENTRY_POINT, other
+            ))),
+        }
+    }
+
+    fn refuel(&mut self) -> Result<(), PluginError> {
+        self.store
+            .set_fuel(self.fuel)
+            .map_err(|e| PluginError::Run(e.to_string()))
+    }
+
+    fn describe(error: wasmi::Error, fuel: u64) -> String {
+        match error.as_trap_code() {
+            Some(TrapCode::OutOfFuel) => format!("no move within {} fuel", fuel),
+            _ => error.to_string(),
+        }
+    }
+}
+
+impl Strategy for Plugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn start(&mut self) {
+        if let Err(e) = self.init() {
+            Log::entry(Level::Warn)
+                .field("strategy", self.name.as_str())
+                .message(e.to_string());
+        }
+    }
+
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus {
+        match self.call(turn) {
+            Ok(play) => play,
+            Err(e) => {
+                Log::entry(Level::Warn)
+                    .field("strategy", self.name.as_str())
+                    .field("round", turn.round)
+                    .message(format!("{}, cooperating", e));
+                PlayStatus::Cooperate
+            }
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use crate::strategy::{choose, Round, Strategy, Turn};
+use serde::Deserialize;
+use settings::PlayStatus;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Longest history a rules file may remember: coverage is checked over the
+/// `4^memory` histories.
+pub const MAX_MEMORY: usize = 6;
+/// Problems listed at most for the histories without a rule.
+const MAX_LISTED: usize = 8;
+
+#[derive(Debug)]
+pub enum RulesError {
+    /// The file could not be read.
+    Read(String),
+    /// The file is not valid TOML or JSON, or a value has the wrong type.
+    Parse(String),
+    /// Values were read but do not make a strategy.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RulesError::Read(message) => write!(f, "cannot read rules: {}", message),
+            RulesError::Parse(message) => write!(f, "cannot parse rules: {}", message),
+            RulesError::Invalid(problems) => {
+                write!(f, "invalid rules:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+/// A rules file as written.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    name: Option<String>,
+    #[serde(default = "RulesFile::default_memory")]
+    memory: usize,
+    /// Chance to cooperate while fewer than `memory` rounds were played.
+    #[serde(default = "RulesFile::default_first")]
+    first: f64,
+    /// Chance to cooperate when no rule matches.
+    default: Option<f64>,
+    #[serde(default)]
+    rules: Vec<RuleFile>,
+}
+
+impl RulesFile {
+    fn default_memory() -> usize {
+        1
+    }
+
+    fn default_first() -> f64 {
+        1.0
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    /// Last rounds, oldest first, e.g. `CD` or `CC DD`.
+    after: String,
+    /// Chance to cooperate after these rounds.
+    cooperate: f64,
+}
+
+/// One move of a pattern.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Letter {
+    Cooperate,
+    Betray,
+    Any,
+}
+
+impl Letter {
+    fn parse(letter: char) -> Option<Self> {
+        match letter.to_ascii_uppercase() {
+            'C' => Some(Letter::Cooperate),
+            'D' => Some(Letter::Betray),
+            '*' => Some(Letter::Any),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, play: &PlayStatus) -> bool {
+        match self {
+            Letter::Cooperate => *play == PlayStatus::Cooperate,
+            Letter::Betray => *play == PlayStatus::Betrail,
+            Letter::Any => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Own move then the other's, for each remembered round, oldest first.
+    after: Vec<[Letter; 2]>,
+    cooperate: f64,
+}
+
+impl Rule {
+    fn matches(&self, history: &[Round]) -> bool {
+        self.after
+            .iter()
+            .zip(history)
+            .all(|([own, other], round)| own.matches(&round.play) && other.matches(&round.other))
+    }
+}
+
+/// A memory-n strategy read from a rules file: the chance to cooperate is
+/// given by the first rule matching the last `memory` rounds.
+///
+/// ```toml
+/// name = "generous-tit-for-tat"
+/// memory = 1
+/// first = 1.0
+///
+/// [[rules]]
+/// after = "*C"
+/// cooperate = 1.0
+///
+/// [[rules]]
+/// after = "*D"
+/// cooperate = 0.3
+/// ```
+///
+/// In `after`, each round is the own move then the move of the other
+/// player: `C` cooperates, `D` betrays and `*` is any move.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    name: String,
+    memory: usize,
+    first: f64,
+    default: Option<f64>,
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Reads a `.toml` or `.json` file. The name defaults to the file name.
+    pub fn load(path: &Path) -> Result<Self, RulesError> {
+        let text = fs::read_to_string(path).map_err(|e| RulesError::Read(e.to_string()))?;
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("rules");
+        let file = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| e.to_string()),
+            Some("json") => serde_json::from_str(&text).map_err(|e| e.to_string()),
+            _ => {
+                return Err(RulesError::Read(String::from(
+                    "unknown format, use a .toml or .json file",
+                )))
+            }
+        };
+        let file = file.map_err(RulesError::Parse)?;
+        Rules::compile(file, name)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, RulesError> {
+        let file = toml::from_str(text).map_err(|e| RulesError::Parse(e.to_string()))?;
+        Rules::compile(file, "rules")
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, RulesError> {
+        let file = serde_json::from_str(text).map_err(|e| RulesError::Parse(e.to_string()))?;
+        Rules::compile(file, "rules")
+    }
+
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Chance to cooperate after `history`, the rounds of the party in order.
+    pub fn cooperation(&self, history: &[Round]) -> f64 {
+        if history.len() < self.memory {
+            return self.first;
+        }
+        let last = &history[history.len() - self.memory..];
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(last))
+            .map(|rule| rule.cooperate)
+            .or(self.default)
+            // Unknown moves of the other player match `*` rules only
+            .unwrap_or(self.first)
+    }
+
+    /// Lists every problem found, not only the first one.
+    fn compile(file: RulesFile, default_name: &str) -> Result<Self, RulesError> {
+        let mut problems = Vec::new();
+        let name = match file.name {
+            Some(name) if name.trim().is_empty() => {
+                problems.push(String::from("name must not be empty"));
+                name
+            }
+            Some(name) => name,
+            None => default_name.to_string(),
+        };
+        if !(1..=MAX_MEMORY).contains(&file.memory) {
+            problems.push(format!(
+                "memory must be between 1 and {}, got {}",
+                MAX_MEMORY, file.memory
+            ));
+        }
+        check_probability("first", file.first, &mut problems);
+        if let Some(default) = file.default {
+            check_probability("default", default, &mut problems);
+        }
+        if file.rules.is_empty() && file.default.is_none() {
+            problems.push(String::from("no rules: add [[rules]] or a default"));
+        }
+
+        let mut rules = Vec::new();
+        for (index, rule) in file.rules.iter().enumerate() {
+            let field = format!("rules[{}]", index);
+            check_probability(
+                &format!("{}.cooperate", field),
+                rule.cooperate,
+                &mut problems,
+            );
+            if !(1..=MAX_MEMORY).contains(&file.memory) {
+                continue;
+            }
+            match parse_after(&rule.after, file.memory) {
+                Ok(after) => rules.push(Rule {
+                    after,
+                    cooperate: rule.cooperate,
+                }),
+                Err(problem) => problems.push(format!("{}.after {}", field, problem)),
+            }
+        }
+        if !problems.is_empty() {
+            return Err(RulesError::Invalid(problems));
+        }
+
+        check_coverage(&rules, file.memory, file.default.is_some(), &mut problems);
+        match problems.is_empty() {
+            true => Ok(Rules {
+                name,
+                memory: file.memory,
+                first: file.first,
+                default: file.default,
+                rules,
+            }),
+            false => Err(RulesError::Invalid(problems)),
+        }
+    }
+}
+
+impl Strategy for Rules {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus {
+        choose(self.cooperation(turn.history))
+    }
+}
+
+fn check_probability(field: &str, value: f64, problems: &mut Vec<String>) {
+    if !(0.0..=1.0).contains(&value) {
+        problems.push(format!(
+            "{} is a chance to cooperate, between 0 and 1, got {}",
+            field, value
+        ));
+    }
+}
+
+fn parse_after(after: &str, memory: usize) -> Result<Vec<[Letter; 2]>, String> {
+    let mut letters = Vec::new();
+    for letter in after.chars().filter(|letter| !letter.is_whitespace()) {
+        match Letter::parse(letter) {
+            Some(parsed) => letters.push(parsed),
+            None => {
+                return Err(format!(
+                    "`{}` has an unknown move `{}`, use C (cooperate), D (betray) or *",
+                    after, letter
+                ))
+            }
+        }
+    }
+    if letters.len() != 2 * memory {
+        return Err(format!(
+            "`{}` must give {} round(s) of two moves, own then other, e.g. `{}`, got {} move(s)",
+            after,
+            memory,
+            vec!["CD"; memory].join(" "),
+            letters.len()
+        ));
+    }
+    Ok(letters
+        .chunks(2)
+        .map(|round| [round[0], round[1]])
+        .collect())
+}
+
+/// Every history of `memory` rounds, oldest round first.
+fn histories(memory: usize) -> impl Iterator<Item = Vec<Round>> {
+    let moves = [PlayStatus::Cooperate, PlayStatus::Betrail];
+    (0..1usize << (2 * memory)).map(move |index| {
+        (0..memory)
+            .map(|round| {
+                let bits = index >> (2 * (memory - 1 - round));
+                Round {
+                    play: moves[(bits >> 1) & 1].clone(),
+                    other: moves[bits & 1].clone(),
+                }
+            })
+            .collect()
+    })
+}
+
+fn label(history: &[Round]) -> String {
+    let letter = |play: &PlayStatus| match play {
+        PlayStatus::Cooperate => 'C',
+        _ => 'D',
+    };
+    history
+        .iter()
+        .map(|round| format!("{}{}", letter(&round.play), letter(&round.other)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Rules hidden by earlier ones, and histories no rule covers without a
+/// default.
+fn check_coverage(rules: &[Rule], memory: usize, default: bool, problems: &mut Vec<String>) {
+    let mut used = vec![false; rules.len()];
+    let mut uncovered = Vec::new();
+    for history in histories(memory) {
+        match rules.iter().position(|rule| rule.matches(&history)) {
+            Some(index) => used[index] = true,
+            None => uncovered.push(label(&history)),
+        }
+    }
+    for (index, _) in used.iter().enumerate().filter(|(_, used)| !**used) {
+        problems.push(format!(
+            "rules[{}] never applies, earlier rules match every history it matches",
+            index
+        ));
+    }
+    if !default && !uncovered.is_empty() {
+        let more = match uncovered.len() > MAX_LISTED {
+            true => format!(" and {} more", uncovered.len() - MAX_LISTED),
+            false => String::new(),
+        };
+        uncovered.truncate(MAX_LISTED);
+        problems.push(format!(
+            "no rule after {}{}: add rules or a default",
+            uncovered.join(", "),
+            more
+        ));
+    }
+}
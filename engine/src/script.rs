@@ -0,0 +1,243 @@
+use crate::strategy::{Strategy, Turn};
+use rhai::packages::{
+    BasicArrayPackage, BasicMapPackage, BasicMathPackage, CorePackage, LogicPackage,
+    MoreStringPackage, Package,
+};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use settings::{Level, Log, PlayStatus};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Function a script must define, taking the turn.
+pub const ENTRY_POINT: &str = "next_move";
+/// Operations run between two checks of the deadline.
+const CHECK_EVERY: u64 = 256;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    /// The file could not be read.
+    Read(String),
+    /// The script does not compile, or has no `next_move(turn)`.
+    Compile(String),
+    /// The script failed, timed out or returned something else than a move.
+    Run(String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::Read(message) => write!(f, "cannot read script: {}", message),
+            ScriptError::Compile(message) => write!(f, "cannot compile script: {}", message),
+            ScriptError::Run(message) => write!(f, "script failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// A strategy written in Rhai. The script defines `next_move(turn)`, which
+/// returns `"cooperate"` or `"betray"` (or `true` to cooperate):
+///
+/// ```rhai
+/// fn next_move(turn) {
+///     if turn.round == 1 || turn.other.last() == "cooperate" {
+///         "cooperate"
+///     } else {
+///         "betray"
+///     }
+/// }
+/// ```
+///
+/// `turn` has `round` (from 1), `total_round`, `bet`, `money`, `other_money`,
+/// `own` and `other` (the moves played so far, `"unknown"` when the move of
+/// the other player was not told), and `history`, the same rounds as maps
+/// of `play` and `other`. Top-level statements run before each move, so
+/// constants are available to functions as `global::NAME`, but nothing is
+/// kept from one move to the next.
+///
+/// Scripts only see the turn: there is no access to files, the network or
+/// the clock, `eval` is disabled and sizes are bounded. Each move must be
+/// chosen within `timeout`; a script failing or running late cooperates
+/// for that round and the failure is logged.
+pub struct Script {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    /// When the move being chosen must be ready.
+    deadline: Arc<Mutex<Option<Instant>>>,
+    timeout: Duration,
+}
+
+impl Script {
+    /// Reads a `.rhai` file. The name is the file name.
+    pub fn load(path: &Path, timeout: Duration) -> Result<Self, ScriptError> {
+        let source = fs::read_to_string(path).map_err(|e| ScriptError::Read(e.to_string()))?;
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("script");
+        Script::compile(name, &source, timeout)
+    }
+
+    /// Compiles the script and runs its top-level statements once, to report
+    /// their errors now rather than at the first move.
+    pub fn compile(name: &str, source: &str, timeout: Duration) -> Result<Self, ScriptError> {
+        let deadline = Arc::new(Mutex::new(None));
+        let engine = Script::engine(name, Arc::clone(&deadline));
+        let ast = engine
+            .compile(source)
+            .map_err(|e| ScriptError::Compile(e.to_string()))?;
+        if !ast
+            .iter_functions()
+            .any(|function| function.name == ENTRY_POINT && function.params.len() == 1)
+        {
+            return Err(ScriptError::Compile(format!(
+                "no function {}(turn) found",
+                ENTRY_POINT
+            )));
+        }
+
+        let mut script = Self {
+            name: name.to_string(),
+            engine,
+            ast,
+            deadline,
+            timeout,
+        };
+        script.timed(|script| script.engine.run_ast(&script.ast))?;
+        Ok(script)
+    }
+
+    /// Runs `next_move` for `turn`.
+    pub fn call(&mut self, turn: &Turn) -> Result<PlayStatus, ScriptError> {
+        let turn = Script::turn_map(turn);
+        let result = self.timed(|script| {
+            script
+                .engine
+                .call_fn::<Dynamic>(&mut Scope::new(), &script.ast, ENTRY_POINT, (turn,))
+        })?;
+        Script::play_of(result)
+    }
+
+    fn engine(name: &str, deadline: Arc<Mutex<Option<Instant>>>) -> Engine {
+        // A raw engine has no module resolver, so no `import` of files, and
+        // only the packages below: no time functions and no blobs
+        let mut engine = Engine::new_raw();
+        engine.register_global_module(CorePackage::new().as_shared_module());
+        engine.register_global_module(LogicPackage::new().as_shared_module());
+        engine.register_global_module(BasicMathPackage::new().as_shared_module());
+        engine.register_global_module(BasicArrayPackage::new().as_shared_module());
+        engine.register_global_module(BasicMapPackage::new().as_shared_module());
+        engine.register_global_module(MoreStringPackage::new().as_shared_module());
+        engine
+            .disable_symbol("eval")
+            .set_max_call_levels(64)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(10_000)
+            .set_max_array_size(10_000)
+            .set_max_map_size(1_000);
+        engine.on_progress(move |operations| {
+            if operations % CHECK_EVERY != 0 {
+                return None;
+            }
+            match *deadline.lock().unwrap() {
+                Some(deadline) if Instant::now() > deadline => Some(Dynamic::UNIT),
+                _ => None,
+            }
+        });
+        let source = name.to_string();
+        engine.on_print(move |text| {
+            Log::entry(Level::Debug)
+                .field("strategy", source.as_str())
+                .message(text)
+        });
+        engine
+    }
+
+    /// Runs `run` with the deadline set `timeout` from now.
+    fn timed<T>(
+        &mut self,
+        run: impl FnOnce(&mut Self) -> Result<T, Box<EvalAltResult>>,
+    ) -> Result<T, ScriptError> {
+        *self.deadline.lock().unwrap() = Some(Instant::now() + self.timeout);
+        let result = run(self);
+        *self.deadline.lock().unwrap() = None;
+        result.map_err(|e| match *e {
+            EvalAltResult::ErrorTerminated(..) => {
+                ScriptError::Run(format!("no move within {} ms", self.timeout.as_millis()))
+            }
+            e => ScriptError::Run(e.to_string()),
+        })
+    }
+
+    fn turn_map(turn: &Turn) -> Map {
+        let name = |play: &PlayStatus| -> Dynamic {
+            match play {
+                PlayStatus::Cooperate => "cooperate".into(),
+                PlayStatus::Betrail => "betray".into(),
+                PlayStatus::Stanby => "unknown".into(),
+            }
+        };
+        let mut own = Array::new();
+        let mut other = Array::new();
+        let mut history = Array::new();
+        for round in turn.history {
+            own.push(name(&round.play));
+            other.push(name(&round.other));
+            let mut played = Map::new();
+            played.insert("play".into(), name(&round.play));
+            played.insert("other".into(), name(&round.other));
+            history.push(played.into());
+        }
+
+        let mut map = Map::new();
+        map.insert("round".into(), (turn.round as i64).into());
+        map.insert("total_round".into(), (turn.total_round as i64).into());
+        map.insert("bet".into(), (turn.bet as i64).into());
+        map.insert("money".into(), turn.money.into());
+        map.insert("other_money".into(), turn.other_money.into());
+        map.insert("own".into(), own.into());
+        map.insert("other".into(), other.into());
+        map.insert("history".into(), history.into());
+        map
+    }
+
+    fn play_of(result: Dynamic) -> Result<PlayStatus, ScriptError> {
+        if let Some(cooperate) = result.clone().try_cast::<bool>() {
+            return Ok(match cooperate {
+                true => PlayStatus::Cooperate,
+                false => PlayStatus::Betrail,
+            });
+        }
+        match result.clone().into_string().as_deref() {
+            Ok("cooperate") => Ok(PlayStatus::Cooperate),
+            Ok("betray") => Ok(PlayStatus::Betrail),
+            _ => Err(ScriptError::Run(format!(
+                "{} returned {}, expected \"cooperate\", \"betray\" or a bool",
+                ENTRY_POINT, result
+            ))),
+        }
+    }
+}
+
+impl Strategy for Script {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus {
+        match self.call(turn) {
+            Ok(play) => play,
+            Err(e) => {
+                Log::entry(Level::Warn)
+                    .field("strategy", self.name.as_str())
+                    .field("round", turn.round)
+                    .message(format!("{}, cooperating", e));
+                PlayStatus::Cooperate
+            }
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use rand::Rng;
+use settings::{PayoffMatrix, PlayStatus};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Colors of frames, after Nowak and May: blue stayed cooperating, red
+/// stayed betraying, green started cooperating and yellow started betraying.
+const STILL_COOPERATE: [u8; 3] = [0, 0, 255];
+const STILL_BETRAY: [u8; 3] = [255, 0, 0];
+const TO_COOPERATE: [u8; 3] = [0, 255, 0];
+const TO_BETRAY: [u8; 3] = [255, 255, 0];
+
+#[derive(Debug)]
+pub enum SpatialError {
+    /// The graph file could not be read.
+    Read(String),
+    /// A line of the graph file is not an edge.
+    Parse(String),
+    /// A frame could not be written.
+    Write(String),
+}
+
+impl fmt::Display for SpatialError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpatialError::Read(message) => write!(f, "cannot read graph: {}", message),
+            SpatialError::Parse(message) => write!(f, "cannot parse graph: {}", message),
+            SpatialError::Write(message) => write!(f, "cannot write: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SpatialError {}
+
+/// Cells a lattice cell plays with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The 4 cells sharing a side.
+    VonNeumann,
+    /// The 8 cells sharing a side or a corner.
+    Moore,
+}
+
+/// Who plays with whom: agents are nodes, and play with the nodes they
+/// share an edge with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    neighbours: Vec<Vec<usize>>,
+    /// Width and height of a lattice, whose cells are numbered row by row.
+    lattice: Option<(usize, usize)>,
+}
+
+impl Graph {
+    /// A `width` by `height` lattice wrapping around its edges, so that
+    /// every cell has the same number of neighbours.
+    pub fn lattice(width: usize, height: usize, neighbourhood: Neighbourhood) -> Self {
+        let offsets: &[(isize, isize)] = match neighbourhood {
+            Neighbourhood::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Neighbourhood::Moore => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        };
+        let wrap = |value: usize, offset: isize, size: usize| {
+            (value as isize + offset).rem_euclid(size as isize) as usize
+        };
+        let neighbours = (0..width * height)
+            .map(|cell| {
+                let (x, y) = (cell % width, cell / width);
+                let mut cells: Vec<usize> = offsets
+                    .iter()
+                    .map(|(dx, dy)| wrap(y, *dy, height) * width + wrap(x, *dx, width))
+                    .filter(|neighbour| *neighbour != cell)
+                    .collect();
+                // Small lattices wrap onto the same cells
+                cells.sort_unstable();
+                cells.dedup();
+                cells
+            })
+            .collect();
+        Self {
+            neighbours,
+            lattice: Some((width, height)),
+        }
+    }
+
+    /// Reads an edge list: one edge per line, two node names separated by
+    /// spaces or tabs. Empty lines and lines starting with `#` are skipped.
+    pub fn load(path: &Path) -> Result<Self, SpatialError> {
+        let text = fs::read_to_string(path).map_err(|e| SpatialError::Read(e.to_string()))?;
+        Graph::parse(&text)
+    }
+
+    /// Nodes are numbered in the order they first appear. Edges are
+    /// undirected; loops and repeated edges are ignored.
+    pub fn parse(text: &str) -> Result<Self, SpatialError> {
+        let mut nodes: HashMap<&str, usize> = HashMap::new();
+        let mut neighbours: Vec<Vec<usize>> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let names: Vec<&str> = line.split_whitespace().collect();
+            let [from, to] = names[..] else {
+                return Err(SpatialError::Parse(format!(
+                    "line {}: expected two nodes, got {}",
+                    index + 1,
+                    line
+                )));
+            };
+            let mut node = |name| {
+                let count = nodes.len();
+                let node = *nodes.entry(name).or_insert(count);
+                if node == neighbours.len() {
+                    neighbours.push(Vec::new());
+                }
+                node
+            };
+            let (from, to) = (node(from), node(to));
+            if from != to && !neighbours[from].contains(&to) {
+                neighbours[from].push(to);
+                neighbours[to].push(from);
+            }
+        }
+        if neighbours.is_empty() {
+            return Err(SpatialError::Parse(String::from("no edges")));
+        }
+        Ok(Self {
+            neighbours,
+            lattice: None,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbours.is_empty()
+    }
+
+    pub fn neighbours(&self, node: usize) -> &[usize] {
+        &self.neighbours[node]
+    }
+
+    /// Width and height, for a lattice.
+    pub fn lattice_size(&self) -> Option<(usize, usize)> {
+        self.lattice
+    }
+}
+
+/// One generation of a spatial game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub generation: u32,
+    pub agents: usize,
+    pub cooperators: usize,
+    /// Mean payoff of the agents over the rounds with their neighbours.
+    pub mean_payoff: f64,
+    /// Agents whose move changed since the previous generation.
+    pub changed: usize,
+}
+
+impl Stats {
+    pub const COLUMNS: [&'static str; 5] = [
+        "generation",
+        "cooperators",
+        "cooperation",
+        "mean_payoff",
+        "changed",
+    ];
+
+    /// Share of the agents cooperating, from 0 to 1.
+    pub fn cooperation(&self) -> f64 {
+        match self.agents {
+            0 => 0.0,
+            agents => self.cooperators as f64 / agents as f64,
+        }
+    }
+
+    /// A CSV line, in the order of `COLUMNS`.
+    pub fn csv(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.generation,
+            self.cooperators,
+            self.cooperation(),
+            self.mean_payoff,
+            self.changed
+        )
+    }
+}
+
+/// Spatial prisoner's dilemma (Nowak and May): each agent of a graph either
+/// cooperates or betrays. Every generation, agents play one round with each
+/// neighbour, and then take the move of whoever scored best among
+/// themselves and their neighbours, keeping their own on a tie.
+#[derive(Debug, Clone)]
+pub struct Spatial {
+    graph: Graph,
+    pub payoff: PayoffMatrix,
+    /// Agents also play a round against themselves, as in Nowak and May.
+    pub self_play: bool,
+    moves: Vec<PlayStatus>,
+    previous: Vec<PlayStatus>,
+    generation: u32,
+}
+
+impl Spatial {
+    /// Agents playing `moves`, one per node of `graph`.
+    pub fn new(graph: Graph, payoff: PayoffMatrix, moves: Vec<PlayStatus>) -> Self {
+        assert_eq!(moves.len(), graph.len(), "one move per node");
+        Self {
+            graph,
+            payoff,
+            self_play: true,
+            previous: moves.clone(),
+            moves,
+            generation: 0,
+        }
+    }
+
+    /// Each agent cooperates with probability `cooperators`, drawn from
+    /// `rng`.
+    pub fn random(
+        graph: Graph,
+        payoff: PayoffMatrix,
+        cooperators: f64,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let moves = (0..graph.len())
+            .map(|_| match rng.gen_bool(cooperators.clamp(0.0, 1.0)) {
+                true => PlayStatus::Cooperate,
+                false => PlayStatus::Betrail,
+            })
+            .collect();
+        Spatial::new(graph, payoff, moves)
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    pub fn moves(&self) -> &[PlayStatus] {
+        &self.moves
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Payoff of each agent over a round with each neighbour, for a bet of 1.
+    pub fn payoffs(&self) -> Vec<f64> {
+        (0..self.graph.len())
+            .map(|agent| {
+                let play = &self.moves[agent];
+                let own = match self.self_play {
+                    true => self.payoff.payoff(play, play, 1),
+                    false => 0.0,
+                };
+                own + self
+                    .graph
+                    .neighbours(agent)
+                    .iter()
+                    .map(|neighbour| self.payoff.payoff(play, &self.moves[*neighbour], 1))
+                    .sum::<f64>()
+            })
+            .collect()
+    }
+
+    pub fn stats(&self) -> Stats {
+        let payoffs = self.payoffs();
+        Stats {
+            generation: self.generation,
+            agents: self.moves.len(),
+            cooperators: self
+                .moves
+                .iter()
+                .filter(|play| **play == PlayStatus::Cooperate)
+                .count(),
+            mean_payoff: payoffs.iter().sum::<f64>() / payoffs.len().max(1) as f64,
+            changed: self
+                .moves
+                .iter()
+                .zip(&self.previous)
+                .filter(|(play, previous)| play != previous)
+                .count(),
+        }
+    }
+
+    /// Plays a generation and imitates the best neighbours, all agents at
+    /// once. Returns the statistics of the new generation.
+    pub fn step(&mut self) -> Stats {
+        let payoffs = self.payoffs();
+        let moves = (0..self.graph.len())
+            .map(|agent| {
+                let mut best = agent;
+                for neighbour in self.graph.neighbours(agent) {
+                    if payoffs[*neighbour] > payoffs[best] {
+                        best = *neighbour;
+                    }
+                }
+                self.moves[best].clone()
+            })
+            .collect();
+        self.previous = std::mem::replace(&mut self.moves, moves);
+        self.generation += 1;
+        self.stats()
+    }
+
+    /// RGB pixels of a lattice, `scale` pixels per cell, `None` for other
+    /// graphs.
+    pub fn pixels(&self, scale: usize) -> Option<(usize, usize, Vec<u8>)> {
+        let (width, height) = self.graph.lattice_size()?;
+        let scale = scale.max(1);
+        let mut pixels = Vec::with_capacity(width * height * scale * scale * 3);
+        for y in 0..height * scale {
+            for x in 0..width * scale {
+                let cell = (y / scale) * width + x / scale;
+                let color = match (&self.previous[cell], &self.moves[cell]) {
+                    (PlayStatus::Cooperate, PlayStatus::Cooperate) => STILL_COOPERATE,
+                    (_, PlayStatus::Cooperate) => TO_COOPERATE,
+                    (PlayStatus::Cooperate, _) => TO_BETRAY,
+                    _ => STILL_BETRAY,
+                };
+                pixels.extend_from_slice(&color);
+            }
+        }
+        Some((width * scale, height * scale, pixels))
+    }
+
+    /// Writes the lattice as a `.png` or `.ppm` image.
+    pub fn write_frame(&self, path: &Path, scale: usize) -> Result<(), SpatialError> {
+        let (width, height, pixels) = self.pixels(scale).ok_or_else(|| {
+            SpatialError::Write(String::from("frames are only drawn for lattices"))
+        })?;
+        let error = |e: String| SpatialError::Write(format!("{}: {}", path.display(), e));
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ppm") => {
+                write_ppm(path, width, height, &pixels).map_err(|e| error(e.to_string()))
+            }
+            Some("png") => write_png(path, width, height, &pixels).map_err(error),
+            _ => Err(error(String::from(
+                "unknown format, use a .png or .ppm file",
+            ))),
+        }
+    }
+}
+
+/// Binary PPM: a short header then the RGB bytes.
+fn write_ppm(path: &Path, width: usize, height: usize, pixels: &[u8]) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(file, "P6\n{} {}\n255\n", width, height)?;
+    file.write_all(pixels)?;
+    file.flush()
+}
+
+fn write_png(path: &Path, width: usize, height: usize, pixels: &[u8]) -> Result<(), String> {
+    let file = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(pixels).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())
+}
@@ -0,0 +1,251 @@
+use crate::memory_one::MemoryOne;
+use crate::plugin::{Plugin, PluginError};
+use crate::rules::Rules;
+use crate::script::{Script, ScriptError};
+use rand::Rng;
+use settings::{PlayStatus, Settings};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Both moves of a round, seen from the strategy playing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Round {
+    pub play: PlayStatus,
+    /// `Stanby` when the move of the other player is not known.
+    pub other: PlayStatus,
+}
+
+/// What a strategy knows when it has to play.
+pub struct Turn<'a> {
+    /// Round to play, from 1.
+    pub round: u32,
+    pub total_round: u32,
+    pub bet: u32,
+    /// Balance of the strategy so far.
+    pub money: f64,
+    /// Balance of the other player so far.
+    pub other_money: f64,
+    /// Rounds already played in this party, in order.
+    pub history: &'a [Round],
+}
+
+impl Turn<'_> {
+    /// Last move of the other player, `None` in the first round or when it
+    /// is not known.
+    pub fn last_other(&self) -> Option<&PlayStatus> {
+        self.history
+            .last()
+            .map(|round| &round.other)
+            .filter(|other| **other != PlayStatus::Stanby)
+    }
+}
+
+/// Decides the moves of a player, from the rounds played so far. Bots play
+/// strategies over the network, tournaments play them against each other.
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    /// Called when a party starts, before its first move.
+    fn start(&mut self) {}
+
+    /// The move for `turn.round`.
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus;
+}
+
+/// Names accepted by `by_name`.
+pub const STRATEGY_NAMES: [&str; 5] = ["cooperate", "betray", "tit-for-tat", "grudger", "random"];
+
+/// One of the built-in strategies.
+pub fn by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    let strategy: Box<dyn Strategy> = match name {
+        "cooperate" => Box::new(Always(PlayStatus::Cooperate, "cooperate")),
+        "betray" => Box::new(Always(PlayStatus::Betrail, "betray")),
+        "tit-for-tat" => Box::new(TitForTat),
+        "grudger" => Box::new(Grudger { betrayed: false }),
+        "random" => Box::new(Random),
+        _ => return None,
+    };
+    Some(strategy)
+}
+
+/// A built-in strategy by name, a memory-one strategy as read by
+/// `MemoryOne::parse` for the payoff matrix of the settings, else a rules
+/// file (`.toml` or `.json`) or a script (`.rhai`) limited to `move_timeout`
+/// per move, or a WebAssembly plugin (`.wasm`, or `.wat` text) limited to
+/// `move_fuel` per move.
+pub fn load(spec: &str, settings: &Settings) -> Result<Box<dyn Strategy>, String> {
+    Factory::new(spec, settings)?.build()
+}
+
+/// Builds instances of a strategy whose file is read once, e.g. one per
+/// party a server plays at the same time. Scripts and plugins are compiled
+/// again from memory for each instance.
+#[derive(Clone)]
+pub struct Factory {
+    spec: String,
+    source: Source,
+}
+
+#[derive(Clone)]
+enum Source {
+    Named,
+    MemoryOne(MemoryOne),
+    Rules(Rules),
+    Script {
+        name: String,
+        text: String,
+        timeout: Duration,
+    },
+    Plugin {
+        name: String,
+        bytes: Vec<u8>,
+        fuel: u64,
+    },
+}
+
+impl Factory {
+    /// Reads the strategy of `spec`, as accepted by `load`. Scripts and
+    /// plugins are only compiled by `build`.
+    pub fn new(spec: &str, settings: &Settings) -> Result<Self, String> {
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", spec, e);
+        let source = if by_name(spec).is_some() {
+            Source::Named
+        } else if let Some(memory_one) = MemoryOne::parse(spec, &settings.payoff) {
+            Source::MemoryOne(memory_one.map_err(|e| error(&e))?)
+        } else {
+            let path = Path::new(spec);
+            let name = |default: &str| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or(default)
+                    .to_string()
+            };
+            match path.extension().and_then(|extension| extension.to_str()) {
+                Some("toml" | "json") => Source::Rules(Rules::load(path).map_err(|e| error(&e))?),
+                Some("rhai") => Source::Script {
+                    name: name("script"),
+                    text: fs::read_to_string(path)
+                        .map_err(|e| error(&ScriptError::Read(e.to_string())))?,
+                    timeout: Duration::from_millis(settings.move_timeout),
+                },
+                Some("wasm" | "wat") => {
+                    let bytes =
+                        fs::read(path).map_err(|e| error(&PluginError::Read(e.to_string())))?;
+                    Source::Plugin {
+                        name: name("plugin"),
+                        bytes: wat::parse_bytes(&bytes)
+                            .map_err(|e| error(&PluginError::Compile(e.to_string())))?
+                            .into_owned(),
+                        fuel: settings.move_fuel,
+                    }
+                }
+                _ => return Err(format!(
+                    "unknown strategy {}, use one of {}, memory-one:, extortion: or generous:, a .toml or .json rules file, a .rhai script or a .wasm plugin",
+                    spec,
+                    STRATEGY_NAMES.join(", ")
+                )),
+            }
+        };
+        Ok(Self {
+            spec: spec.to_string(),
+            source,
+        })
+    }
+
+    /// A new instance, in the state of a strategy just loaded.
+    pub fn build(&self) -> Result<Box<dyn Strategy>, String> {
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", self.spec, e);
+        let strategy: Box<dyn Strategy> = match &self.source {
+            Source::Named => by_name(&self.spec).expect("checked by new"),
+            Source::MemoryOne(memory_one) => Box::new(memory_one.clone()),
+            Source::Rules(rules) => Box::new(rules.clone()),
+            Source::Script {
+                name,
+                text,
+                timeout,
+            } => Box::new(Script::compile(name, text, *timeout).map_err(|e| error(&e))?),
+            Source::Plugin { name, bytes, fuel } => {
+                Box::new(Plugin::compile(name, bytes, *fuel).map_err(|e| error(&e))?)
+            }
+        };
+        Ok(strategy)
+    }
+}
+
+/// Cooperates with probability `cooperate`, from 0 to 1.
+pub(crate) fn choose(cooperate: f64) -> PlayStatus {
+    let cooperates = match cooperate {
+        p if p >= 1.0 => true,
+        p if p <= 0.0 => false,
+        p => rand::thread_rng().gen_bool(p),
+    };
+    if cooperates {
+        PlayStatus::Cooperate
+    } else {
+        PlayStatus::Betrail
+    }
+}
+
+/// Plays the same move every round.
+pub struct Always(pub PlayStatus, pub &'static str);
+
+impl Strategy for Always {
+    fn name(&self) -> &str {
+        self.1
+    }
+
+    fn next_move(&mut self, _turn: &Turn) -> PlayStatus {
+        self.0.clone()
+    }
+}
+
+/// Cooperates first, then plays the last move of the other player.
+pub struct TitForTat;
+
+impl Strategy for TitForTat {
+    fn name(&self) -> &str {
+        "tit-for-tat"
+    }
+
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus {
+        turn.last_other().cloned().unwrap_or(PlayStatus::Cooperate)
+    }
+}
+
+/// Cooperates until the other player betrays once, then always betrays.
+pub struct Grudger {
+    betrayed: bool,
+}
+
+impl Strategy for Grudger {
+    fn name(&self) -> &str {
+        "grudger"
+    }
+
+    fn start(&mut self) {
+        self.betrayed = false;
+    }
+
+    fn next_move(&mut self, turn: &Turn) -> PlayStatus {
+        self.betrayed |= turn.last_other() == Some(&PlayStatus::Betrail);
+        if self.betrayed {
+            PlayStatus::Betrail
+        } else {
+            PlayStatus::Cooperate
+        }
+    }
+}
+
+/// Cooperates or betrays with even odds.
+pub struct Random;
+
+impl Strategy for Random {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn next_move(&mut self, _turn: &Turn) -> PlayStatus {
+        choose(0.5)
+    }
+}
@@ -0,0 +1,149 @@
+use crate::strategy::{Round, Strategy, Turn};
+use settings::{PayoffMatrix, PlayStatus, Player, Settings};
+
+/// A party between two strategies, without the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// Rounds seen from the first strategy.
+    pub rounds: Vec<Round>,
+    /// Money won or lost by each strategy over the party.
+    pub payoffs: (f64, f64),
+}
+
+/// Results of a strategy over a tournament.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub matches: u32,
+    /// Money won or lost over every match.
+    pub payoff: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub rounds: u32,
+    pub cooperations: u32,
+}
+
+impl Standing {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            matches: 0,
+            payoff: 0.0,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            rounds: 0,
+            cooperations: 0,
+        }
+    }
+
+    /// Share of the rounds played cooperating, from 0 to 1.
+    pub fn cooperation(&self) -> f64 {
+        match self.rounds {
+            0 => 0.0,
+            rounds => self.cooperations as f64 / rounds as f64,
+        }
+    }
+
+    fn add(
+        &mut self,
+        payoff: f64,
+        other: f64,
+        rounds: &[Round],
+        own: impl Fn(&Round) -> &PlayStatus,
+    ) {
+        self.matches += 1;
+        self.payoff += payoff;
+        match payoff.partial_cmp(&other) {
+            Some(std::cmp::Ordering::Greater) => self.wins += 1,
+            Some(std::cmp::Ordering::Less) => self.losses += 1,
+            _ => self.draws += 1,
+        }
+        self.rounds += rounds.len() as u32;
+        self.cooperations += rounds
+            .iter()
+            .filter(|round| *own(round) == PlayStatus::Cooperate)
+            .count() as u32;
+    }
+}
+
+/// Round-robin between strategies: each one plays every other one, `repeat`
+/// parties of `rounds` rounds, with the payoff matrix of the server.
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    pub payoff: PayoffMatrix,
+    pub rounds: u32,
+    pub bet: u32,
+    pub repeat: u32,
+}
+
+impl Tournament {
+    /// Parties of `default_rounds` rounds betting `default_bet`.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            payoff: settings.payoff,
+            rounds: settings.default_rounds,
+            bet: settings.default_bet,
+            repeat: 1,
+        }
+    }
+
+    /// Plays one party between `first` and `second`.
+    pub fn play(&self, first: &mut dyn Strategy, second: &mut dyn Strategy) -> Match {
+        first.start();
+        second.start();
+        let start = Player::default().money;
+        let mut rounds: Vec<Round> = Vec::new();
+        let mut mirrored: Vec<Round> = Vec::new();
+        let (mut money, mut other_money) = (start, start);
+        for round in 1..=self.rounds {
+            let play = first.next_move(&self.turn(round, (money, other_money), &rounds));
+            let other = second.next_move(&self.turn(round, (other_money, money), &mirrored));
+            money += self.payoff.money(&play, &other, self.bet);
+            other_money += self.payoff.money(&other, &play, self.bet);
+            mirrored.push(Round {
+                play: other.clone(),
+                other: play.clone(),
+            });
+            rounds.push(Round { play, other });
+        }
+        Match {
+            rounds,
+            payoffs: (money - start, other_money - start),
+        }
+    }
+
+    /// Plays every pair of strategies and returns the standings, best payoff
+    /// first.
+    pub fn run(&self, strategies: &mut [Box<dyn Strategy>]) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = strategies
+            .iter()
+            .map(|strategy| Standing::new(strategy.name()))
+            .collect();
+        for first in 0..strategies.len() {
+            for second in first + 1..strategies.len() {
+                let (head, tail) = strategies.split_at_mut(second);
+                for _ in 0..self.repeat {
+                    let played = self.play(head[first].as_mut(), tail[0].as_mut());
+                    let (payoff, other) = played.payoffs;
+                    standings[first].add(payoff, other, &played.rounds, |round| &round.play);
+                    standings[second].add(other, payoff, &played.rounds, |round| &round.other);
+                }
+            }
+        }
+        standings.sort_by(|a, b| b.payoff.total_cmp(&a.payoff));
+        standings
+    }
+
+    fn turn<'a>(&self, round: u32, money: (f64, f64), history: &'a [Round]) -> Turn<'a> {
+        Turn {
+            round,
+            total_round: self.rounds,
+            bet: self.bet,
+            money: money.0,
+            other_money: money.1,
+            history,
+        }
+    }
+}
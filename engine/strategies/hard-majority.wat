@@ -0,0 +1,29 @@
+;; Betrays first and whenever the other player betrayed at least as often as
+;; they cooperated. Build a .wasm with `wat2wasm hard-majority.wat`, or load
+;; the text as is.
+(module
+  (memory (export "memory") 1)
+
+  ;; the history always fits at the start of the page
+  (func (export "alloc") (param $len i32) (result i32)
+    i32.const 0)
+
+  (func (export "next_move") (param $history i32) (param $rounds i32) (result i32)
+    (local $i i32)
+    (local $balance i32)
+    (block $done
+      (loop $round
+        (br_if $done (i32.ge_u (local.get $i) (local.get $rounds)))
+        ;; the other move is the second byte: 0 betray, 1 cooperate, 2 unknown
+        (block $next
+          (block $betray
+            (block $cooperate
+              (br_table $betray $cooperate $next
+                (i32.load8_u offset=1
+                  (i32.add (local.get $history) (i32.shl (local.get $i) (i32.const 1))))))
+            (local.set $balance (i32.add (local.get $balance) (i32.const 1)))
+            (br $next))
+          (local.set $balance (i32.sub (local.get $balance) (i32.const 1))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $round)))
+    (i32.gt_s (local.get $balance) (i32.const 0))))
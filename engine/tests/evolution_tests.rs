@@ -0,0 +1,124 @@
+use engine::strategy;
+use engine::{Dynamics, Evolution, Generations, Strategy};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use settings::Settings;
+use std::fs;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evolution(dynamics: Dynamics) -> Evolution {
+        let mut evolution = Evolution::new(&Settings::default());
+        evolution.tournament.rounds = 10;
+        evolution.dynamics = dynamics;
+        evolution
+    }
+
+    fn run(evolution: &Evolution, names: &[&str]) -> Generations {
+        let load = |name: &&str| strategy::by_name(name).unwrap();
+        let mut strategies: Vec<Box<dyn Strategy>> = names.iter().map(load).collect();
+        let mut twins: Vec<Box<dyn Strategy>> = names.iter().map(load).collect();
+        evolution.run(&mut strategies, &mut twins, &mut StdRng::seed_from_u64(7))
+    }
+
+    fn assert_shares(shares: &[f64]) {
+        let total: f64 = shares.iter().sum();
+        assert!((total - 1.0).abs() < 1e-9, "shares sum to {}", total);
+        assert!(shares.iter().all(|share| (0.0..=1.0).contains(share)));
+    }
+
+    #[test]
+    fn payoffs_include_parties_against_itself() {
+        let generations = run(&evolution(Dynamics::Replicator), &["cooperate", "betray"]);
+        assert_eq!(generations.names, ["cooperate", "betray"]);
+        // 10 rounds betting 10 with the default payoff matrix
+        assert_eq!(
+            generations.payoffs,
+            vec![vec![50.0, -200.0], vec![200.0, -100.0]]
+        );
+    }
+
+    #[test]
+    fn replicator_dynamics() {
+        let mut evolution = evolution(Dynamics::Replicator);
+        evolution.generations = 50;
+        let generations = run(&evolution, &["cooperate", "betray"]);
+        assert_eq!(generations.shares.len(), 51);
+        assert_eq!(generations.shares[0], [0.5, 0.5]);
+        for pair in generations.shares.windows(2) {
+            assert_shares(&pair[1]);
+            assert!(pair[1][1] >= pair[0][1]);
+        }
+        assert!(generations.shares[50][1] > 0.99);
+
+        // without selection nothing changes, mutation keeps every strategy
+        let payoffs = generations.payoffs;
+        evolution.selection = 0.0;
+        let shares = evolution.evolve(&payoffs, &[0.2, 0.8], &mut rand::thread_rng());
+        assert!(shares.iter().all(|shares| shares == &[0.2, 0.8]));
+        evolution.selection = 1.0;
+        evolution.mutation = 0.1;
+        let shares = evolution.evolve(&payoffs, &[0.5, 0.5], &mut rand::thread_rng());
+        assert!(shares.last().unwrap()[0] > 0.04);
+    }
+
+    #[test]
+    fn moran_process() {
+        let mut evolution = evolution(Dynamics::Moran { population: 10 });
+        evolution.generations = 20;
+        let generations = run(&evolution, &["cooperate", "betray", "tit-for-tat"]);
+        assert_eq!(generations.shares.len(), 21);
+        // 10 players split as 4, 3 and 3
+        assert_eq!(generations.shares[0], [0.4, 0.3, 0.3]);
+        for shares in &generations.shares {
+            assert_shares(shares);
+            for share in shares {
+                assert!((share * 10.0 - (share * 10.0).round()).abs() < 1e-9);
+            }
+        }
+
+        // the same seed gives the same generations
+        let again = run(&evolution, &["cooperate", "betray", "tit-for-tat"]);
+        assert_eq!(again.shares, generations.shares);
+
+        // selection favours betrayers against cooperators
+        let payoffs = vec![vec![50.0, -200.0], vec![200.0, -100.0]];
+        let shares = evolution.evolve(&payoffs, &[0.5, 0.5], &mut StdRng::seed_from_u64(7));
+        assert_eq!(shares[20], [0.0, 1.0]);
+
+        // a strategy gone never comes back without mutation
+        let mut rng = rand::thread_rng();
+        let shares = evolution.evolve(&generations.payoffs, &[0.0, 1.0, 0.0], &mut rng);
+        assert!(shares.iter().all(|shares| shares == &[0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn write_generations() {
+        let generations = Generations {
+            names: vec![
+                String::from("tit-for-tat"),
+                String::from("memory-one:1,0,0,1"),
+            ],
+            payoffs: vec![vec![50.0, 50.0], vec![50.0, 50.0]],
+            shares: vec![vec![0.5, 0.5], vec![0.25, 0.75]],
+        };
+        let mut csv = Vec::new();
+        generations.write_csv_to(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "generation,tit-for-tat,\"memory-one:1,0,0,1\"\n0,0.5,0.5\n1,0.25,0.75\n"
+        );
+
+        let dir = std::env::temp_dir().join(format!("evolution_tests_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["shares.csv", "shares.xlsx"] {
+            generations.write(&dir.join(name)).unwrap();
+            assert!(fs::metadata(dir.join(name)).unwrap().len() > 0);
+        }
+        let error = generations.write(&dir.join("shares.txt")).unwrap_err();
+        assert!(error.ends_with("unknown format, use a .csv or .xlsx file"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
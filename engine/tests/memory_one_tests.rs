@@ -0,0 +1,137 @@
+use engine::memory_one::{expected_payoffs, stationary};
+use engine::strategy;
+use engine::{MemoryOne, Round, Strategy, Turn};
+use settings::{PayoffMatrix, PlayStatus, Settings};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(history: &[Round]) -> Turn<'_> {
+        Turn {
+            round: history.len() as u32 + 1,
+            total_round: 5,
+            bet: 10,
+            money: 100.0,
+            other_money: 100.0,
+            history,
+        }
+    }
+
+    fn memory_one(first: f64, p: [f64; 4]) -> MemoryOne {
+        MemoryOne::new("test", first, p).unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn moves_follow_the_last_round() {
+        // pavlov
+        let mut pavlov = memory_one(1.0, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pavlov.next_move(&turn(&[])), PlayStatus::Cooperate);
+        let round = |play, other| Round { play, other };
+        let history = [
+            round(PlayStatus::Cooperate, PlayStatus::Cooperate),
+            round(PlayStatus::Cooperate, PlayStatus::Betrail),
+        ];
+        assert_eq!(pavlov.next_move(&turn(&history)), PlayStatus::Betrail);
+        let history = [round(PlayStatus::Betrail, PlayStatus::Betrail)];
+        assert_eq!(pavlov.next_move(&turn(&history)), PlayStatus::Cooperate);
+        // an unknown move plays as the first round
+        let history = [round(PlayStatus::Betrail, PlayStatus::Stanby)];
+        assert_eq!(pavlov.cooperation(&history), 1.0);
+
+        let error = MemoryOne::new("test", 1.0, [1.0, 1.5, 0.0, 0.0]).unwrap_err();
+        assert_eq!(error, "CD must be between 0 and 1, got 1.5");
+    }
+
+    #[test]
+    fn zero_determinant_strategies() {
+        let payoff = PayoffMatrix::default();
+        let extortion = MemoryOne::extortion(&payoff, 3.0, 1.0).unwrap();
+        assert_eq!(extortion.name(), "extortion-3");
+        let expected = [0.7, 0.0, 0.6, 0.0];
+        for (p, expected) in extortion.probabilities().into_iter().zip(expected) {
+            assert_close(p, expected);
+        }
+
+        // the linear relation holds whatever the other player does
+        let generous = MemoryOne::generous(&payoff, 2.0, 0.5).unwrap();
+        let others = [
+            memory_one(1.0, [1.0, 0.0, 1.0, 0.0]),
+            memory_one(0.5, [0.5, 0.5, 0.5, 0.5]),
+            memory_one(0.0, [0.9, 0.2, 0.7, 0.1]),
+        ];
+        for other in &others {
+            let (own, theirs) = expected_payoffs(&payoff, &extortion, other);
+            assert_close(own - payoff.punishment, 3.0 * (theirs - payoff.punishment));
+            let (own, theirs) = expected_payoffs(&payoff, &generous, other);
+            assert_close(own - payoff.reward, 2.0 * (theirs - payoff.reward));
+        }
+
+        let error = MemoryOne::extortion(&payoff, 0.5, 1.0).unwrap_err();
+        assert_eq!(error, "chi must be at least 1, got 0.5");
+        let error = MemoryOne::generous(&payoff, 2.0, 0.0).unwrap_err();
+        assert_eq!(error, "phi must be above 0 and at most 1, got 0");
+        let harmony = PayoffMatrix {
+            temptation: 0.0,
+            ..payoff
+        };
+        assert!(MemoryOne::extortion(&harmony, 2.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn long_run_payoffs() {
+        let payoff = PayoffMatrix::default();
+        let tit_for_tat = memory_one(1.0, [1.0, 0.0, 1.0, 0.0]);
+        let betray = memory_one(0.0, [0.0; 4]);
+        let random = memory_one(0.5, [0.5; 4]);
+
+        assert_eq!(stationary(&tit_for_tat, &betray), [0.0, 0.0, 0.0, 1.0]);
+        let (own, other) = expected_payoffs(&payoff, &tit_for_tat, &random);
+        assert_close(own, (0.5 - 2.0 + 2.0 - 1.0) / 4.0);
+        assert_close(other, own);
+        let (own, other) = expected_payoffs(&payoff, &betray, &random);
+        assert_close(own, (2.0 - 1.0) / 2.0);
+        assert_close(other, (-2.0 - 1.0) / 2.0);
+
+        // no single stationary distribution: it depends on the first round
+        let (own, other) = expected_payoffs(&payoff, &tit_for_tat, &tit_for_tat);
+        assert_close(own, payoff.reward);
+        assert_close(other, payoff.reward);
+        let suspicious = memory_one(0.0, [1.0, 0.0, 1.0, 0.0]);
+        let (own, other) = expected_payoffs(&payoff, &tit_for_tat, &suspicious);
+        assert_close(own, (payoff.sucker + payoff.temptation) / 2.0);
+        assert_close(other, own);
+    }
+
+    #[test]
+    fn load_memory_one_strategies() {
+        let settings = Settings::default();
+        let pavlov = strategy::load("memory-one:1,0,0,1", &settings).unwrap();
+        assert_eq!(pavlov.name(), "memory-one:1,0,0,1");
+        assert_eq!(
+            strategy::load("extortion:3", &settings).unwrap().name(),
+            "extortion-3"
+        );
+        assert_eq!(
+            strategy::load("generous:2,0.5", &settings).unwrap().name(),
+            "generous-2"
+        );
+
+        let error = strategy::load("memory-one:1,0", &settings).err().unwrap();
+        assert_eq!(
+            error,
+            "memory-one:1,0: expected memory-one:<p_cc>,<p_cd>,<p_dc>,<p_dd>[,<first>]"
+        );
+        let error = strategy::load("extortion:x", &settings).err().unwrap();
+        assert_eq!(error, "extortion:x: x is not a number");
+    }
+}
@@ -0,0 +1,158 @@
+use engine::strategy;
+use engine::{Plugin, PluginError, Round, Strategy, Turn};
+use settings::{PlayStatus, Settings};
+use std::path::Path;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FUEL: u64 = 10_000;
+
+    fn compiled(body: &str) -> Result<Plugin, PluginError> {
+        let wasm = wat::parse_str(format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) i32.const 16)
+                {})"#,
+            body
+        ))
+        .unwrap();
+        Plugin::compile("test", &wasm, FUEL)
+    }
+
+    fn turn(history: &[Round]) -> Turn<'_> {
+        Turn {
+            round: history.len() as u32 + 1,
+            total_round: 5,
+            bet: 10,
+            money: 100.0,
+            other_money: 100.0,
+            history,
+        }
+    }
+
+    fn round(play: PlayStatus, other: PlayStatus) -> Round {
+        Round { play, other }
+    }
+
+    #[test]
+    fn history_is_written_in_memory() {
+        // plays the other's last move
+        let mut plugin = compiled(
+            r#"
+            (func (export "init"))
+            (func (export "next_move") (param $history i32) (param $rounds i32) (result i32)
+                (if (result i32) (i32.eqz (local.get $rounds))
+                    (then (i32.const 1))
+                    (else (i32.load8_u
+                        (i32.sub (i32.add (local.get $history) (i32.shl (local.get $rounds) (i32.const 1)))
+                                 (i32.const 1))))))
+            "#,
+        )
+        .unwrap();
+        plugin.start();
+        assert_eq!(plugin.call(&turn(&[])).unwrap(), PlayStatus::Cooperate);
+        let history = [
+            round(PlayStatus::Cooperate, PlayStatus::Cooperate),
+            round(PlayStatus::Cooperate, PlayStatus::Betrail),
+        ];
+        assert_eq!(plugin.call(&turn(&history)).unwrap(), PlayStatus::Betrail);
+
+        // an unknown move is 2, which is not a move
+        let history = [round(PlayStatus::Cooperate, PlayStatus::Stanby)];
+        let error = plugin.call(&turn(&history)).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "plugin failed: next_move returned 2, expected 1 (cooperate) or 0 (betray)"
+        );
+        assert_eq!(plugin.next_move(&turn(&history)), PlayStatus::Cooperate);
+    }
+
+    #[test]
+    fn moves_are_limited_by_fuel() {
+        let mut plugin = compiled(
+            r#"
+            (func (export "next_move") (param i32) (param i32) (result i32)
+                (loop $forever (br $forever))
+                (i32.const 0))
+            "#,
+        )
+        .unwrap();
+        let error = plugin.call(&turn(&[])).unwrap_err().to_string();
+        assert_eq!(error, "plugin failed: no move within 10000 fuel");
+        // fuel is given again for each move
+        let error = plugin.call(&turn(&[])).unwrap_err().to_string();
+        assert!(error.contains("10000 fuel"));
+        assert_eq!(plugin.next_move(&turn(&[])), PlayStatus::Cooperate);
+
+        // the start function is limited too
+        let error = compiled(
+            r#"
+            (func $start (loop $forever (br $forever)))
+            (start $start)
+            "#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "cannot compile plugin: no move within 10000 fuel"
+        );
+    }
+
+    #[test]
+    fn modules_are_sandboxed() {
+        let wasm =
+            wat::parse_str(r#"(module (import "env" "clock" (func (result i64))))"#).unwrap();
+        let error = Plugin::compile("clock", &wasm, FUEL).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "cannot compile plugin: plugins may not import anything, found env::clock"
+        );
+
+        // memory cannot grow past the limit
+        let mut plugin = compiled(
+            r#"
+            (func (export "next_move") (param i32) (param i32) (result i32)
+                (i32.ne (memory.grow (i32.const 1000)) (i32.const -1)))
+            "#,
+        )
+        .unwrap();
+        assert_eq!(plugin.call(&turn(&[])).unwrap(), PlayStatus::Betrail);
+    }
+
+    #[test]
+    fn exports_are_checked() {
+        let error = compiled("").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "cannot compile plugin: no function next_move(i32, i32) -> i32 exported"
+        );
+        let error = compiled(r#"(func (export "next_move") (param i32) (result i32) i32.const 1)"#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("next_move(i32, i32) -> i32"));
+
+        let error = Plugin::compile("junk", b"\0asm junk", FUEL).err().unwrap();
+        assert!(error.to_string().starts_with("cannot compile plugin"));
+    }
+
+    #[test]
+    fn load_plugins() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("strategies/hard-majority.wat");
+        let mut plugin = strategy::load(path.to_str().unwrap(), &Settings::default()).unwrap();
+        assert_eq!(plugin.name(), "hard-majority");
+        assert_eq!(plugin.next_move(&turn(&[])), PlayStatus::Betrail);
+        let history = [
+            round(PlayStatus::Betrail, PlayStatus::Cooperate),
+            round(PlayStatus::Cooperate, PlayStatus::Stanby),
+        ];
+        assert_eq!(plugin.next_move(&turn(&history)), PlayStatus::Cooperate);
+
+        let error = strategy::load("missing.wasm", &Settings::default())
+            .err()
+            .unwrap();
+        assert!(error.starts_with("missing.wasm: cannot read plugin"));
+    }
+}
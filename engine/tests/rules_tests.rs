@@ -0,0 +1,181 @@
+use engine::strategy;
+use engine::{Round, Rules, RulesError, Strategy, Turn};
+use settings::{PlayStatus, Settings};
+use std::path::Path;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(letters: &str) -> Round {
+        let play = |letter| match letter {
+            'C' => PlayStatus::Cooperate,
+            _ => PlayStatus::Betrail,
+        };
+        let mut letters = letters.chars();
+        Round {
+            play: play(letters.next().unwrap()),
+            other: play(letters.next().unwrap()),
+        }
+    }
+
+    fn problems_of(text: &str) -> Vec<String> {
+        match Rules::from_toml(text) {
+            Err(RulesError::Invalid(problems)) => problems,
+            other => panic!("expected invalid rules, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn memory_one_rules() {
+        let rules = Rules::from_toml(
+            r#"
+            name = "pavlov"
+            first = 1
+
+            [[rules]]
+            after = "CC"
+            cooperate = 1
+
+            [[rules]]
+            after = "dd"
+            cooperate = 1.0
+
+            [[rules]]
+            after = "**"
+            cooperate = 0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.name(), "pavlov");
+        assert_eq!(rules.cooperation(&[]), 1.0);
+        assert_eq!(rules.cooperation(&[round("DD"), round("CC")]), 1.0);
+        assert_eq!(rules.cooperation(&[round("CC"), round("CD")]), 0.0);
+        assert_eq!(rules.cooperation(&[round("DC")]), 0.0);
+
+        let mut rules = rules;
+        let history = [round("CD")];
+        let turn = Turn {
+            round: 2,
+            total_round: 5,
+            bet: 10,
+            money: 80.0,
+            other_money: 140.0,
+            history: &history,
+        };
+        assert_eq!(rules.next_move(&turn), PlayStatus::Betrail);
+    }
+
+    #[test]
+    fn memory_two_rules_in_json() {
+        let rules = Rules::from_json(
+            r#"{
+                "memory": 2,
+                "first": 0.0,
+                "default": 1.0,
+                "rules": [{ "after": "*D *D", "cooperate": 0.25 }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!((rules.name(), rules.memory()), ("rules", 2));
+        assert_eq!(rules.cooperation(&[round("CD")]), 0.0);
+        assert_eq!(rules.cooperation(&[round("CD"), round("DD")]), 0.25);
+        assert_eq!(
+            rules.cooperation(&[round("DD"), round("CD"), round("CC")]),
+            1.0
+        );
+    }
+
+    #[test]
+    fn every_problem_is_listed() {
+        let problems = problems_of(
+            r#"
+            name = " "
+            memory = 2
+            first = 2
+
+            [[rules]]
+            after = "CX CC"
+            cooperate = 0.5
+
+            [[rules]]
+            after = "CC C"
+            cooperate = -1
+            "#,
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "name must not be empty",
+                "first is a chance to cooperate, between 0 and 1, got 2",
+                "rules[0].after `CX CC` has an unknown move `X`, use C (cooperate), D (betray) or *",
+                "rules[1].cooperate is a chance to cooperate, between 0 and 1, got -1",
+                "rules[1].after `CC C` must give 2 round(s) of two moves, own then other, e.g. `CD CD`, got 3 move(s)",
+            ]
+        );
+
+        let problems = problems_of(
+            r#"
+            [[rules]]
+            after = "C*"
+            cooperate = 1
+
+            [[rules]]
+            after = "CD"
+            cooperate = 0
+            "#,
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "rules[1] never applies, earlier rules match every history it matches",
+                "no rule after DC, DD: add rules or a default",
+            ]
+        );
+
+        assert!(problems_of("memory = 0\ndefault = 1")
+            .contains(&String::from("memory must be between 1 and 6, got 0")));
+        assert_eq!(
+            problems_of(""),
+            vec!["no rules: add [[rules]] or a default"]
+        );
+    }
+
+    #[test]
+    fn parse_errors_tell_where() {
+        let error = Rules::from_toml("[[rules]]\nafter = \"CC\"\ncooperate = 1\nfoo = 3\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("cannot parse rules"));
+        assert!(error.contains("line 4"));
+        assert!(error.contains("unknown field `foo`"));
+
+        let error = Rules::from_json("{\"rules\": [{\"after\": \"CC\", \"cooperate\": \"yes\"}]}")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("line 1"));
+    }
+
+    #[test]
+    fn load_rules_files() {
+        let strategies = Path::new(env!("CARGO_MANIFEST_DIR")).join("strategies");
+        for file in [
+            "generous-tit-for-tat.toml",
+            "pavlov.toml",
+            "tit-for-two-tats.json",
+        ] {
+            let path = strategies.join(file);
+            let strategy = strategy::load(path.to_str().unwrap(), &Settings::default()).unwrap();
+            assert_eq!(strategy.name(), file.split('.').next().unwrap());
+        }
+
+        let error = strategy::load("missing.toml", &Settings::default())
+            .err()
+            .unwrap();
+        assert!(error.starts_with("missing.toml: cannot read rules"));
+        let error = strategy::load("tit-for-tat.yaml", &Settings::default())
+            .err()
+            .unwrap();
+        assert!(error.starts_with("unknown strategy tit-for-tat.yaml"));
+    }
+}
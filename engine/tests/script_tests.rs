@@ -0,0 +1,143 @@
+use engine::strategy;
+use engine::{Factory, Round, Script, ScriptError, Strategy, Turn};
+use settings::{PlayStatus, Settings};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    fn turn(history: &[Round]) -> Turn<'_> {
+        Turn {
+            round: history.len() as u32 + 1,
+            total_round: 3,
+            bet: 10,
+            money: 100.0,
+            other_money: 120.0,
+            history,
+        }
+    }
+
+    fn betrayed() -> Round {
+        Round {
+            play: PlayStatus::Cooperate,
+            other: PlayStatus::Betrail,
+        }
+    }
+
+    fn compile(source: &str) -> Result<Script, ScriptError> {
+        Script::compile("test", source, TIMEOUT)
+    }
+
+    #[test]
+    fn scripts_see_the_turn() {
+        let mut script = compile(
+            r#"
+            const LIMIT = 110.0;
+            fn next_move(turn) {
+                if turn.history.len() != turn.other.len() { return "error"; }
+                if turn.round == 1 { return "cooperate"; }
+                if turn.other_money > global::LIMIT && turn.own[0] == "cooperate" {
+                    return "betray";
+                }
+                turn.history.last().other == "cooperate"
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(script.name(), "test");
+        assert_eq!(script.call(&turn(&[])).unwrap(), PlayStatus::Cooperate);
+        assert_eq!(
+            script.call(&turn(&[betrayed()])).unwrap(),
+            PlayStatus::Betrail
+        );
+    }
+
+    #[test]
+    fn compile_errors_tell_where() {
+        let error = compile("fn next_move(turn) {\n  if turn.round { \n}")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.starts_with("cannot compile script"));
+        assert!(error.contains("line 3"), "{}", error);
+
+        let error = compile("fn play(turn) { true }").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "cannot compile script: no function next_move(turn) found"
+        );
+        assert!(compile("eval(\"1\"); fn next_move(turn) { true }").is_err());
+        // no clock either
+        let error = compile("let now = timestamp(); fn next_move(turn) { true }")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("timestamp"), "{}", error);
+    }
+
+    #[test]
+    fn moves_are_time_limited() {
+        let mut script = compile("fn next_move(turn) { loop { } }").unwrap();
+        let start = Instant::now();
+        let error = script.call(&turn(&[])).unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(error.to_string(), "script failed: no move within 50 ms");
+        // A failing script cooperates
+        assert_eq!(script.next_move(&turn(&[])), PlayStatus::Cooperate);
+
+        assert!(compile("loop { } fn next_move(turn) { true }").is_err());
+    }
+
+    #[test]
+    fn scripts_must_return_a_move() {
+        let mut script = compile("fn next_move(turn) { 42 }").unwrap();
+        let error = script.call(&turn(&[])).unwrap_err().to_string();
+        assert!(error.contains("returned 42"), "{}", error);
+
+        let mut script = compile("fn next_move(turn) { turn.missing.len() }").unwrap();
+        assert!(script.call(&turn(&[])).is_err());
+        let mut script = compile("fn next_move(turn) { let s = \"x\"; loop { s += s; } }").unwrap();
+        assert!(script.call(&turn(&[])).is_err());
+    }
+
+    #[test]
+    fn load_script_files() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("strategies/soft-majority.rhai");
+        let mut strategy = strategy::load(path.to_str().unwrap(), &Settings::default()).unwrap();
+        assert_eq!(strategy.name(), "soft-majority");
+        assert_eq!(strategy.next_move(&turn(&[])), PlayStatus::Cooperate);
+        assert_eq!(
+            strategy.next_move(&turn(&[betrayed()])),
+            PlayStatus::Betrail
+        );
+        // Last round, behind
+        let history = [betrayed(), betrayed()];
+        assert_eq!(strategy.next_move(&turn(&history)), PlayStatus::Betrail);
+
+        let error = strategy::load("missing.rhai", &Settings::default())
+            .err()
+            .unwrap();
+        assert!(error.starts_with("missing.rhai: cannot read script"));
+    }
+
+    #[test]
+    fn factories_read_the_script_once() {
+        let dir = std::env::temp_dir().join(format!("factory-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("betray.rhai");
+        std::fs::write(&path, "fn next_move(turn) { false }").unwrap();
+        let factory = Factory::new(path.to_str().unwrap(), &Settings::default()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        for _ in 0..2 {
+            let mut strategy = factory.build().unwrap();
+            assert_eq!(strategy.name(), "betray");
+            assert_eq!(strategy.next_move(&turn(&[])), PlayStatus::Betrail);
+        }
+        assert!(Factory::new("missing.rhai", &Settings::default()).is_err());
+    }
+}
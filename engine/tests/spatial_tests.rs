@@ -0,0 +1,145 @@
+use engine::{Graph, Neighbourhood, Spatial, SpatialError, Stats};
+use settings::{PayoffMatrix, PlayStatus};
+use std::fs;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nowak and May's matrix, betraying paying `temptation` against a
+    /// cooperator and nothing otherwise.
+    fn nowak_may(temptation: f64) -> PayoffMatrix {
+        PayoffMatrix {
+            reward: 1.0,
+            sucker: 0.0,
+            temptation,
+            punishment: 0.0,
+        }
+    }
+
+    /// A `size` by `size` lattice of cooperators with one betrayer in the
+    /// middle.
+    fn single_betrayer(size: usize, temptation: f64) -> Spatial {
+        let graph = Graph::lattice(size, size, Neighbourhood::Moore);
+        let mut moves = vec![PlayStatus::Cooperate; size * size];
+        moves[size * size / 2] = PlayStatus::Betrail;
+        Spatial::new(graph, nowak_may(temptation), moves)
+    }
+
+    fn betrayers(spatial: &Spatial) -> Vec<usize> {
+        (0..spatial.moves().len())
+            .filter(|cell| spatial.moves()[*cell] == PlayStatus::Betrail)
+            .collect()
+    }
+
+    #[test]
+    fn lattices_wrap_around() {
+        let moore = Graph::lattice(5, 4, Neighbourhood::Moore);
+        assert_eq!((moore.len(), moore.lattice_size()), (20, Some((5, 4))));
+        assert_eq!(moore.neighbours(0), [1, 4, 5, 6, 9, 15, 16, 19]);
+        let von_neumann = Graph::lattice(5, 4, Neighbourhood::VonNeumann);
+        assert_eq!(von_neumann.neighbours(0), [1, 4, 5, 15]);
+        // no cell is its own neighbour, nor twice the same one
+        let narrow = Graph::lattice(2, 1, Neighbourhood::Moore);
+        assert_eq!(narrow.neighbours(0), [1]);
+    }
+
+    #[test]
+    fn edge_lists() {
+        let graph = Graph::parse("# a star\nhub a\nhub b\n\na hub\nb b\nhub\tc\n").unwrap();
+        assert_eq!(graph.len(), 4);
+        assert_eq!(graph.lattice_size(), None);
+        assert_eq!(graph.neighbours(0), [1, 2, 3]);
+        assert_eq!(graph.neighbours(2), [0]);
+
+        let error = Graph::parse("a b\na b c\n").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "cannot parse graph: line 2: expected two nodes, got a b c"
+        );
+        assert!(matches!(
+            Graph::parse("# nothing\n"),
+            Err(SpatialError::Parse(_))
+        ));
+        assert!(matches!(
+            Graph::load("missing.edges".as_ref()),
+            Err(SpatialError::Read(_))
+        ));
+    }
+
+    #[test]
+    fn agents_imitate_the_best_neighbour() {
+        // a betrayer among cooperators scores 8 * 1.85, more than the 9 of a
+        // cooperator, and its neighbours follow it
+        let mut spatial = single_betrayer(7, 1.85);
+        assert_eq!(
+            spatial.stats(),
+            Stats {
+                generation: 0,
+                agents: 49,
+                cooperators: 48,
+                mean_payoff: (40.0 * 9.0 + 8.0 * 8.0 + 8.0 * 1.85) / 49.0,
+                changed: 0,
+            }
+        );
+        let stats = spatial.step();
+        assert_eq!(
+            (stats.generation, stats.cooperators, stats.changed),
+            (1, 40, 8)
+        );
+        assert_eq!(betrayers(&spatial), [16, 17, 18, 23, 24, 25, 30, 31, 32]);
+
+        // below 9 / 8, it scores less and cooperators stay
+        let mut spatial = single_betrayer(7, 1.1);
+        let stats = spatial.step();
+        assert_eq!((stats.cooperators, stats.changed), (48, 0));
+        assert_eq!(betrayers(&spatial), [24]);
+
+        // without playing against itself, a cooperator scores 8
+        let mut spatial = single_betrayer(7, 1.1);
+        spatial.self_play = false;
+        assert_eq!(spatial.payoffs()[0], 8.0);
+        assert_eq!(spatial.step().cooperators, 40);
+    }
+
+    #[test]
+    fn frames() {
+        let mut spatial = single_betrayer(3, 1.85);
+        let (width, height, pixels) = spatial.pixels(2).unwrap();
+        assert_eq!((width, height, pixels.len()), (6, 6, 6 * 6 * 3));
+        // blue cooperators, red betrayers
+        assert_eq!(pixels[..3], [0, 0, 255]);
+        assert_eq!(pixels[(2 * 6 + 2) * 3..][..3], [255, 0, 0]);
+        // yellow for cooperators who start betraying
+        spatial.step();
+        let (_, _, pixels) = spatial.pixels(1).unwrap();
+        assert_eq!(pixels[..3], [255, 255, 0]);
+
+        let dir = std::env::temp_dir().join(format!("spatial_tests_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        spatial.write_frame(&dir.join("frame.ppm"), 2).unwrap();
+        let ppm = fs::read(dir.join("frame.ppm")).unwrap();
+        assert!(ppm.starts_with(b"P6\n6 6\n255\n"));
+        assert_eq!(ppm.len(), 11 + 6 * 6 * 3);
+        spatial.write_frame(&dir.join("frame.png"), 2).unwrap();
+        assert!(fs::read(dir.join("frame.png"))
+            .unwrap()
+            .starts_with(b"\x89PNG"));
+        assert!(spatial.write_frame(&dir.join("frame.gif"), 2).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let graph = Graph::parse("a b\n").unwrap();
+        let spatial = Spatial::new(graph, nowak_may(1.5), vec![PlayStatus::Cooperate; 2]);
+        assert!(spatial.pixels(1).is_none());
+    }
+
+    #[test]
+    fn statistics_as_csv() {
+        let spatial = single_betrayer(3, 1.0);
+        assert_eq!(
+            Stats::COLUMNS.join(","),
+            "generation,cooperators,cooperation,mean_payoff,changed"
+        );
+        assert_eq!(spatial.stats().csv(), "0,8,0.8888888888888888,8,0");
+    }
+}
@@ -0,0 +1,119 @@
+use engine::strategy::{self, STRATEGY_NAMES};
+use engine::{Round, Strategy, Tournament, Turn};
+use settings::{PlayStatus, Settings};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(rounds: u32) -> Tournament {
+        Tournament {
+            rounds,
+            ..Tournament::new(&Settings::default())
+        }
+    }
+
+    fn turn(history: &[Round]) -> Turn<'_> {
+        Turn {
+            round: history.len() as u32 + 1,
+            total_round: 5,
+            bet: 10,
+            money: 100.0,
+            other_money: 100.0,
+            history,
+        }
+    }
+
+    fn betrayed() -> Round {
+        Round {
+            play: PlayStatus::Cooperate,
+            other: PlayStatus::Betrail,
+        }
+    }
+
+    #[test]
+    fn built_in_strategies() {
+        assert!(strategy::by_name("nobody").is_none());
+        for name in STRATEGY_NAMES {
+            assert_eq!(strategy::by_name(name).unwrap().name(), name);
+        }
+
+        let mut tit_for_tat = strategy::by_name("tit-for-tat").unwrap();
+        assert_eq!(tit_for_tat.next_move(&turn(&[])), PlayStatus::Cooperate);
+        assert_eq!(
+            tit_for_tat.next_move(&turn(&[betrayed()])),
+            PlayStatus::Betrail
+        );
+        let unknown = Round {
+            play: PlayStatus::Cooperate,
+            other: PlayStatus::Stanby,
+        };
+        assert_eq!(
+            tit_for_tat.next_move(&turn(&[unknown])),
+            PlayStatus::Cooperate
+        );
+
+        let mut grudger = strategy::by_name("grudger").unwrap();
+        grudger.next_move(&turn(&[betrayed()]));
+        let forgiven = [
+            betrayed(),
+            Round {
+                play: PlayStatus::Betrail,
+                other: PlayStatus::Cooperate,
+            },
+        ];
+        assert_eq!(grudger.next_move(&turn(&forgiven)), PlayStatus::Betrail);
+        grudger.start();
+        assert_eq!(grudger.next_move(&turn(&[])), PlayStatus::Cooperate);
+    }
+
+    #[test]
+    fn play_a_party() {
+        let mut tit_for_tat = strategy::by_name("tit-for-tat").unwrap();
+        let mut betray = strategy::by_name("betray").unwrap();
+        let played = tournament(3).play(tit_for_tat.as_mut(), betray.as_mut());
+        let plays: Vec<_> = played.rounds.iter().map(|round| &round.play).collect();
+        assert_eq!(
+            plays,
+            [
+                &PlayStatus::Cooperate,
+                &PlayStatus::Betrail,
+                &PlayStatus::Betrail
+            ]
+        );
+        assert!(played
+            .rounds
+            .iter()
+            .all(|round| round.other == PlayStatus::Betrail));
+        // sucker then punished twice, at a bet of 10
+        assert_eq!(played.payoffs, (-40.0, 0.0));
+    }
+
+    #[test]
+    fn round_robin_standings() {
+        let mut strategies: Vec<Box<dyn Strategy>> = ["cooperate", "betray", "tit-for-tat"]
+            .iter()
+            .map(|name| strategy::by_name(name).unwrap())
+            .collect();
+        let tournament = Tournament {
+            repeat: 2,
+            ..tournament(4)
+        };
+        let standings = tournament.run(&mut strategies);
+
+        // betraying pays off in short parties
+        let names: Vec<_> = standings.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["betray", "tit-for-tat", "cooperate"]);
+        let betray = &standings[0];
+        assert_eq!((betray.matches, betray.wins, betray.losses), (4, 4, 0));
+        assert_eq!(betray.payoff, 2.0 * (80.0 - 10.0));
+        assert_eq!(betray.cooperation(), 0.0);
+        let tit_for_tat = &standings[1];
+        assert_eq!((tit_for_tat.draws, tit_for_tat.losses), (2, 2));
+        assert_eq!(tit_for_tat.payoff, 2.0 * (20.0 - 50.0));
+        assert_eq!(tit_for_tat.cooperation(), 5.0 / 8.0);
+        let cooperate = &standings[2];
+        assert_eq!(cooperate.payoff, 2.0 * (20.0 - 80.0));
+        assert_eq!(cooperate.cooperation(), 1.0);
+    }
+}
@@ -1,250 +1,146 @@
 slint::include_modules!();
-use settings::{Log, PlayStatus, Protocol, Settings, Status};
-use std::io::{Read, Write};
-use std::net::{Shutdown, TcpStream};
-use std::sync::RwLock;
-use std::sync::{Arc, Mutex, MutexGuard};
+use client::{Client, ClientError, ClientState, Event, Screen};
+use settings::{Log, PlayStatus, Settings};
+use std::sync::mpsc::Receiver;
 use std::thread;
-use std::time::{Duration, Instant};
 
 pub struct Controller {
-    pub settings: Settings,
-    pub interface: Arc<RwLock<AppWindow>>,
-    pub tcp: TcpStream,
-    pub protocol: Arc<Mutex<Protocol>>,
+    pub interface: AppWindow,
+    pub client: Client,
+    events: Receiver<Event>,
 }
-const BUFFER_SIZE: usize = 1024;
-type BufferSize = [u8; BUFFER_SIZE];
 
 pub struct Interface {}
 
 impl Interface {
-    fn set_default_input(ui: &AppWindow) {
-        ui.set_number_bet(10);
-        ui.set_number_round(5);
+    fn set_default_input(ui: &AppWindow, state: &ClientState) {
+        ui.set_number_bet(state.bet as i32);
+        ui.set_number_round(state.rounds as i32);
     }
 
-    fn reset_interface(ui: &AppWindow) {
-        ui.set_menu_visible(false);
-        ui.set_game_visible(false);
-        ui.set_search_visible(false);
-        ui.set_create_visible(false);
-        ui.set_wait_visible(false);
-    }
-
-    fn go_create_game_ui(ui: &AppWindow) {
-        Interface::reset_interface(&ui);
-        Interface::set_default_input(&ui);
-        ui.set_create_visible(true);
-    }
-    fn go_end_game(ui: &AppWindow, text: &str) {
-        Interface::reset_interface(&ui);
-        ui.set_end_game_visible(true);
-        ui.set_status_game(text.try_into().unwrap());
-    }
-    fn go_in_game(ui: &AppWindow, party_id: u32, money: f64, round: u32, total_round: u32) {
-        Interface::reset_interface(&ui);
-        ui.set_game_visible(true);
-        ui.set_party_id(party_id as i32);
-        ui.set_player1_money(money as f32);
-        ui.set_total_rounds(total_round as i32);
-        ui.set_party_rounds(round as i32);
-    }
-    fn go_waiting_player(ui: &AppWindow) {
-        Interface::reset_interface(&ui);
-        ui.set_wait_visible(true);
+    /// Shows the view of the current screen, with the party of `state`.
+    fn show(ui: &AppWindow, state: &ClientState) {
+        let screen = state.screen();
+        ui.set_menu_visible(*screen == Screen::Menu);
+        ui.set_create_visible(*screen == Screen::CreateGame);
+        ui.set_search_visible(*screen == Screen::Lobby);
+        ui.set_wait_visible(*screen == Screen::Waiting);
+        ui.set_game_visible(*screen == Screen::Game);
+        ui.set_end_game_visible(matches!(screen, Screen::End(_)));
+        ui.set_server_message(state.server_message().into());
+
+        let protocol = state.protocol();
+        match screen {
+            Screen::Game => {
+                ui.set_party_id(protocol.party_id as i32);
+                ui.set_player1_money(protocol.player.money as f32);
+                ui.set_total_rounds(protocol.total_round as i32);
+                ui.set_party_rounds(protocol.round as i32);
+            }
+            Screen::End(end) => ui.set_status_game(end.text().into()),
+            _ => (),
+        }
     }
 }
 
-unsafe impl Send for AppWindow {}
-unsafe impl Sync for AppWindow {}
-
 impl Controller {
     pub fn new(settings: Settings) -> Self {
-        let tcp = TcpStream::connect(format!("{}:{}", settings.host, settings.port))
-            .expect("Connection failed.");
+        let (client, events) = Client::connect(&settings).expect("Connection failed.");
+        let ui = AppWindow::new().unwrap();
+        Interface::set_default_input(&ui, &client.state());
         Self {
-            settings: settings,
-            tcp: tcp,
-            protocol: Arc::new(Mutex::new(Protocol::default())),
-            interface: Arc::new(RwLock::new(AppWindow::new().unwrap())),
+            interface: ui,
+            client,
+            events,
         }
     }
 
-    pub fn run(&mut self) {
-        let ui = Arc::clone(&self.interface);
-        let ui_for_closure = Arc::clone(&self.interface);
-        let protocol = Arc::clone(&self.protocol);
-        let protocol_for_closure = Arc::clone(&self.protocol);
-        let protocol_for_closure_read = Arc::clone(&self.protocol);
-        let mut tcp_stream = self.tcp.try_clone().unwrap();
+    pub fn run(self) {
+        let ui = self.interface.as_weak();
+        let client = self.client.clone();
+        let events = self.events;
+        // The window lives on this thread: events only ask its event loop
+        // to refresh it.
         thread::spawn(move || {
-            {
-                let protocol_mut = protocol_for_closure.lock().unwrap();
-                let bytes: Vec<u8> = protocol_mut.to_bytes();
-                tcp_stream.write_all(&bytes).unwrap();
-                tcp_stream.flush().unwrap();
-            }
-
-            let mut buffer: BufferSize = [0; 1024];
-            loop {
-                match tcp_stream.read(&mut buffer) {
-                    Ok(bytes_read) => {
-                        if bytes_read == 0 {}
-                        Log::show("ERROR", "Connection closed by remote endpoint".to_string());
-                        let updated_protocol = Protocol::from_bytes(&buffer[..bytes_read]);
-                        let mut protocol_guard = protocol_for_closure_read.lock().unwrap();
-                        *protocol_guard = updated_protocol; // Update the content inside the Mutex
-                        Log::show("INFO", format!("Protocol : {:?}", protocol_guard));
-
-                        let protocol_c = protocol_guard.clone();
-                        match protocol_c.party_status {
-                            Status::Started => {
-                                let ui_arc = ui_for_closure.read().unwrap();
-                                let party_id = protocol_c.party_id;
-                                let money = protocol_c.player.money;
-                                let round = protocol_c.round;
-                                let total_round = protocol_c.total_round;
-                                Interface::go_in_game(&ui_arc, party_id, money, round, total_round);
-                            }
-                            Status::Win => {
-                                let text = "Win";
-                                let ui_arc = ui_for_closure.read().unwrap();
-                                Interface::go_end_game(&ui_arc, text);
-                            }
-                            Status::Lose => {
-                                let text = "Lose";
-                                let ui_arc = ui_for_closure.read().unwrap();
-                                Interface::go_end_game(&ui_arc, text);
-                            }
-                            Status::Equal => {
-                                let text = "Equal game";
-                                let ui_arc = ui_for_closure.read().unwrap();
-                                Interface::go_end_game(&ui_arc, text);
-                            }
-                            _ => (),
-                        }
-                    }
-                    Err(e) => {
-                        Log::show("ERROR", format!("Erreur read"));
-                        break;
-                    }
+            for event in events {
+                if let Event::PartyEnded { end, .. } = event {
+                    Log::info(format!("Party ended: {}", end.text()));
+                }
+                let client = client.clone();
+                let refreshed =
+                    ui.upgrade_in_event_loop(move |ui| Interface::show(&ui, &client.state()));
+                if refreshed.is_err() {
+                    break;
                 }
             }
         });
 
-        let tcp_stream = self.tcp.try_clone().unwrap();
-
-        Controller::init(ui, &tcp_stream, protocol)
+        Controller::attach_event_handlers(&self.interface, &self.client);
+        Interface::show(&self.interface, &self.client.state());
+        let _ = self.interface.run();
     }
 
-    fn init(ui: Arc<RwLock<AppWindow>>, tcp_stream: &TcpStream, protocol: Arc<Mutex<Protocol>>) {
-        let ui_arc = ui.read().expect("Error reading intreface");
-        Controller::attach_event_handlers(&ui, &tcp_stream, protocol);
-        Interface::reset_interface(&ui_arc);
-        ui_arc.set_menu_visible(true);
-        let _ = ui_arc.run();
+    fn attach_event_handlers(ui: &AppWindow, client: &Client) {
+        Self::attach_event_game(ui, client);
+        Self::attach_create_game(ui, client);
+        Self::attach_party_play(ui, client, PlayStatus::Betrail);
+        Self::attach_party_play(ui, client, PlayStatus::Cooperate);
     }
 
-    fn attach_event_handlers(
-        ui: &Arc<RwLock<AppWindow>>,
-        tcp_stream: &TcpStream,
-        protocol: Arc<Mutex<Protocol>>,
-    ) {
-        Self::attach_event_game(&ui, &tcp_stream, &protocol);
-        Self::attach_create_game(&ui, &tcp_stream, &protocol);
-        Self::attach_party_betray(&ui, &tcp_stream, &protocol);
-        Self::attach_party_cooperat(&ui, &tcp_stream, &protocol);
+    /// Logs a refused action and shows the resulting screen.
+    fn done(ui: &AppWindow, client: &Client, result: Result<(), ClientError>) {
+        if let Err(e) = result {
+            Log::warn(e);
+        }
+        Interface::show(ui, &client.state());
     }
 
-    fn attach_event_game(
-        ui: &Arc<RwLock<AppWindow>>,
-        tcp_stream: &TcpStream,
-        protocol: &Arc<Mutex<Protocol>>,
-    ) {
-        let mut tcp_stream_: TcpStream = tcp_stream.try_clone().expect("Clone failed...");
-        let ui_cloned = ui.read().unwrap().clone_strong();
-        let protocol_cloned_ = Arc::clone(&protocol);
-
-        ui.read().unwrap().on_event_game(move |data| {
-            Log::show("INFO", data.to_string());
-            if data.trim() == "CREATE" {
-                Interface::go_create_game_ui(&ui_cloned);
+    fn attach_event_game(ui: &AppWindow, client: &Client) {
+        let ui_weak = ui.as_weak();
+        let client = client.clone();
+
+        ui.on_event_game(move |data| {
+            Log::info(data.to_string());
+            let result = if data.trim() == "CREATE" {
+                client
+                    .update(|state| {
+                        state.open_create();
+                        None
+                    })
+                    .map(|_| ())
+                    .map_err(ClientError::from)
             } else {
-                let mut protocol_cloned = protocol_cloned_.lock().unwrap();
-                protocol_cloned.party_status = Status::JoinParty;
-
-                let bytes = protocol_cloned.to_bytes();
-                tcp_stream_.write_all(&bytes).unwrap();
-                tcp_stream_.flush().unwrap();
-
-                Interface::go_waiting_player(&ui_cloned);
-            }
-        });
-    }
-
-    fn attach_create_game(
-        ui: &Arc<RwLock<AppWindow>>,
-        tcp_stream: &TcpStream,
-        protocol: &Arc<Mutex<Protocol>>,
-    ) {
-        let mut tcp_stream_: TcpStream = tcp_stream.try_clone().expect("Clone failed...");
-        let ui_cloned = ui.read().unwrap().clone_strong();
-        let protocol_cloned_ = Arc::clone(&protocol);
-
-        ui.read().unwrap().on_create_game(move || {
-            let total_round = ui_cloned.get_number_round();
-            let bet = ui_cloned.get_number_bet();
-            let mut protocol_cloned = protocol_cloned_.lock().unwrap();
-            protocol_cloned.bet = bet as u32;
-            protocol_cloned.total_round = total_round as u32;
-            protocol_cloned.party_status = Status::Created;
-
-            let bytes = protocol_cloned.to_bytes();
-            tcp_stream_.write_all(&bytes).unwrap();
-            tcp_stream_.flush().unwrap();
-
-            Interface::go_waiting_player(&ui_cloned);
+                client.join()
+            };
+            Controller::done(&ui_weak.unwrap(), &client, result);
         });
     }
 
-    fn attach_party_betray(
-        ui: &Arc<RwLock<AppWindow>>,
-        tcp_stream: &TcpStream,
-        protocol: &Arc<Mutex<Protocol>>,
-    ) {
-        let mut tcp_stream_: TcpStream = tcp_stream.try_clone().expect("Clone failed...");
-        let ui_cloned = ui.read().unwrap().clone_strong();
-        let protocol_cloned_ = Arc::clone(&protocol);
-        ui.read().unwrap().on_party_betray(move || {
-            let mut protocol_cloned = protocol_cloned_.lock().unwrap();
-            protocol_cloned.play = PlayStatus::Betrail;
+    fn attach_create_game(ui: &AppWindow, client: &Client) {
+        let ui_weak = ui.as_weak();
+        let client = client.clone();
 
-            let bytes = protocol_cloned.to_bytes();
-            tcp_stream_.write_all(&bytes).unwrap();
-            tcp_stream_.flush().unwrap();
-
-            Interface::go_waiting_player(&ui_cloned);
+        ui.on_create_game(move || {
+            let ui = ui_weak.unwrap();
+            let total_round = ui.get_number_round();
+            let bet = ui.get_number_bet();
+            let result = client.create(total_round as u32, bet as u32);
+            Controller::done(&ui, &client, result);
         });
     }
 
-    fn attach_party_cooperat(
-        ui: &Arc<RwLock<AppWindow>>,
-        tcp_stream: &TcpStream,
-        protocol: &Arc<Mutex<Protocol>>,
-    ) {
-        let mut tcp_stream_: TcpStream = tcp_stream.try_clone().expect("Clone failed...");
-        let ui_cloned = ui.read().unwrap().clone_strong();
-        let protocol_cloned_ = Arc::clone(&protocol);
-        ui.read().unwrap().on_party_cooperat(move || {
-            let mut protocol_cloned = protocol_cloned_.lock().unwrap();
-            protocol_cloned.play = PlayStatus::Cooperate;
-
-            let bytes = protocol_cloned.to_bytes();
-            tcp_stream_.write_all(&bytes).unwrap();
-            tcp_stream_.flush().unwrap();
-
-            Interface::go_waiting_player(&ui_cloned);
-        });
+    fn attach_party_play(ui: &AppWindow, client: &Client, play: PlayStatus) {
+        let ui_weak = ui.as_weak();
+        let client = client.clone();
+        let betray = play == PlayStatus::Betrail;
+        let on_play = move || {
+            let result = client.play(play.clone());
+            Controller::done(&ui_weak.unwrap(), &client, result);
+        };
+        if betray {
+            ui.on_party_betray(on_play);
+        } else {
+            ui.on_party_cooperat(on_play);
+        }
     }
 }
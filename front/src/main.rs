@@ -6,7 +6,7 @@ use controller::Controller;
 use settings::Settings;
 
 fn main() {
-    let settings = Settings::load("../settings/settings.json");
-    let mut crl = Controller::new(settings);
+    let settings = Settings::from_args("client");
+    let crl = Controller::new(settings);
     crl.run();
 }
@@ -0,0 +1,604 @@
+use crate::{Log, LogConfig, PlayStatus};
+use config::{Config, Environment, File, Map, Value, ValueKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Prefix of the environment variables overriding the settings file, e.g.
+/// `PRISONER_PORT=9002` or `PRISONER_LOG__LEVEL=DEBUG`.
+pub const ENV_PREFIX: &str = "PRISONER";
+/// Environment variable naming the settings file.
+pub const CONFIG_ENV: &str = "PRISONER_CONFIG";
+/// Environment variable naming the profile to apply.
+pub const PROFILE_ENV: &str = "PRISONER_PROFILE";
+/// Directory of the settings file under the XDG config directory.
+pub const CONFIG_DIR: &str = "prisoner-dilemma";
+pub const CONFIG_FILE: &str = "settings.json";
+/// Key of the settings file holding one section of overrides per profile.
+const PROFILES_KEY: &str = "profiles";
+/// Placeholders of `export_file`, written `{party_id}`.
+pub const EXPORT_PLACEHOLDERS: [&str; 6] =
+    ["date", "time", "party_id", "player1", "player2", "ext"];
+
+pub const USAGE: &str = "Options:
+  --config <file>       settings file, defaults to $PRISONER_CONFIG, then
+                        $XDG_CONFIG_HOME/prisoner-dilemma/settings.json
+  --profile <name>      profile section of the settings file to apply
+  --set <key>=<value>   override a setting, e.g. --set port=9002 or --set log.level=DEBUG
+  --print-config        print the resulting settings as JSON and exit
+  --help                show this help";
+
+/// Money won or lost in a round, as a multiple of the party bet.
+///
+/// It must stay a prisoner's dilemma: `temptation > reward > punishment > sucker`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PayoffMatrix {
+    /// Both players cooperate.
+    pub reward: f64,
+    /// The player cooperates, the other betrays.
+    pub sucker: f64,
+    /// The player betrays, the other cooperates.
+    pub temptation: f64,
+    /// Both players betray.
+    pub punishment: f64,
+}
+
+impl Default for PayoffMatrix {
+    fn default() -> Self {
+        Self {
+            reward: 0.5,
+            sucker: -2.0,
+            temptation: 2.0,
+            punishment: -1.0,
+        }
+    }
+}
+
+impl PayoffMatrix {
+    /// Money won or lost by a player for one round, given both moves.
+    pub fn payoff(&self, play: &PlayStatus, other: &PlayStatus, bet: u32) -> f64 {
+        let factor = match (play, other) {
+            (PlayStatus::Cooperate, PlayStatus::Cooperate) => self.reward,
+            (PlayStatus::Cooperate, PlayStatus::Betrail) => self.sucker,
+            (PlayStatus::Betrail, PlayStatus::Cooperate) => self.temptation,
+            (PlayStatus::Betrail, PlayStatus::Betrail) => self.punishment,
+            _ => 0.0,
+        };
+        factor * bet as f64
+    }
+
+    /// Money actually paid for a round of a party: whole units, fractions
+    /// dropped, so that cooperating at the default matrix pays `bet / 2`.
+    pub fn money(&self, play: &PlayStatus, other: &PlayStatus, bet: u32) -> f64 {
+        self.payoff(play, other, bet).trunc()
+    }
+}
+
+/// File format of exported parties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ExportFormat {
+    #[default]
+    Xlsx,
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl ExportFormat {
+    /// Also the extension of exported files.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl TryFrom<String> for ExportFormat {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl From<ExportFormat> for String {
+    fn from(format: ExportFormat) -> Self {
+        format.as_str().to_string()
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        [ExportFormat::Xlsx, ExportFormat::Csv, ExportFormat::Jsonl]
+            .into_iter()
+            .find(|format| format.as_str().eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("unknown export format {}, use xlsx, csv or jsonl", value))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub host: String,
+    pub port: u16,
+    /// Bet proposed by clients when creating a party.
+    pub default_bet: u32,
+    /// Number of rounds proposed by clients when creating a party.
+    pub default_rounds: u32,
+    pub payoff: PayoffMatrix,
+    /// Seconds a created party may wait for a second player, 0 keeps it forever.
+    pub party_ttl: u64,
+    /// Seconds a single write to a client may take before it is dropped.
+    pub write_timeout: u64,
+    /// Directory party results are exported to, created if missing.
+    pub export_dir: String,
+    /// Name of an exported party under `export_dir`, with `{date}`, `{time}`
+    /// (UTC), `{party_id}`, `{player1}`, `{player2}` and `{ext}` replaced.
+    pub export_file: String,
+    pub export_format: ExportFormat,
+    /// JSON lines file finished parties are appended to, empty to disable.
+    pub storage_path: String,
+    /// Number of finished parties kept in memory by the server.
+    pub archive_size: usize,
+    /// Read admin commands from the server standard input.
+    pub admin_console: bool,
+    /// Unix socket serving admin commands, empty to disable.
+    pub admin_socket: String,
+    /// Port of the read-only HTTP/JSON API, 0 to disable.
+    pub http_port: u16,
+    /// Strategy taking the free seat of parties nobody joined within
+    /// `bot_wait` seconds: a built-in name or a strategy file, empty to
+    /// disable.
+    pub bot_seat: String,
+    pub bot_wait: u64,
+    /// Milliseconds a scripted strategy may take to choose a move.
+    pub move_timeout: u64,
+    /// Fuel (about one unit per instruction) a WebAssembly strategy may burn
+    /// to choose a move.
+    pub move_fuel: u64,
+    pub log: LogConfig,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            host: String::from("127.0.0.1"),
+            port: 9001,
+            default_bet: 10,
+            default_rounds: 5,
+            payoff: PayoffMatrix::default(),
+            party_ttl: 300,
+            write_timeout: 5,
+            export_dir: String::from(".."),
+            export_file: String::from("game_{party_id}.{ext}"),
+            export_format: ExportFormat::Xlsx,
+            storage_path: String::new(),
+            archive_size: 100,
+            admin_console: true,
+            admin_socket: String::new(),
+            http_port: 0,
+            bot_seat: String::new(),
+            bot_wait: 30,
+            move_timeout: 100,
+            move_fuel: 1_000_000,
+            log: LogConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    /// The command line could not be parsed.
+    Arguments(String),
+    /// A source could not be read or a value has the wrong type.
+    Load(String),
+    /// Values were read but do not make sense together.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Arguments(message) => write!(f, "invalid arguments: {}", message),
+            SettingsError::Load(message) => write!(f, "cannot load settings: {}", message),
+            SettingsError::Invalid(problems) => {
+                write!(f, "invalid settings:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<config::ConfigError> for SettingsError {
+    fn from(error: config::ConfigError) -> Self {
+        SettingsError::Load(error.to_string())
+    }
+}
+
+/// Options shared by the binaries reading a settings file.
+#[derive(Debug, Default, PartialEq)]
+pub struct CommandLine {
+    pub config: Option<PathBuf>,
+    pub profile: Option<String>,
+    /// `key=value` pairs given with `--set`, applied over the file and the
+    /// environment.
+    pub overrides: Vec<(String, String)>,
+    pub print_config: bool,
+    pub help: bool,
+}
+
+impl CommandLine {
+    /// Parses the arguments following the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, SettingsError> {
+        let (command_line, rest) = CommandLine::parse_known(args)?;
+        match rest.first() {
+            Some(arg) => Err(SettingsError::Arguments(format!("unknown option {}", arg))),
+            None => Ok(command_line),
+        }
+    }
+
+    /// Like `parse`, returning the arguments which are not settings options
+    /// in order, for binaries taking arguments of their own.
+    pub fn parse_known(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<(Self, Vec<String>), SettingsError> {
+        let mut command_line = CommandLine::default();
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--print-config" => command_line.print_config = true,
+                "--help" | "-h" => command_line.help = true,
+                "--config" => {
+                    command_line.config = Some(PathBuf::from(CommandLine::value(&arg, &mut args)?))
+                }
+                "--profile" => command_line.profile = Some(CommandLine::value(&arg, &mut args)?),
+                "--set" => {
+                    let pair = CommandLine::value(&arg, &mut args)?;
+                    command_line.overrides.push(CommandLine::pair(&pair)?);
+                }
+                _ => match arg.strip_prefix("--set=") {
+                    Some(pair) => command_line.overrides.push(CommandLine::pair(pair)?),
+                    None => rest.push(arg),
+                },
+            }
+        }
+        Ok((command_line, rest))
+    }
+
+    fn value(
+        option: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<String, SettingsError> {
+        args.next()
+            .ok_or_else(|| SettingsError::Arguments(format!("{} expects a value", option)))
+    }
+
+    fn pair(pair: &str) -> Result<(String, String), SettingsError> {
+        match pair.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => Err(SettingsError::Arguments(format!(
+                "--set expects key=value, got {}",
+                pair
+            ))),
+        }
+    }
+}
+
+/// Where settings are read from, lowest priority first: the settings file,
+/// its profile section, the environment, then command-line overrides.
+#[derive(Debug, Default)]
+pub struct LoadOptions {
+    /// Settings file, built-in defaults only when `None`.
+    pub file: Option<PathBuf>,
+    /// Section of `profiles` in the file applied over its top-level keys.
+    pub profile: Option<String>,
+    /// `PRISONER_*` variables, the process environment when `None`.
+    pub environment: Option<HashMap<String, String>>,
+    pub overrides: Vec<(String, String)>,
+}
+
+impl LoadOptions {
+    /// Finds the settings file and profile of a binary: `--config`, then
+    /// `PRISONER_CONFIG`, then the XDG config directory if the file exists
+    /// there, otherwise built-in defaults only. The profile is `--profile`,
+    /// then `PRISONER_PROFILE`, then `default_profile`.
+    pub fn discover(
+        command_line: CommandLine,
+        default_profile: &str,
+        environment: &HashMap<String, String>,
+    ) -> Self {
+        let variable = |name: &str| environment.get(name).filter(|value| !value.is_empty());
+        let file = command_line
+            .config
+            .or_else(|| variable(CONFIG_ENV).map(PathBuf::from))
+            .or_else(|| {
+                let config_home = match variable("XDG_CONFIG_HOME") {
+                    Some(dir) => PathBuf::from(dir),
+                    None => Path::new(variable("HOME")?).join(".config"),
+                };
+                let file = config_home.join(CONFIG_DIR).join(CONFIG_FILE);
+                file.is_file().then_some(file)
+            });
+        let profile = command_line
+            .profile
+            .or_else(|| variable(PROFILE_ENV).cloned())
+            .unwrap_or_else(|| default_profile.to_string());
+
+        let settings_environment = environment
+            .iter()
+            .filter(|(name, _)| *name != CONFIG_ENV && *name != PROFILE_ENV)
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        Self {
+            file,
+            profile: Some(profile),
+            environment: Some(settings_environment),
+            overrides: command_line.overrides,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads a settings file, then the `PRISONER_*` environment variables.
+    pub fn load(file_name: &str) -> Result<Self, SettingsError> {
+        Settings::load_with(LoadOptions {
+            file: Some(PathBuf::from(file_name)),
+            ..Default::default()
+        })
+    }
+
+    /// Missing keys keep their default value.
+    pub fn load_with(options: LoadOptions) -> Result<Self, SettingsError> {
+        let mut builder = Config::builder();
+        if let Some(file) = &options.file {
+            if !file.is_file() {
+                return Err(SettingsError::Load(format!(
+                    "settings file {} not found",
+                    file.display()
+                )));
+            }
+            let file = Config::builder()
+                .add_source(File::from(file.as_path()))
+                .build()?;
+            let profile = match &options.profile {
+                Some(profile) => Some(Settings::profile(&file, profile)?),
+                None => None,
+            };
+            builder = builder.add_source(file);
+            if let Some(profile) = profile {
+                builder = builder.add_source(profile);
+            }
+        }
+        let environment = options.environment.unwrap_or_else(|| {
+            std::env::vars()
+                .filter(|(name, _)| name != CONFIG_ENV && name != PROFILE_ENV)
+                .collect()
+        });
+        builder = builder.add_source(
+            Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator("_")
+                .separator("__")
+                .source(Some(environment)),
+        );
+        for (key, value) in options.overrides.iter() {
+            builder = builder.set_override(key.as_str(), value.as_str())?;
+        }
+
+        let mut values: Map<String, Value> = builder.build()?.try_deserialize()?;
+        values.remove(PROFILES_KEY);
+        let settings = Settings::deserialize(Value::new(None, values))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// The profile section of a settings file as a source of its own. A
+    /// profile missing from the file changes nothing.
+    fn profile(file: &Config, profile: &str) -> Result<Config, SettingsError> {
+        let mut builder = Config::builder();
+        let section = file
+            .get_table(PROFILES_KEY)
+            .ok()
+            .and_then(|mut profiles| profiles.remove(profile));
+        if let Some(section) = section {
+            let mut keys = Vec::new();
+            Settings::flatten(String::new(), section, &mut keys);
+            for (key, value) in keys {
+                builder = builder.set_override(key, value)?;
+            }
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Dotted keys of the leaves of a table, so that a profile only replaces
+    /// the nested keys it sets.
+    fn flatten(prefix: String, value: Value, keys: &mut Vec<(String, Value)>) {
+        match value.kind {
+            ValueKind::Table(table) => {
+                for (key, value) in table {
+                    let key = match prefix.is_empty() {
+                        true => key,
+                        false => format!("{}.{}", prefix, key),
+                    };
+                    Settings::flatten(key, value, keys);
+                }
+            }
+            _ => keys.push((prefix, value)),
+        }
+    }
+
+    /// Loads the settings of a binary running with `default_profile`, from
+    /// its command line and environment, then applies their `log` section.
+    /// Handles `--help` and `--print-config`, and exits on errors.
+    pub fn from_args(default_profile: &str) -> Self {
+        let command_line = CommandLine::parse(std::env::args().skip(1));
+        Settings::from_command_line(command_line, default_profile, "")
+    }
+
+    /// Like `from_args` for binaries taking arguments of their own, which are
+    /// returned in order. `usage` describes them above the settings options.
+    pub fn from_args_with(default_profile: &str, usage: &str) -> (Self, Vec<String>) {
+        let mut rest = Vec::new();
+        let command_line =
+            CommandLine::parse_known(std::env::args().skip(1)).map(|(command_line, known)| {
+                rest = known;
+                command_line
+            });
+        let settings = Settings::from_command_line(command_line, default_profile, usage);
+        (settings, rest)
+    }
+
+    fn from_command_line(
+        command_line: Result<CommandLine, SettingsError>,
+        default_profile: &str,
+        usage: &str,
+    ) -> Self {
+        let usage = match usage {
+            "" => USAGE.to_string(),
+            usage => format!("{}\n\n{}", usage, USAGE),
+        };
+        let settings = command_line.and_then(|command_line| {
+            if command_line.help {
+                println!("{}", usage);
+                std::process::exit(0);
+            }
+            let print_config = command_line.print_config;
+            let environment = std::env::vars().collect();
+            let options = LoadOptions::discover(command_line, default_profile, &environment);
+            let source = match &options.file {
+                Some(file) => file.display().to_string(),
+                None => String::from("built-in defaults"),
+            };
+            let profile = options.profile.clone().unwrap_or_default();
+            let settings = Settings::load_with(options)?;
+            if print_config {
+                println!("{}", settings.to_json());
+                std::process::exit(0);
+            }
+            if let Err(e) = Log::init(&settings.log) {
+                Log::error(format!("opening log file {}: {}", settings.log.file, e));
+            }
+            Log::info(format!("Settings from {}, profile {}", source, profile));
+            Ok(settings)
+        });
+        match settings {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("{}", e);
+                eprintln!("{}", usage);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    /// Lists every problem found, not only the first one.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let mut problems = Vec::new();
+        if self.host.trim().is_empty() {
+            problems.push(String::from("host must not be empty"));
+        }
+        if self.default_bet == 0 {
+            problems.push(String::from("default_bet must be greater than 0"));
+        }
+        if self.default_rounds == 0 {
+            problems.push(String::from("default_rounds must be greater than 0"));
+        }
+        let payoff = &self.payoff;
+        if !(payoff.temptation > payoff.reward
+            && payoff.reward > payoff.punishment
+            && payoff.punishment > payoff.sucker)
+        {
+            problems.push(format!(
+                "payoff must satisfy temptation > reward > punishment > sucker, got {} > {} > {} > {}",
+                payoff.temptation, payoff.reward, payoff.punishment, payoff.sucker
+            ));
+        }
+        if self.write_timeout == 0 {
+            problems.push(String::from("write_timeout must be greater than 0"));
+        }
+        if self.move_timeout == 0 {
+            problems.push(String::from("move_timeout must be greater than 0"));
+        }
+        if self.move_fuel == 0 {
+            problems.push(String::from("move_fuel must be greater than 0"));
+        }
+        if self.export_dir.trim().is_empty() {
+            problems.push(String::from("export_dir must not be empty"));
+        }
+        if let Err(problem) = Settings::check_template(&self.export_file) {
+            problems.push(format!("export_file {}", problem));
+        }
+        if let Some(extension) = Settings::extension(&self.export_file) {
+            if extension != "{ext}" && !extension.eq_ignore_ascii_case(self.export_format.as_str())
+            {
+                problems.push(format!(
+                    "export_file extension .{} contradicts export_format {}, use .{{ext}}",
+                    extension,
+                    self.export_format.as_str()
+                ));
+            }
+        }
+        if self.http_port != 0 && self.http_port == self.port {
+            problems.push(format!(
+                "http_port must differ from port, both are {}",
+                self.port
+            ));
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(SettingsError::Invalid(problems)),
+        }
+    }
+
+    fn check_template(template: &str) -> Result<(), String> {
+        if template.trim().is_empty() {
+            return Err(String::from("must not be empty"));
+        }
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(length) = rest[start..].find('}') else {
+                return Err(format!("has an unclosed placeholder in {}", template));
+            };
+            let name = &rest[start + 1..start + length];
+            if !EXPORT_PLACEHOLDERS.contains(&name) {
+                return Err(format!(
+                    "has an unknown placeholder {{{}}}, use one of {{{}}}",
+                    name,
+                    EXPORT_PLACEHOLDERS.join("}, {")
+                ));
+            }
+            rest = &rest[start + length + 1..];
+        }
+        // Each party needs a file of its own
+        if !template.contains("{party_id}") {
+            return Err(format!("must contain {{party_id}}, got {}", template));
+        }
+        Ok(())
+    }
+
+    /// Extension of the file name a template ends with, if any.
+    fn extension(template: &str) -> Option<&str> {
+        let name = template.rsplit('/').next().unwrap_or(template);
+        name.rsplit_once('.')
+            .map(|(_, extension)| extension)
+            .filter(|extension| !extension.is_empty())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
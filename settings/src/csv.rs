@@ -0,0 +1,10 @@
+//! CSV written by the exports of the server and the engine binaries.
+
+/// A CSV field, quoted when it holds a comma, a quote or a line break.
+pub fn quoted(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -1,13 +1,23 @@
-use config::Config;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
 
-#[derive(Debug, Clone)]
+mod configuration;
+pub mod csv;
+mod logging;
+pub use configuration::{
+    CommandLine, ExportFormat, LoadOptions, PayoffMatrix, Settings, SettingsError, CONFIG_DIR,
+    CONFIG_ENV, CONFIG_FILE, ENV_PREFIX, EXPORT_PLACEHOLDERS, PROFILE_ENV, USAGE,
+};
+pub use logging::{Entry, Level, Log, LogConfig, LogFormat};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub id: u32,
     pub money: f64,
 }
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
 pub enum Status {
+    #[default]
     Init,
     Created,
     WaitingPlayer,
@@ -17,15 +27,85 @@ pub enum Status {
     Win,
     Lose,
     Equal,
+    Shutdown,
+    Kicked,
+    Broadcast,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
 pub enum PlayStatus {
     Betrail,
     Cooperate,
+    #[default]
     Stanby,
 }
 
+impl From<&Status> for u8 {
+    fn from(status: &Status) -> u8 {
+        match status {
+            Status::Init => 0,
+            Status::Created => 1,
+            Status::WaitingPlayer => 2,
+            Status::Started => 3,
+            Status::Finished => 4,
+            Status::JoinParty => 5,
+            Status::Win => 6,
+            Status::Lose => 7,
+            Status::Equal => 8,
+            Status::Shutdown => 9,
+            Status::Kicked => 10,
+            Status::Broadcast => 11,
+        }
+    }
+}
+
+/// Reads a status byte of a frame, returning unknown bytes as the error.
+impl TryFrom<u8> for Status {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Status, u8> {
+        match byte {
+            0 => Ok(Status::Init),
+            1 => Ok(Status::Created),
+            2 => Ok(Status::WaitingPlayer),
+            3 => Ok(Status::Started),
+            4 => Ok(Status::Finished),
+            5 => Ok(Status::JoinParty),
+            6 => Ok(Status::Win),
+            7 => Ok(Status::Lose),
+            8 => Ok(Status::Equal),
+            9 => Ok(Status::Shutdown),
+            10 => Ok(Status::Kicked),
+            11 => Ok(Status::Broadcast),
+            _ => Err(byte),
+        }
+    }
+}
+
+impl From<&PlayStatus> for u8 {
+    fn from(play: &PlayStatus) -> u8 {
+        match play {
+            PlayStatus::Betrail => 0,
+            PlayStatus::Cooperate => 1,
+            PlayStatus::Stanby => 2,
+        }
+    }
+}
+
+/// Reads a play byte of a frame, returning unknown bytes as the error.
+impl TryFrom<u8> for PlayStatus {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<PlayStatus, u8> {
+        match byte {
+            0 => Ok(PlayStatus::Betrail),
+            1 => Ok(PlayStatus::Cooperate),
+            2 => Ok(PlayStatus::Stanby),
+            _ => Err(byte),
+        }
+    }
+}
+
 impl PlayStatus {
     // Custom method to convert enum variant to string
     pub fn to_string(&self) -> &'static str {
@@ -37,13 +117,22 @@ impl PlayStatus {
     }
 }
 
-#[derive(Debug, Clone)]
+/// One side of a played round: the player, its move and its money after the round.
+pub type RoundPlay = (Player, PlayStatus, u32);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PartyRound {
-    pub round_played: Vec<((Player, PlayStatus, u32), (Player, PlayStatus, u32))>,
+    pub round_played: Vec<(RoundPlay, RoundPlay)>,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Party {
+    /// Drawn at random by each server run, so only unique together with
+    /// `created`.
     pub id: u32,
+    /// Creation time, in seconds since the Unix epoch. 0 for parties stored
+    /// before it was recorded.
+    #[serde(default)]
+    pub created: u64,
     pub total_round: u32,
     pub round: u32,
     pub status: Status,
@@ -54,13 +143,13 @@ pub struct Party {
     pub looser: Option<Player>,
     pub party_round: PartyRound,
 }
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Game {
     pub parties: Vec<Party>,
     pub players: Vec<Player>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Protocol {
     pub player: Player,
     pub party_status: Status,
@@ -69,33 +158,15 @@ pub struct Protocol {
     pub bet: u32,
     pub party_id: u32,
     pub play: PlayStatus,
+    /// Free text sent by the server, e.g. an operator broadcast.
+    pub message: String,
 }
 
 impl Default for Player {
     fn default() -> Self {
         Self {
             id: 0,
-            money: 100.0,
-        }
-    }
-}
-
-impl Default for Status {
-    fn default() -> Self {
-        Status::Init
-    }
-}
-
-impl Default for PlayStatus {
-    fn default() -> Self {
-        PlayStatus::Stanby
-    }
-}
-
-impl Default for PartyRound {
-    fn default() -> Self {
-        Self {
-            round_played: Vec::new(),
+            money: Player::STARTING_MONEY,
         }
     }
 }
@@ -104,6 +175,7 @@ impl Default for Party {
     fn default() -> Self {
         Self {
             id: 0,
+            created: 0,
             total_round: 0,
             round: 1,
             status: Status::default(),
@@ -123,30 +195,10 @@ impl Game {
     }
 }
 
-impl Default for Game {
-    fn default() -> Self {
-        Self {
-            parties: Vec::new(),
-            players: Vec::new(),
-        }
-    }
-}
-
-impl Default for Protocol {
-    fn default() -> Self {
-        Self {
-            player: Player::default(),
-            party_status: Status::default(),
-            total_round: 0,
-            round: 0,
-            bet: 0,
-            party_id: 0,
-            play: PlayStatus::default(),
-        }
-    }
-}
-
 impl Player {
+    /// Balance of every player joining a party.
+    pub const STARTING_MONEY: f64 = 100.0;
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend(&self.id.to_be_bytes());
@@ -167,63 +219,89 @@ impl Player {
 }
 
 impl Protocol {
+    /// Size in bytes of the fixed part of a frame. It ends with the length
+    /// of the text message that follows it on the wire.
+    pub const HEADER_SIZE: usize = 32;
+
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(30); // Adjust the capacity based on your exact byte size
+        // Longer messages are cut, on a char boundary to stay valid UTF-8
+        let mut size = self.message.len().min(u16::MAX as usize);
+        while !self.message.is_char_boundary(size) {
+            size -= 1;
+        }
+        let message = &self.message.as_bytes()[..size];
+        let mut bytes = Vec::with_capacity(Self::HEADER_SIZE + message.len());
 
         bytes.extend_from_slice(&self.player.to_bytes());
 
-        bytes.push(match self.party_status {
-            Status::Init => 0,
-            Status::Created => 1,
-            Status::WaitingPlayer => 2,
-            Status::Started => 3,
-            Status::Finished => 4,
-            Status::JoinParty => 5,
-            Status::Win => 6,
-            Status::Lose => 7,
-            Status::Equal => 8,
-        });
+        bytes.push(u8::from(&self.party_status));
 
         bytes.extend_from_slice(&self.total_round.to_be_bytes());
         bytes.extend_from_slice(&self.round.to_be_bytes());
         bytes.extend_from_slice(&self.bet.to_be_bytes());
         bytes.extend_from_slice(&self.party_id.to_be_bytes());
 
-        bytes.push(match self.play {
-            PlayStatus::Betrail => 0,
-            PlayStatus::Cooperate => 1,
-            PlayStatus::Stanby => 2,
-        });
+        bytes.push(u8::from(&self.play));
+
+        bytes.extend_from_slice(&(message.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(message);
 
         bytes
     }
+
+    /// Length of the text message following a frame header.
+    pub fn message_size(header: &[u8]) -> usize {
+        u16::from_be_bytes([header[30], header[31]]) as usize
+    }
+
+    /// Reads one whole frame from a blocking stream.
+    pub fn read_from(reader: &mut impl Read) -> std::io::Result<Protocol> {
+        let mut bytes = vec![0; Self::HEADER_SIZE];
+        reader.read_exact(&mut bytes)?;
+        bytes.resize(Self::HEADER_SIZE + Self::message_size(&bytes), 0);
+        reader.read_exact(&mut bytes[Self::HEADER_SIZE..])?;
+        Ok(Self::from_bytes(&bytes))
+    }
+    /// Like `from_bytes`, but rejects frames `from_bytes` would have to
+    /// guess: unknown status or play bytes and messages which are not UTF-8.
+    pub fn decode(bytes: &[u8]) -> Result<Protocol, String> {
+        if bytes.len() < Self::HEADER_SIZE {
+            return Err(format!("frame of {} bytes is too short", bytes.len()));
+        }
+        if let Err(byte) = Status::try_from(bytes[12]) {
+            return Err(format!("unknown status byte {}", byte));
+        }
+        if let Err(byte) = PlayStatus::try_from(bytes[29]) {
+            return Err(format!("unknown play byte {}", byte));
+        }
+        let size = Self::message_size(bytes);
+        match bytes.get(Self::HEADER_SIZE..Self::HEADER_SIZE + size) {
+            Some(message) if std::str::from_utf8(message).is_ok() => Ok(Self::from_bytes(bytes)),
+            Some(_) => Err(String::from("message is not valid UTF-8")),
+            None => Err(format!("message of {} bytes is truncated", size)),
+        }
+    }
     pub fn from_bytes(bytes: &[u8]) -> Protocol {
         let player_bytes: &[u8; 12] = bytes.get(..12).unwrap().try_into().ok().unwrap();
         let player: Player = Player::from_bytes(player_bytes).unwrap();
 
-        let party_status = match bytes[12] {
-            0 => Status::Init,
-            1 => Status::Created,
-            2 => Status::WaitingPlayer,
-            3 => Status::Started,
-            4 => Status::Finished,
-            5 => Status::JoinParty,
-            6 => Status::Win,
-            7 => Status::Lose,
-            8 => Status::Equal,
-            _ => Status::Init, // Invalid status byte
-        };
+        // Invalid status bytes read as Init
+        let party_status = Status::try_from(bytes[12]).unwrap_or_default();
 
         let total_round = u32::from_be_bytes(bytes[13..17].try_into().unwrap());
         let round = u32::from_be_bytes(bytes[17..21].try_into().unwrap());
         let bet = u32::from_be_bytes(bytes[21..25].try_into().unwrap());
         let party_id = u32::from_be_bytes(bytes[25..29].try_into().unwrap());
 
-        let play = match bytes[29] {
-            0 => PlayStatus::Betrail,
-            1 => PlayStatus::Cooperate,
-            2 => PlayStatus::Stanby,
-            _ => PlayStatus::Stanby, // Invalid play status byte
+        // Invalid play bytes read as Stanby
+        let play = PlayStatus::try_from(bytes[29]).unwrap_or_default();
+
+        let message = match bytes.get(Self::HEADER_SIZE..) {
+            Some(message) => {
+                let size = Self::message_size(bytes).min(message.len());
+                String::from_utf8_lossy(&message[..size]).to_string()
+            }
+            None => String::new(),
         };
 
         Protocol {
@@ -234,56 +312,7 @@ impl Protocol {
             bet,
             party_id,
             play,
-        }
-    }
-}
-
-pub struct Settings {
-    pub host: String,
-    pub port: String,
-}
-
-pub struct Log;
-
-impl Log {
-    pub fn show(key: &str, value: String) {
-        match key {
-            "WARN" => println!("** [WARN] {} **", value),
-            "DEBUG" => println!("** [DEBUG] {} **", value),
-            "INFO" => println!("** [INFO] {} **", value),
-            "ERROR" => println!("** [ERROR] {} **", value),
-            _ => println!("{}", value),
-        }
-    }
-}
-
-impl Settings {
-    pub fn load(file_name: &str) -> Self {
-        let settings = Config::builder()
-            .add_source(config::File::with_name(file_name))
-            .build()
-            .unwrap();
-
-        let settings_map = settings
-            .try_deserialize::<HashMap<String, String>>()
-            .unwrap();
-
-        Self {
-            host: Self::get_configuration_value(&settings_map, "host"),
-            port: Self::get_configuration_value(&settings_map, "port"),
-        }
-    }
-
-    pub fn get_configuration_value(dict: &HashMap<String, String>, key: &str) -> String {
-        match dict.get(key) {
-            Some(value) => value.to_string(),
-            None => {
-                Log::show(
-                    "ERROR",
-                    format!("Key {} not found in the configuration file.", key),
-                );
-                String::new()
-            }
+            message,
         }
     }
 }
@@ -0,0 +1,380 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl TryFrom<String> for Level {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl From<Level> for String {
+    fn from(level: Level) -> Self {
+        level.as_str().to_string()
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Level::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("unknown log level {}", value))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl TryFrom<String> for LogFormat {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl From<LogFormat> for String {
+    fn from(format: LogFormat) -> Self {
+        match format {
+            LogFormat::Text => String::from("text"),
+            LogFormat::Json => String::from("json"),
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format {}", value)),
+        }
+    }
+}
+
+/// Where and how log entries are written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: Level,
+    pub format: LogFormat,
+    /// File entries are appended to instead of the standard output, empty
+    /// for the standard output.
+    pub file: String,
+    /// Size in bytes after which the file is rotated, 0 to never rotate.
+    pub max_size: u64,
+    /// Rotated files kept next to the log file, as `<file>.1` (newest) to
+    /// `<file>.<max_files>`.
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::Info,
+            format: LogFormat::Text,
+            file: String::new(),
+            max_size: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// One log entry under construction. Nothing is written until `message`.
+#[must_use]
+pub struct Entry {
+    level: Level,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl Entry {
+    pub fn party(self, party_id: u32) -> Self {
+        self.field("party_id", party_id)
+    }
+
+    pub fn player(self, player_id: u32) -> Self {
+        self.field("player_id", player_id)
+    }
+
+    pub fn field(mut self, key: &'static str, value: impl Into<Value>) -> Self {
+        self.fields.push((key, value.into()));
+        self
+    }
+
+    pub fn message(self, message: impl fmt::Display) {
+        if !Log::enabled(self.level) {
+            return;
+        }
+        let line = self.render(&message.to_string(), SystemTime::now(), Log::format());
+        Log::write(&line);
+    }
+
+    /// The entry as one line, without the trailing newline.
+    pub fn render(&self, message: &str, time: SystemTime, format: LogFormat) -> String {
+        let timestamp = Log::timestamp(time);
+        match format {
+            LogFormat::Text => {
+                let mut line = format!("{} {:<5} {}", timestamp, self.level, message);
+                for (key, value) in self.fields.iter() {
+                    line.push_str(&format!(" {}={}", key, value));
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut object = Map::new();
+                object.insert("timestamp".to_string(), json!(timestamp));
+                object.insert("level".to_string(), json!(self.level.as_str()));
+                object.insert("message".to_string(), json!(message));
+                for (key, value) in self.fields.iter() {
+                    object.insert(key.to_string(), value.clone());
+                }
+                Value::Object(object).to_string()
+            }
+        }
+    }
+}
+
+/// File sink rotated once it grows past `max_size`.
+struct FileSink {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl FileSink {
+    fn open(path: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.max_size > 0 && self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(self.rotated(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Lowest level written, as an index in `Level::ALL`.
+static LEVEL: AtomicU8 = AtomicU8::new(1);
+static FORMAT: AtomicU8 = AtomicU8::new(0);
+static SINK: Mutex<Option<FileSink>> = Mutex::new(None);
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Process-wide leveled logger.
+///
+/// Entries go to the standard output, or the standard error after
+/// `use_stderr`, until `init` configures a file sink.
+/// Party and player ids are attached as fields rather than spelled out in
+/// the message, so they can be filtered on in JSON output:
+///
+/// `Log::entry(Level::Info).party(id).message("Party expired")`
+pub struct Log;
+
+impl Log {
+    /// Applies a configuration. Entries keep going to the standard output
+    /// if the log file cannot be opened.
+    pub fn init(config: &LogConfig) -> io::Result<()> {
+        Log::set_level(config.level);
+        FORMAT.store(config.format as u8, Ordering::Relaxed);
+        let sink = match config.file.as_str() {
+            "" => None,
+            file => Some(FileSink::open(
+                Path::new(file),
+                config.max_size,
+                config.max_files,
+            )?),
+        };
+        *SINK.lock().unwrap_or_else(|e| e.into_inner()) = sink;
+        Ok(())
+    }
+
+    /// Sends entries not written to a file to the standard error, for
+    /// binaries printing their data to the standard output.
+    pub fn use_stderr(stderr: bool) {
+        STDERR.store(stderr, Ordering::Relaxed);
+    }
+
+    pub fn set_level(level: Level) {
+        LEVEL.store(level as u8, Ordering::Relaxed);
+    }
+
+    pub fn level() -> Level {
+        Level::ALL[LEVEL.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn format() -> LogFormat {
+        match FORMAT.load(Ordering::Relaxed) {
+            0 => LogFormat::Text,
+            _ => LogFormat::Json,
+        }
+    }
+
+    pub fn enabled(level: Level) -> bool {
+        level >= Log::level()
+    }
+
+    pub fn entry(level: Level) -> Entry {
+        Entry {
+            level,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn debug(message: impl fmt::Display) {
+        Log::entry(Level::Debug).message(message);
+    }
+
+    pub fn info(message: impl fmt::Display) {
+        Log::entry(Level::Info).message(message);
+    }
+
+    pub fn warn(message: impl fmt::Display) {
+        Log::entry(Level::Warn).message(message);
+    }
+
+    pub fn error(message: impl fmt::Display) {
+        Log::entry(Level::Error).message(message);
+    }
+
+    fn write(line: &str) {
+        let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+        match sink.as_mut() {
+            Some(file) => {
+                if let Err(e) = file.write_line(line) {
+                    eprintln!("{}", line);
+                    eprintln!("writing to log file {}: {}", file.path.display(), e);
+                }
+            }
+            None if STDERR.load(Ordering::Relaxed) => eprintln!("{}", line),
+            None => println!("{}", line),
+        }
+    }
+
+    /// RFC 3339 UTC timestamp with milliseconds.
+    pub fn timestamp(time: SystemTime) -> String {
+        let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = elapsed.as_secs();
+        let (year, month, day) = Log::civil_date((seconds / 86_400) as i64);
+        let time_of_day = seconds % 86_400;
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            time_of_day / 3600,
+            time_of_day / 60 % 60,
+            time_of_day % 60,
+            elapsed.subsec_millis()
+        )
+    }
+
+    /// Gregorian date of a day count since 1970-01-01.
+    fn civil_date(days: i64) -> (i64, u32, u32) {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+}
@@ -1,41 +1,98 @@
-use settings::{Log};
+use settings::{Level, Log, LogConfig, LogFormat};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Held by the tests writing through the process-wide logger, which run in
+/// parallel otherwise.
+static LOGGER: Mutex<()> = Mutex::new(());
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_log_show_warn() {
-        let message = String::from("This is a warning message");
-        Log::show("WARN", message.clone());
+    fn test_log_levels() {
+        let _logger = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+        Log::debug("This is a debug message");
+        Log::info("This is an info message");
+        Log::warn("This is a warning message");
+        Log::error("This is an error message");
         // You can check the output visually in the test output.
     }
 
     #[test]
-    fn test_log_show_debug() {
-        let message = String::from("This is a debug message");
-        Log::show("DEBUG", message.clone());
-        // You can check the output visually in the test output.
+    fn test_parse_level() {
+        assert_eq!("warn".parse::<Level>(), Ok(Level::Warn));
+        assert_eq!("ERROR".parse::<Level>(), Ok(Level::Error));
+        assert!("LOUD".parse::<Level>().is_err());
+        assert!(Level::Debug < Level::Info && Level::Warn < Level::Error);
     }
 
     #[test]
-    fn test_log_show_info() {
-        let message = String::from("This is an info message");
-        Log::show("INFO", message.clone());
-        // You can check the output visually in the test output.
+    fn test_timestamp() {
+        assert_eq!(Log::timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!(Log::timestamp(time), "2024-02-29T12:34:56.789Z");
     }
 
     #[test]
-    fn test_log_show_error() {
-        let message = String::from("This is an error message");
-        Log::show("ERROR", message.clone());
-        // You can check the output visually in the test output.
+    fn test_render_text() {
+        let line = Log::entry(Level::Warn).party(7).player(42).render(
+            "Player left",
+            UNIX_EPOCH,
+            LogFormat::Text,
+        );
+        assert_eq!(
+            line,
+            "1970-01-01T00:00:00.000Z WARN  Player left party_id=7 player_id=42"
+        );
     }
 
     #[test]
-    fn test_log_show_other() {
-        let message = String::from("This is another message");
-        Log::show("OTHER", message.clone());
-        // You can check the output visually in the test output.
+    fn test_render_json() {
+        let line = Log::entry(Level::Info).party(7).render(
+            "Party \"7\" expired",
+            UNIX_EPOCH,
+            LogFormat::Json,
+        );
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "INFO");
+        assert_eq!(value["message"], "Party \"7\" expired");
+        assert_eq!(value["party_id"], 7);
+        assert_eq!(value["timestamp"], "1970-01-01T00:00:00.000Z");
+    }
+
+    /// Every step touches the process-wide logger, so they run in one test.
+    #[test]
+    fn test_file_sink_and_level() {
+        let _logger = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = std::env::temp_dir().join(format!("settings-log-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let file = dir.join("server.log");
+        let config = LogConfig {
+            level: Level::Warn,
+            format: LogFormat::Json,
+            file: file.to_string_lossy().to_string(),
+            max_size: 200,
+            max_files: 2,
+        };
+        Log::init(&config).unwrap();
+        assert_eq!(Log::level(), Level::Warn);
+
+        Log::info("This message is hidden");
+        for index in 0..10 {
+            Log::entry(Level::Error)
+                .player(index)
+                .message("This message is kept");
+        }
+
+        Log::init(&LogConfig::default()).unwrap();
+        let current = std::fs::read_to_string(&file).unwrap();
+        assert!(!current.contains("hidden"));
+        assert!(current.lines().count() >= 1);
+        assert!(std::fs::metadata(dir.join("server.log.1")).is_ok());
+        assert!(std::fs::metadata(dir.join("server.log.2")).is_ok());
+        assert!(std::fs::metadata(dir.join("server.log.3")).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
     }
-}
\ No newline at end of file
+}
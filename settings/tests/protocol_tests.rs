@@ -0,0 +1,137 @@
+use settings::{PlayStatus, Player, Protocol, Status};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol_round_trip() {
+        let protocol = Protocol {
+            player: Player {
+                id: 42,
+                money: 87.5,
+            },
+            party_status: Status::Started,
+            total_round: 5,
+            round: 2,
+            bet: 10,
+            party_id: 7,
+            play: PlayStatus::Cooperate,
+            message: String::new(),
+        };
+
+        let bytes = protocol.to_bytes();
+        assert_eq!(bytes.len(), Protocol::HEADER_SIZE);
+
+        let decoded = Protocol::from_bytes(&bytes);
+        assert_eq!(decoded.player.id, 42);
+        assert_eq!(decoded.player.money, 87.5);
+        assert_eq!(decoded.party_status, Status::Started);
+        assert_eq!(decoded.total_round, 5);
+        assert_eq!(decoded.round, 2);
+        assert_eq!(decoded.bet, 10);
+        assert_eq!(decoded.party_id, 7);
+        assert_eq!(decoded.play, PlayStatus::Cooperate);
+    }
+
+    #[test]
+    fn shutdown_status_round_trip() {
+        let protocol = Protocol {
+            party_status: Status::Shutdown,
+            ..Default::default()
+        };
+
+        let decoded = Protocol::from_bytes(&protocol.to_bytes());
+        assert_eq!(decoded.party_status, Status::Shutdown);
+    }
+
+    #[test]
+    fn message_round_trip() {
+        let protocol = Protocol {
+            party_status: Status::Broadcast,
+            message: String::from("Server restarts in 5 minutes"),
+            ..Default::default()
+        };
+
+        let bytes = protocol.to_bytes();
+        assert_eq!(Protocol::message_size(&bytes), protocol.message.len());
+
+        let decoded = Protocol::from_bytes(&bytes);
+        assert_eq!(decoded.party_status, Status::Broadcast);
+        assert_eq!(decoded.message, protocol.message);
+    }
+
+    #[test]
+    fn long_messages_are_cut_on_a_char_boundary() {
+        // 'é' takes 2 bytes and u16::MAX is odd
+        let protocol = Protocol {
+            message: "é".repeat(40_000),
+            ..Default::default()
+        };
+        let decoded = Protocol::decode(&protocol.to_bytes()).unwrap();
+        assert_eq!(decoded.message.len(), u16::MAX as usize - 1);
+        assert!(protocol.message.starts_with(&decoded.message));
+    }
+
+    #[test]
+    fn read_frames_from_stream() {
+        let first = Protocol {
+            party_status: Status::Broadcast,
+            message: String::from("hello"),
+            ..Default::default()
+        };
+        let second = Protocol {
+            party_status: Status::Kicked,
+            ..Default::default()
+        };
+        let mut bytes = first.to_bytes();
+        bytes.extend(second.to_bytes());
+        let mut stream = bytes.as_slice();
+
+        let decoded = Protocol::read_from(&mut stream).unwrap();
+        assert_eq!(decoded.message, "hello");
+        let decoded = Protocol::read_from(&mut stream).unwrap();
+        assert_eq!(decoded.party_status, Status::Kicked);
+        assert!(Protocol::read_from(&mut stream).is_err());
+    }
+
+    #[test]
+    fn status_bytes_round_trip() {
+        for byte in 0..=u8::MAX {
+            if let Ok(status) = Status::try_from(byte) {
+                assert_eq!(u8::from(&status), byte);
+            }
+            if let Ok(play) = PlayStatus::try_from(byte) {
+                assert_eq!(u8::from(&play), byte);
+            }
+        }
+        assert_eq!(Status::try_from(11), Ok(Status::Broadcast));
+        assert_eq!(Status::try_from(12), Err(12));
+        assert_eq!(PlayStatus::try_from(3), Err(3));
+    }
+
+    #[test]
+    fn decode_rejects_invalid_frames() {
+        let protocol = Protocol {
+            message: String::from("hi"),
+            ..Default::default()
+        };
+        let bytes = protocol.to_bytes();
+        assert_eq!(Protocol::decode(&bytes).unwrap().message, "hi");
+
+        let mut bad_status = bytes.clone();
+        bad_status[12] = 200;
+        assert!(Protocol::decode(&bad_status).is_err());
+
+        let mut bad_play = bytes.clone();
+        bad_play[29] = 7;
+        assert!(Protocol::decode(&bad_play).is_err());
+
+        let mut bad_message = bytes.clone();
+        bad_message[Protocol::HEADER_SIZE] = 0xff;
+        assert!(Protocol::decode(&bad_message).is_err());
+
+        assert!(Protocol::decode(&bytes[..Protocol::HEADER_SIZE + 1]).is_err());
+        assert!(Protocol::decode(&bytes[..4]).is_err());
+    }
+}
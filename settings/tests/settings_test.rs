@@ -1,31 +1,309 @@
-use settings::Settings;
+use settings::{
+    CommandLine, ExportFormat, Level, LoadOptions, PayoffMatrix, PlayStatus, Settings,
+    SettingsError,
+};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn find_key_get_configuration() {
-        let mut dict = HashMap::new();
-        dict.insert(String::from("KEY1"), String::from("VALUE1"));
+    const FIXTURE: &str = "./tests/fixture_settings.json";
+
+    fn no_environment() -> Option<HashMap<String, String>> {
+        Some(HashMap::new())
+    }
+
+    fn load_with(
+        file: &str,
+        environment: Option<HashMap<String, String>>,
+        overrides: &[(String, String)],
+    ) -> Result<Settings, SettingsError> {
+        Settings::load_with(LoadOptions {
+            file: Some(PathBuf::from(file)),
+            profile: None,
+            environment,
+            overrides: overrides.to_vec(),
+        })
+    }
 
-        // Get key
-        let value1 = Settings::get_configuration_value(&dict, "KEY1");
-        assert_eq!(value1, String::from("VALUE1"));
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
 
-        // No key
-        let value2 = Settings::get_configuration_value(&dict, "KEY2");
-        assert_eq!(value2, String::new());
+    /// A config home holding a settings file, unique to each test.
+    fn config_home(name: &str, contents: &str) -> PathBuf {
+        let home = std::env::temp_dir().join(format!("settings-{}-{}", name, std::process::id()));
+        let dir = home.join(settings::CONFIG_DIR);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(settings::CONFIG_FILE), contents).unwrap();
+        home
     }
 
     #[test]
     fn setting_creation() {
-        let file_name = "./tests/fixture_settings.json";
-
-        let settings = Settings::load(file_name);
+        let settings = load_with(FIXTURE, no_environment(), &[]).unwrap();
 
         assert_eq!(settings.host, "1");
-        assert_eq!(settings.port, "2");
+        assert_eq!(settings.port, 2);
+
+        // Missing keys fall back to their defaults
+        assert_eq!(settings.party_ttl, 300);
+        assert_eq!(settings.archive_size, 100);
+        assert_eq!(settings.storage_path, String::new());
+        assert_eq!(settings.http_port, 0);
+        assert_eq!(settings.default_bet, 10);
+        assert_eq!(settings.payoff, PayoffMatrix::default());
+        assert_eq!((settings.bot_seat.as_str(), settings.move_timeout), ("", 100));
+        assert_eq!(settings.move_fuel, 1_000_000);
+    }
+
+    #[test]
+    fn repository_settings_are_valid() {
+        let settings = load_with("settings.json", no_environment(), &[]).unwrap();
+        assert_eq!(settings.port, 9001);
+        assert_eq!(settings.log.level, Level::Info);
+        assert_eq!(settings.http_port, 0);
+
+        for (profile, http_port, level) in
+            [("server", 9080, Level::Info), ("client", 0, Level::Warn)]
+        {
+            let settings = Settings::load_with(LoadOptions {
+                file: Some(PathBuf::from("settings.json")),
+                profile: Some(String::from(profile)),
+                environment: no_environment(),
+                ..Default::default()
+            })
+            .unwrap();
+            assert_eq!(settings.http_port, http_port);
+            assert_eq!(settings.log.level, level);
+            assert_eq!(settings.log.max_files, 5);
+        }
+    }
+
+    #[test]
+    fn discover_the_settings_file() {
+        let home = config_home("discover", r#"{"port": 9300}"#);
+        let xdg = home.to_string_lossy().to_string();
+        let environment = variables(&[("XDG_CONFIG_HOME", &xdg)]);
+
+        let options = LoadOptions::discover(CommandLine::default(), "server", &environment);
+        let settings = Settings::load_with(options).unwrap();
+        assert_eq!(settings.port, 9300);
+
+        let environment = variables(&[("XDG_CONFIG_HOME", &xdg), ("PRISONER_CONFIG", FIXTURE)]);
+        let options = LoadOptions::discover(CommandLine::default(), "server", &environment);
+        assert_eq!(options.file, Some(PathBuf::from(FIXTURE)));
+        assert!(!options.environment.unwrap().contains_key("PRISONER_CONFIG"));
+
+        let command_line = CommandLine::parse(["--config", "settings.json"].map(String::from));
+        let options = LoadOptions::discover(command_line.unwrap(), "server", &environment);
+        assert_eq!(options.file, Some(PathBuf::from("settings.json")));
+
+        let environment = variables(&[("XDG_CONFIG_HOME", "/nonexistent")]);
+        let options = LoadOptions::discover(CommandLine::default(), "server", &environment);
+        assert_eq!(options.file, None);
+        assert_eq!(Settings::load_with(options).unwrap().port, 9001);
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn missing_settings_file_is_reported() {
+        let environment = variables(&[("PRISONER_CONFIG", "missing.json")]);
+        let options = LoadOptions::discover(CommandLine::default(), "server", &environment);
+        let error = Settings::load_with(options).unwrap_err();
+        assert!(error.to_string().contains("missing.json"));
+    }
+
+    #[test]
+    fn profile_overrides_the_file_only() {
+        let home = config_home(
+            "profile",
+            r#"{"port": 9300, "log": {"level": "WARN", "max_files": 2},
+                "profiles": {"bots": {"port": 9400, "log": {"level": "ERROR"}}}}"#,
+        );
+        let xdg = home.to_string_lossy().to_string();
+        let discover = |args: &[&str], pairs: &[(&str, &str)]| {
+            let mut environment = variables(pairs);
+            environment.insert(String::from("XDG_CONFIG_HOME"), xdg.clone());
+            let command_line = CommandLine::parse(args.iter().map(|arg| arg.to_string()));
+            let options = LoadOptions::discover(command_line.unwrap(), "client", &environment);
+            Settings::load_with(options).unwrap()
+        };
+
+        // The default profile is missing from the file
+        assert_eq!(discover(&[], &[]).port, 9300);
+
+        let settings = discover(&[], &[("PRISONER_PROFILE", "bots")]);
+        assert_eq!(settings.port, 9400);
+        assert_eq!(settings.log.level, Level::Error);
+        assert_eq!(settings.log.max_files, 2);
+
+        let settings = discover(&["--profile", "bots"], &[("PRISONER_PORT", "9500")]);
+        assert_eq!(settings.port, 9500);
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn environment_then_command_line_override_the_file() {
+        let environment = HashMap::from([
+            (String::from("PRISONER_PORT"), String::from("9100")),
+            (String::from("PRISONER_HOST"), String::from("0.0.0.0")),
+            (String::from("PRISONER_LOG__LEVEL"), String::from("debug")),
+            (
+                String::from("PRISONER_EXPORT_FORMAT"),
+                String::from("JSONL"),
+            ),
+        ]);
+        let overrides = [(String::from("port"), String::from("9200"))];
+
+        let settings = load_with(FIXTURE, Some(environment), &overrides).unwrap();
+
+        assert_eq!(settings.host, "0.0.0.0");
+        assert_eq!(settings.port, 9200);
+        assert_eq!(settings.log.level, Level::Debug);
+        assert_eq!(settings.export_format, ExportFormat::Jsonl);
+    }
+
+    #[test]
+    fn wrong_types_are_reported() {
+        let overrides = [(String::from("port"), String::from("ninety"))];
+        let error = load_with(FIXTURE, no_environment(), &overrides).unwrap_err();
+        assert!(matches!(error, SettingsError::Load(_)));
+        assert!(error.to_string().contains("port"));
+
+        let overrides = [(String::from("export_format"), String::from("pdf"))];
+        let error = load_with(FIXTURE, no_environment(), &overrides).unwrap_err();
+        assert!(error.to_string().contains("unknown export format pdf"));
+
+        let overrides = [(String::from("log.level"), String::from("LOUD"))];
+        let error = load_with(FIXTURE, no_environment(), &overrides).unwrap_err();
+        assert!(error.to_string().contains("unknown log level LOUD"));
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let overrides = [(String::from("prot"), String::from("9001"))];
+        let error = load_with(FIXTURE, no_environment(), &overrides).unwrap_err();
+        assert!(error.to_string().contains("prot"));
+    }
+
+    #[test]
+    fn every_invalid_value_is_reported() {
+        let settings = Settings {
+            host: String::new(),
+            default_bet: 0,
+            payoff: PayoffMatrix {
+                reward: 3.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let Err(SettingsError::Invalid(problems)) = settings.validate() else {
+            panic!("settings should be invalid");
+        };
+        assert_eq!(problems.len(), 3);
+        assert!(Settings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn export_file_placeholders_are_checked() {
+        for (template, valid) in [
+            ("{date}/game_{party_id}_{player1}_vs_{player2}.{ext}", true),
+            ("game_{party_id}.xlsx", true),
+            ("game.xlsx", false),
+            ("{date}_{time}.{ext}", false),
+            ("game_{id}.xlsx", false),
+            ("game_{party_id.xlsx", false),
+            (" ", false),
+        ] {
+            let settings = Settings {
+                export_file: String::from(template),
+                ..Default::default()
+            };
+            assert_eq!(settings.validate().is_ok(), valid, "{}", template);
+        }
+    }
+
+    #[test]
+    fn export_file_extension_matches_the_format() {
+        for (template, format, valid) in [
+            ("game_{party_id}.{ext}", ExportFormat::Csv, true),
+            ("game_{party_id}.csv", ExportFormat::Csv, true),
+            ("game_{party_id}.XLSX", ExportFormat::Xlsx, true),
+            ("game_{party_id}", ExportFormat::Jsonl, true),
+            ("game_{party_id}.xlsx", ExportFormat::Csv, false),
+            ("v1.0/game_{party_id}.csv", ExportFormat::Jsonl, false),
+        ] {
+            let settings = Settings {
+                export_file: String::from(template),
+                export_format: format,
+                ..Default::default()
+            };
+            assert_eq!(settings.validate().is_ok(), valid, "{}", template);
+        }
+    }
+
+    #[test]
+    fn payoff_scales_with_the_bet() {
+        let payoff = PayoffMatrix::default();
+        let (c, d) = (PlayStatus::Cooperate, PlayStatus::Betrail);
+
+        assert_eq!(payoff.payoff(&c, &c, 10), 5.0);
+        assert_eq!(payoff.payoff(&c, &d, 10), -20.0);
+        assert_eq!(payoff.payoff(&d, &c, 10), 20.0);
+        assert_eq!(payoff.payoff(&d, &d, 10), -10.0);
+        assert_eq!(payoff.payoff(&PlayStatus::Stanby, &c, 10), 0.0);
+
+        // parties pay whole money, like the original bet / 2
+        assert_eq!(payoff.payoff(&c, &c, 7), 3.5);
+        assert_eq!(payoff.money(&c, &c, 7), 3.0);
+        assert_eq!(payoff.money(&d, &c, 7), 14.0);
+        assert_eq!(payoff.money(&d, &d, 7), -7.0);
+    }
+
+    #[test]
+    fn parse_command_line() {
+        let args = [
+            "--set",
+            "port=9002",
+            "--set=log.level=WARN",
+            "--print-config",
+            "--profile",
+            "client",
+        ];
+        let command_line = CommandLine::parse(args.map(String::from)).unwrap();
+
+        assert!(command_line.print_config);
+        assert_eq!(command_line.profile.as_deref(), Some("client"));
+        assert_eq!(
+            command_line.overrides,
+            vec![
+                (String::from("port"), String::from("9002")),
+                (String::from("log.level"), String::from("WARN")),
+            ]
+        );
+        assert!(CommandLine::parse([String::from("--verbose")]).is_err());
+
+        let args = ["join", "--profile", "client", "--moves", "ccb"];
+        let (command_line, rest) = CommandLine::parse_known(args.map(String::from)).unwrap();
+        assert_eq!(command_line.profile.as_deref(), Some("client"));
+        assert_eq!(rest, ["join", "--moves", "ccb"]);
+        assert!(CommandLine::parse([String::from("--set")]).is_err());
+        assert!(CommandLine::parse([String::from("--config")]).is_err());
+        assert!(CommandLine::parse(["--set", "=1"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn printed_config_loads_back() {
+        let settings = Settings::default();
+        let printed: Settings = serde_json::from_str(&settings.to_json()).unwrap();
+        assert_eq!(printed.port, settings.port);
+        assert_eq!(printed.log.level, settings.log.level);
     }
 }
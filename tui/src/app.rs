@@ -0,0 +1,126 @@
+use client::{ClientState, Screen};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use settings::{PlayStatus, Protocol};
+
+/// Field of the create game form being edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Rounds,
+    Bet,
+}
+
+/// Create game form, kept as text while typed.
+pub struct Form {
+    pub rounds: String,
+    pub bet: String,
+    pub field: Field,
+    /// Why the last submit was refused.
+    pub error: Option<String>,
+}
+
+impl Form {
+    fn new(state: &ClientState) -> Self {
+        Self {
+            rounds: state.rounds.to_string(),
+            bet: state.bet.to_string(),
+            field: Field::Rounds,
+            error: None,
+        }
+    }
+
+    fn value(&self) -> Result<(u32, u32), String> {
+        let number = |name: &str, text: &str| {
+            text.parse::<u32>()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| format!("{} must be a positive number", name))
+        };
+        Ok((number("Rounds", &self.rounds)?, number("Bet", &self.bet)?))
+    }
+
+    fn edit(&mut self, code: KeyCode) {
+        let text = match self.field {
+            Field::Rounds => &mut self.rounds,
+            Field::Bet => &mut self.bet,
+        };
+        match code {
+            KeyCode::Char(digit) if digit.is_ascii_digit() && text.len() < 9 => text.push(digit),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                self.field = match self.field {
+                    Field::Rounds => Field::Bet,
+                    Field::Bet => Field::Rounds,
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// What only a terminal needs on top of the client state: the text of the
+/// form and whether to quit.
+pub struct App {
+    pub form: Form,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(state: &ClientState) -> Self {
+        Self {
+            form: Form::new(state),
+            quit: false,
+        }
+    }
+
+    /// Applies a key press to `state`, returning the frame to send to the
+    /// server.
+    pub fn handle_key(&mut self, state: &mut ClientState, key: KeyEvent) -> Option<Protocol> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return None;
+        }
+
+        match (state.screen().clone(), key.code) {
+            (Screen::CreateGame, KeyCode::Enter) => match self.form.value() {
+                Ok((rounds, bet)) => {
+                    self.form.error = None;
+                    state.create(rounds, bet)
+                }
+                Err(e) => {
+                    self.form.error = Some(e);
+                    None
+                }
+            },
+            (Screen::CreateGame, KeyCode::Esc) => {
+                state.back();
+                None
+            }
+            (Screen::CreateGame, code) => {
+                self.form.edit(code);
+                None
+            }
+            (Screen::Menu, KeyCode::Char('c')) => {
+                self.form = Form::new(state);
+                state.open_create();
+                None
+            }
+            (Screen::Menu, KeyCode::Char('j')) => state.join(),
+            (Screen::Game, KeyCode::Char('c')) => state.play(PlayStatus::Cooperate),
+            (Screen::Game, KeyCode::Char('b')) => state.play(PlayStatus::Betrail),
+            (Screen::Lobby, KeyCode::Esc) | (Screen::End(_), KeyCode::Esc | KeyCode::Enter) => {
+                state.back();
+                None
+            }
+            (_, KeyCode::Char('q')) => {
+                self.quit = true;
+                None
+            }
+            _ => None,
+        }
+    }
+}
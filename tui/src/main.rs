@@ -0,0 +1,72 @@
+use client::Client;
+use ratatui::crossterm::event::{self, Event};
+use settings::{Log, Settings};
+use std::io;
+use std::time::Duration;
+use tui::app::App;
+use tui::view;
+
+const USAGE: &str = "Usage: tui [options]
+
+Full-screen terminal client: create or join parties and play them with
+the keyboard, the keys available are shown at the bottom of the screen.";
+
+/// How often the screen is refreshed while no key is pressed.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Draws the client state on every tick or key press. A lost connection
+/// shows on the screen: nothing is logged while the terminal is in use.
+fn run(client: Client) -> io::Result<()> {
+    let mut app = App::new(&client.state());
+    let mut terminal = ratatui::init();
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| view::draw(frame, &app, &client.state())) {
+            break Err(e);
+        }
+
+        match event::poll(TICK) {
+            Ok(false) => continue,
+            Ok(true) => (),
+            Err(e) => break Err(e),
+        }
+        match event::read() {
+            Ok(Event::Key(key)) => {
+                // A failed send ends with the connection, as the server sees it.
+                let _ = client.update(|state| app.handle_key(state, key));
+            }
+            Ok(_) => (),
+            Err(e) => break Err(e),
+        }
+        if app.quit {
+            break Ok(());
+        }
+    };
+    ratatui::restore();
+    client.close();
+    result
+}
+
+fn main() {
+    let (settings, args) = Settings::from_args_with("client", USAGE);
+    if let Some(arg) = args.first() {
+        eprintln!("unknown option {}", arg);
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+
+    // The screen is drawn from the state, events are not needed.
+    let client = match Client::connect(&settings) {
+        Ok((client, _)) => client,
+        Err(e) => {
+            Log::error(format!(
+                "connecting to {}:{}: {}",
+                settings.host, settings.port, e
+            ));
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = run(client) {
+        Log::error(format!("terminal: {}", e));
+        std::process::exit(1);
+    }
+}
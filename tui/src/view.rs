@@ -0,0 +1,145 @@
+use crate::app::{App, Field};
+use client::{ClientState, Screen};
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use ratatui::Frame;
+use settings::Status;
+
+/// Draws the screen of `state`: a title, the view of the current screen,
+/// the last server broadcast and the keys available.
+pub fn draw(frame: &mut Frame, app: &App, state: &ClientState) {
+    let [title, body, message, help] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let player = match state.player_id() {
+        0 => String::from("connecting..."),
+        id => format!("player #{}", id),
+    };
+    frame.render_widget(
+        Line::from(format!("Prisoner's dilemma - {}", player)).bold(),
+        title,
+    );
+
+    let block = Block::default().borders(Borders::ALL);
+    let inner = block.inner(body);
+    frame.render_widget(block, body);
+    match state.screen() {
+        Screen::Menu => centered(frame, inner, vec![Line::from("Welcome to the Game !!")]),
+        Screen::CreateGame => create_game(frame, inner, app),
+        Screen::Lobby => centered(frame, inner, vec![Line::from("Searching for a game....")]),
+        // Created, nobody joined yet
+        Screen::Waiting if state.protocol().party_status != Status::Started => {
+            centered(frame, inner, vec![Line::from("Waiting for player....")])
+        }
+        Screen::Waiting => party(frame, inner, state, "Waiting for player...."),
+        Screen::Game => party(frame, inner, state, "Your move"),
+        Screen::End(end) => party(frame, inner, state, &format!("{} !!", end.text())),
+    }
+
+    frame.render_widget(
+        Line::from(state.server_message())
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::ITALIC),
+            ),
+        message,
+    );
+    frame.render_widget(Line::from(keys(state.screen())).dim(), help);
+}
+
+fn keys(screen: &Screen) -> &'static str {
+    match screen {
+        Screen::Menu => "[c] create game  [j] join game  [q] quit",
+        Screen::CreateGame => "[tab] next field  [enter] create  [esc] back",
+        Screen::Lobby => "[esc] back to menu  [q] quit",
+        Screen::Waiting => "[q] quit",
+        Screen::Game => "[c] cooperate  [b] betray  [q] quit",
+        Screen::End(_) => "[enter] back to menu  [q] quit",
+    }
+}
+
+fn centered(frame: &mut Frame, area: Rect, lines: Vec<Line>) {
+    let [_, middle, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(lines.len() as u16),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+    frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), middle);
+}
+
+fn create_game(frame: &mut Frame, area: Rect, app: &App) {
+    let form = &app.form;
+    let input = |label: &str, text: &str, field: Field| {
+        let style = if form.field == field {
+            Style::default().reversed()
+        } else {
+            Style::default()
+        };
+        Line::from(vec![
+            Span::raw(format!("{:<22}", label)),
+            Span::styled(format!(" {:<10}", text), style),
+        ])
+    };
+    let mut lines = vec![
+        Line::from("Create Game").bold(),
+        Line::default(),
+        input("Rounds for the party:", &form.rounds, Field::Rounds),
+        input("Bet per game:", &form.bet, Field::Bet),
+    ];
+    if let Some(error) = &form.error {
+        lines.push(Line::default());
+        lines.push(Line::from(error.as_str()).red());
+    }
+    centered(frame, area, lines);
+}
+
+/// Header of the current party and the rounds resolved so far.
+fn party(frame: &mut Frame, area: Rect, state: &ClientState, status: &str) {
+    let protocol = state.protocol();
+    let [header, history] =
+        Layout::vertical([Constraint::Length(4), Constraint::Min(0)]).areas(area);
+    let round = protocol.round.min(protocol.total_round);
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!(
+                "Party  #{} round: {}/{}, bet {}",
+                protocol.party_id, round, protocol.total_round, protocol.bet
+            ))
+            .bold(),
+            Line::from(format!("Player: {:.2}", protocol.player.money)),
+            Line::from(status.to_string()).italic(),
+        ])
+        .alignment(Alignment::Center),
+        header,
+    );
+
+    // Keep the last rounds when they do not all fit.
+    let shown = history.height.saturating_sub(1) as usize;
+    let skipped = state.history().len().saturating_sub(shown);
+    let rows = state.history().iter().skip(skipped).map(|played| {
+        Row::new(vec![
+            played.round.to_string(),
+            played.play.to_string().to_string(),
+            played
+                .other
+                .as_ref()
+                .map_or("?", |other| other.to_string())
+                .to_string(),
+            format!("{:+.2}", played.payout),
+            format!("{:.2}", played.balance),
+        ])
+    });
+    let table = Table::new(rows, [Constraint::Fill(1); 5])
+        .header(Row::new(["Round", "You", "Other", "Payout", "Balance"]).bold());
+    frame.render_widget(table, history);
+}
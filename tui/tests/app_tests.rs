@@ -0,0 +1,111 @@
+use client::{ClientState, Screen};
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Terminal;
+use settings::{PlayStatus, Player, Protocol, Settings, Status};
+use tui::app::{App, Field};
+use tui::view;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(status: Status, round: u32, money: f64) -> Protocol {
+        Protocol {
+            player: Player { id: 42, money },
+            party_status: status,
+            total_round: 3,
+            round,
+            bet: 10,
+            party_id: 7,
+            ..Default::default()
+        }
+    }
+
+    fn press(app: &mut App, state: &mut ClientState, code: KeyCode) -> Option<Protocol> {
+        app.handle_key(state, KeyEvent::from(code))
+    }
+
+    fn render(app: &App, state: &ClientState) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
+        terminal
+            .draw(|frame| view::draw(frame, app, state))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|line| line.iter().map(|cell| cell.symbol()).collect::<String>() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn create_a_party_from_the_form() {
+        let mut state = ClientState::new(&Settings::default());
+        let mut app = App::new(&state);
+        state.receive(frame(Status::Init, 0, 100.0));
+        let screen = render(&app, &state);
+        assert!(screen.contains("player #42"));
+        assert!(screen.contains("Welcome to the Game !!"));
+        assert!(screen.contains("[c] create game"));
+
+        assert!(press(&mut app, &mut state, KeyCode::Char('c')).is_none());
+        assert_eq!(state.screen(), &Screen::CreateGame);
+        for code in [KeyCode::Backspace; 9] {
+            press(&mut app, &mut state, code);
+        }
+        assert!(press(&mut app, &mut state, KeyCode::Enter).is_none());
+        assert!(render(&app, &state).contains("Rounds must be a positive number"));
+
+        press(&mut app, &mut state, KeyCode::Char('3'));
+        press(&mut app, &mut state, KeyCode::Char('x'));
+        press(&mut app, &mut state, KeyCode::Tab);
+        assert_eq!(app.form.field, Field::Bet);
+        app.form.bet.clear();
+        press(&mut app, &mut state, KeyCode::Char('1'));
+        press(&mut app, &mut state, KeyCode::Char('0'));
+        let created = press(&mut app, &mut state, KeyCode::Enter).unwrap();
+        assert_eq!(created.party_status, Status::Created);
+        assert_eq!((created.total_round, created.bet), (3, 10));
+        assert!(render(&app, &state).contains("Waiting for player...."));
+    }
+
+    #[test]
+    fn play_rounds_with_history() {
+        let mut state = ClientState::new(&Settings::default());
+        let mut app = App::new(&state);
+        state.receive(frame(Status::Init, 0, 100.0));
+        assert_eq!(
+            press(&mut app, &mut state, KeyCode::Char('j'))
+                .unwrap()
+                .party_status,
+            Status::JoinParty
+        );
+        assert!(render(&app, &state).contains("Searching for a game...."));
+
+        state.receive(frame(Status::Started, 1, 100.0));
+        assert!(render(&app, &state).contains("Party  #7 round: 1/3, bet 10"));
+        let play = press(&mut app, &mut state, KeyCode::Char('b')).unwrap();
+        assert_eq!(play.play, PlayStatus::Betrail);
+        assert!(press(&mut app, &mut state, KeyCode::Char('c')).is_none());
+
+        state.receive(frame(Status::Started, 2, 120.0));
+        let screen = render(&app, &state);
+        assert!(screen.contains("Your move"));
+        assert!(screen.contains("Betrayal"));
+        assert!(screen.contains("+20.00"));
+
+        let mut notice = frame(Status::Broadcast, 0, 0.0);
+        notice.message = String::from("maintenance at noon");
+        state.receive(notice);
+        state.receive(frame(Status::Lose, 2, 120.0));
+        let screen = render(&app, &state);
+        assert!(screen.contains("Lose !!"));
+        assert!(screen.contains("maintenance at noon"));
+
+        press(&mut app, &mut state, KeyCode::Enter);
+        assert_eq!(state.screen(), &Screen::Menu);
+        press(&mut app, &mut state, KeyCode::Char('q'));
+        assert!(app.quit);
+    }
+}